@@ -492,8 +492,15 @@ pub mod common {
                 .bitfield_enum("event_class_flag_t")
                 .bitfield_enum("micro_checkpoint_flags_t")
                 .bitfield_enum("access_t")
+                // Attribute kinds are combined with flags such as `Sim_Attr_Persistent`
+                .bitfield_enum("attr_attr_t")
                 .bitfield_enum("breakpoint_flag")
                 .bitfield_enum("save_flags_t")
+                .bitfield_enum("transaction_flags_t")
+                // Atom ids are allocated at runtime for non-core atom types
+                .newtype_enum("atom_id_t")
+                // Notifier types are allocated at runtime for non-core notifiers
+                .newtype_enum("notifier_type_t")
                 // Blocklisted because use 128-bit types which are not FFI-safe
                 .blocklist_function("__acoshl")
                 .blocklist_function("acoshl")
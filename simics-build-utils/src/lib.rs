@@ -11,6 +11,10 @@ use std::{
 };
 use versions;
 
+mod register_map;
+
+pub use register_map::*;
+
 /// Get the only subdirectory of a directory, if only one exists. If zero or more than one subdirectories
 /// exist, returns an error
 pub fn subdir<P>(dir: P) -> Result<PathBuf>
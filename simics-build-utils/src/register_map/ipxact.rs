@@ -0,0 +1,390 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reading of IP-XACT (IEEE 1685-2009 and 1685-2014) component descriptions. Each address
+//! block is read as a bank.
+
+use super::{
+    parse_int, xml::Element, BankDescription, FieldAccess, FieldDescription, RegisterDescription,
+    RegisterMap,
+};
+use anyhow::{anyhow, bail, ensure, Result};
+
+/// Get the access type of a field from its IP-XACT access, modified write value and read
+/// action
+fn field_access(
+    access: Option<&str>,
+    modified_write_value: Option<&str>,
+    read_action: Option<&str>,
+) -> FieldAccess {
+    match (access, modified_write_value, read_action) {
+        (_, _, Some("clear")) => FieldAccess::ReadToClear,
+        (Some("read-only"), _, _) => FieldAccess::ReadOnly,
+        (_, Some("oneToClear"), _) => FieldAccess::WriteOneToClear,
+        (_, Some("oneToSet"), _) => FieldAccess::WriteOneToSet,
+        (Some("write-only" | "writeOnce"), _, _) => FieldAccess::WriteOnly,
+        _ => FieldAccess::ReadWrite,
+    }
+}
+
+/// Get the reset value of an element, either from a 1685-2009 `reset` element or the first
+/// 1685-2014 `resets` element
+fn reset_value(element: &Element) -> Result<Option<u64>> {
+    element
+        .child("reset")
+        .or_else(|| element.child("resets").and_then(|r| r.child("reset")))
+        .and_then(|r| r.child_text("value"))
+        .map(parse_int)
+        .transpose()
+}
+
+/// Get the number of instances of an element with `dim` elements
+fn dim_count(element: &Element) -> Result<u64> {
+    element
+        .children("dim")
+        .map(|d| parse_int(&d.text))
+        .try_fold(1, |count, dim| Ok(count * dim?))
+}
+
+/// Read the registers in an `addressBlock` or `registerFile` element, at `base` in the block
+fn registers(
+    element: &Element,
+    base: u64,
+    prefix: &str,
+    access: Option<&str>,
+    registers_out: &mut Vec<RegisterDescription>,
+) -> Result<()> {
+    for child in &element.children {
+        if child.name != "register" && child.name != "registerFile" {
+            continue;
+        }
+
+        let name = child
+            .child_text("name")
+            .ok_or_else(|| anyhow!("{} has no name", child.name))?;
+        let offset = child
+            .child_text("addressOffset")
+            .map(parse_int)
+            .transpose()?
+            .ok_or_else(|| anyhow!("{name} has no addressOffset"))?;
+        let access = child.child_text("access").or(access);
+        let count = dim_count(child)?;
+
+        if child.name == "registerFile" {
+            let range = child
+                .child_text("range")
+                .map(parse_int)
+                .transpose()?
+                .ok_or_else(|| anyhow!("Register file {name} has no range"))?;
+
+            for i in 0..count {
+                let name = if count > 1 {
+                    format!("{prefix}{name}{i}_")
+                } else {
+                    format!("{prefix}{name}_")
+                };
+                registers(
+                    child,
+                    base + offset + i * range,
+                    &name,
+                    access,
+                    registers_out,
+                )?;
+            }
+
+            continue;
+        }
+
+        let bits = child
+            .child_text("size")
+            .map(parse_int)
+            .transpose()?
+            .ok_or_else(|| anyhow!("Register {name} has no size"))?;
+        ensure!(
+            bits % 8 == 0 && (8..=64).contains(&bits),
+            "Register {name} has an unsupported size of {bits} bits"
+        );
+        let size = bits / 8;
+
+        let mut reset = reset_value(child)?.unwrap_or(0);
+        let mut fields = Vec::new();
+
+        for field in child.children("field") {
+            let field_name = field
+                .child_text("name")
+                .ok_or_else(|| anyhow!("Field of register {name} has no name"))?;
+            let lsb = field
+                .child_text("bitOffset")
+                .map(parse_int)
+                .transpose()?
+                .ok_or_else(|| anyhow!("Field {field_name} has no bitOffset"))?;
+            let width = field
+                .child_text("bitWidth")
+                .map(parse_int)
+                .transpose()?
+                .ok_or_else(|| anyhow!("Field {field_name} has no bitWidth"))?;
+            ensure!(width > 0, "Field {field_name} has a width of 0");
+            let msb = lsb.saturating_add(width - 1);
+            ensure!(
+                msb < bits,
+                "Field {field_name} of register {name} does not fit in {bits} bits"
+            );
+
+            // 1685-2014 only specifies reset values on fields
+            if let Some(value) = reset_value(field)? {
+                let mask = u64::MAX >> (64 - width.min(64));
+                reset = (reset & !(mask << lsb)) | ((value & mask) << lsb);
+            }
+
+            fields.push(FieldDescription {
+                name: field_name.to_string(),
+                description: field
+                    .child_text("description")
+                    .unwrap_or_default()
+                    .to_string(),
+                lsb: lsb.try_into()?,
+                msb: msb.try_into()?,
+                access: field_access(
+                    field.child_text("access").or(access),
+                    field.child_text("modifiedWriteValue"),
+                    field.child_text("readAction"),
+                ),
+            });
+        }
+
+        let register_access = field_access(access, None, None);
+        if fields.is_empty() && register_access != FieldAccess::ReadWrite {
+            fields.push(FieldDescription {
+                name: "value".to_string(),
+                description: String::new(),
+                lsb: 0,
+                msb: bits as u32 - 1,
+                access: register_access,
+            });
+        }
+
+        for i in 0..count {
+            registers_out.push(RegisterDescription {
+                name: if count > 1 {
+                    format!("{prefix}{name}{i}")
+                } else {
+                    format!("{prefix}{name}")
+                },
+                description: child
+                    .child_text("description")
+                    .unwrap_or_default()
+                    .to_string(),
+                offset: base + offset + i * size,
+                size,
+                reset: reset & (u64::MAX >> (64 - bits)),
+                fields: fields.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the address blocks of a memory map, including those nested in banks
+fn address_blocks<'a>(element: &'a Element, blocks: &mut Vec<&'a Element>) {
+    for child in &element.children {
+        match child.name.as_str() {
+            "addressBlock" => blocks.push(child),
+            "bank" => address_blocks(child, blocks),
+            _ => {}
+        }
+    }
+}
+
+/// Read an IP-XACT component description
+pub(crate) fn parse(component: &Element) -> Result<RegisterMap> {
+    if component.name != "component" {
+        bail!(
+            "Expected an IP-XACT <component> element, found <{}>",
+            component.name
+        );
+    }
+
+    let mut blocks = Vec::new();
+
+    component
+        .child("memoryMaps")
+        .ok_or_else(|| anyhow!("Component has no memory maps"))?
+        .children("memoryMap")
+        .for_each(|m| address_blocks(m, &mut blocks));
+
+    let mut banks = Vec::new();
+
+    for block in blocks {
+        let name = block
+            .child_text("name")
+            .ok_or_else(|| anyhow!("Address block has no name"))?;
+
+        let mut bank_registers = Vec::new();
+        registers(
+            block,
+            0,
+            "",
+            block.child_text("access"),
+            &mut bank_registers,
+        )?;
+        bank_registers.sort_by_key(|r| r.offset);
+
+        if bank_registers.is_empty() {
+            continue;
+        }
+
+        banks.push(BankDescription {
+            name: name.to_string(),
+            description: block
+                .child_text("description")
+                .unwrap_or_default()
+                .to_string(),
+            registers: bank_registers,
+        });
+    }
+
+    Ok(RegisterMap {
+        banks,
+        skipped: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ipxact_2009() {
+        let map = RegisterMap::from_ipxact(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<spirit:component xmlns:spirit="http://www.spiritconsortium.org/XMLSchema/SPIRIT/1685-2009">
+  <spirit:vendor>example.com</spirit:vendor>
+  <spirit:name>timer</spirit:name>
+  <spirit:memoryMaps>
+    <spirit:memoryMap>
+      <spirit:name>map</spirit:name>
+      <spirit:addressBlock>
+        <spirit:name>regs</spirit:name>
+        <spirit:baseAddress>0</spirit:baseAddress>
+        <spirit:range>4096</spirit:range>
+        <spirit:width>32</spirit:width>
+        <spirit:register>
+          <spirit:name>ctrl</spirit:name>
+          <spirit:addressOffset>0x4</spirit:addressOffset>
+          <spirit:size>32</spirit:size>
+          <spirit:access>read-write</spirit:access>
+          <spirit:reset>
+            <spirit:value>0x10</spirit:value>
+          </spirit:reset>
+          <spirit:field>
+            <spirit:name>irq</spirit:name>
+            <spirit:bitOffset>4</spirit:bitOffset>
+            <spirit:bitWidth>1</spirit:bitWidth>
+            <spirit:modifiedWriteValue>oneToClear</spirit:modifiedWriteValue>
+          </spirit:field>
+        </spirit:register>
+        <spirit:register>
+          <spirit:name>id</spirit:name>
+          <spirit:addressOffset>0x0</spirit:addressOffset>
+          <spirit:size>32</spirit:size>
+          <spirit:access>read-only</spirit:access>
+        </spirit:register>
+      </spirit:addressBlock>
+    </spirit:memoryMap>
+  </spirit:memoryMaps>
+</spirit:component>"#,
+        )
+        .unwrap();
+
+        assert_eq!(map.banks.len(), 1);
+        let regs = &map.banks[0].registers;
+        assert_eq!(regs[0].name, "id");
+        assert_eq!(regs[0].fields[0].access, FieldAccess::ReadOnly);
+        assert_eq!(regs[1].name, "ctrl");
+        assert_eq!(regs[1].reset, 0x10);
+        assert_eq!(regs[1].fields[0].access, FieldAccess::WriteOneToClear);
+    }
+
+    #[test]
+    fn test_parse_ipxact_2014() {
+        let map = RegisterMap::from_ipxact(
+            r#"<ipxact:component xmlns:ipxact="http://www.accellera.org/XMLSchema/IPXACT/1685-2014">
+  <ipxact:memoryMaps>
+    <ipxact:memoryMap>
+      <ipxact:name>map</ipxact:name>
+      <ipxact:addressBlock>
+        <ipxact:name>regs</ipxact:name>
+        <ipxact:baseAddress>'h0</ipxact:baseAddress>
+        <ipxact:range>'h100</ipxact:range>
+        <ipxact:width>32</ipxact:width>
+        <ipxact:register>
+          <ipxact:name>data</ipxact:name>
+          <ipxact:dim>2</ipxact:dim>
+          <ipxact:addressOffset>'h8</ipxact:addressOffset>
+          <ipxact:size>16</ipxact:size>
+          <ipxact:field>
+            <ipxact:name>low</ipxact:name>
+            <ipxact:bitOffset>0</ipxact:bitOffset>
+            <ipxact:resets>
+              <ipxact:reset>
+                <ipxact:value>'h5</ipxact:value>
+              </ipxact:reset>
+            </ipxact:resets>
+            <ipxact:bitWidth>8</ipxact:bitWidth>
+            <ipxact:readAction>clear</ipxact:readAction>
+          </ipxact:field>
+        </ipxact:register>
+      </ipxact:addressBlock>
+    </ipxact:memoryMap>
+  </ipxact:memoryMaps>
+</ipxact:component>"#,
+        )
+        .unwrap();
+
+        let regs = &map.banks[0].registers;
+        assert_eq!(
+            regs.iter()
+                .map(|r| (r.name.as_str(), r.offset, r.size, r.reset))
+                .collect::<Vec<_>>(),
+            vec![("data0", 8, 2, 5), ("data1", 10, 2, 5)]
+        );
+        assert_eq!(regs[0].fields[0].access, FieldAccess::ReadToClear);
+    }
+
+    #[test]
+    fn test_parse_ipxact_field_out_of_range() {
+        let result = RegisterMap::from_ipxact(
+            r#"<ipxact:component xmlns:ipxact="http://www.accellera.org/XMLSchema/IPXACT/1685-2014">
+  <ipxact:memoryMaps>
+    <ipxact:memoryMap>
+      <ipxact:name>map</ipxact:name>
+      <ipxact:addressBlock>
+        <ipxact:name>regs</ipxact:name>
+        <ipxact:baseAddress>'h0</ipxact:baseAddress>
+        <ipxact:range>'h100</ipxact:range>
+        <ipxact:width>64</ipxact:width>
+        <ipxact:register>
+          <ipxact:name>wide</ipxact:name>
+          <ipxact:addressOffset>'h0</ipxact:addressOffset>
+          <ipxact:size>64</ipxact:size>
+          <ipxact:field>
+            <ipxact:name>high</ipxact:name>
+            <ipxact:bitOffset>64</ipxact:bitOffset>
+            <ipxact:resets>
+              <ipxact:reset>
+                <ipxact:value>'h1</ipxact:value>
+              </ipxact:reset>
+            </ipxact:resets>
+            <ipxact:bitWidth>4</ipxact:bitWidth>
+          </ipxact:field>
+        </ipxact:register>
+      </ipxact:addressBlock>
+    </ipxact:memoryMap>
+  </ipxact:memoryMaps>
+</ipxact:component>"#,
+        );
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,574 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Generation of register bank models from CMSIS-SVD and IP-XACT register descriptions
+//!
+//! A register description is read into a [`RegisterMap`], which is rendered as Rust source
+//! declaring one `#[class]` and `#[bank]` struct per peripheral (SVD) or address block
+//! (IP-XACT). The generated source is typically written to `OUT_DIR` from a build script:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! fn main() -> anyhow::Result<()> {
+//!     simics_build_utils::generate_register_banks("regs/uart.svd", "uart_regs.rs")
+//! }
+//!
+//! // lib.rs
+//! mod regs {
+//!     include!(concat!(env!("OUT_DIR"), "/uart_regs.rs"));
+//! }
+//! ```
+
+mod ipxact;
+mod svd;
+mod xml;
+
+use anyhow::{anyhow, bail, Result};
+use std::{
+    collections::HashMap,
+    env::var,
+    fmt::Write,
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How software may access the bits of a register field
+pub enum FieldAccess {
+    /// Readable and writable
+    ReadWrite,
+    /// Readable, writes are ignored
+    ReadOnly,
+    /// Writable, reads return zero
+    WriteOnly,
+    /// Readable, writing a one clears the bit
+    WriteOneToClear,
+    /// Readable, writing a one sets the bit
+    WriteOneToSet,
+    /// Readable, reading clears the field and writes are ignored
+    ReadToClear,
+}
+
+impl FieldAccess {
+    /// The name of the access type in the `#[field(access = "...")]` attribute
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldAccess::ReadWrite => "rw",
+            FieldAccess::ReadOnly => "ro",
+            FieldAccess::WriteOnly => "wo",
+            FieldAccess::WriteOneToClear => "rw1c",
+            FieldAccess::WriteOneToSet => "rw1s",
+            FieldAccess::ReadToClear => "rc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A field of a register
+pub struct FieldDescription {
+    /// The name of the field
+    pub name: String,
+    /// The description of the field
+    pub description: String,
+    /// The least significant bit of the field
+    pub lsb: u32,
+    /// The most significant bit of the field
+    pub msb: u32,
+    /// How the field may be accessed
+    pub access: FieldAccess,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A register in a bank
+pub struct RegisterDescription {
+    /// The name of the register
+    pub name: String,
+    /// The description of the register
+    pub description: String,
+    /// The offset of the register in its bank
+    pub offset: u64,
+    /// The size of the register in bytes
+    pub size: u64,
+    /// The value of the register after reset
+    pub reset: u64,
+    /// The fields of the register
+    pub fields: Vec<FieldDescription>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A bank of registers, generated as one class
+pub struct BankDescription {
+    /// The name of the bank, used as the class name
+    pub name: String,
+    /// The description of the bank
+    pub description: String,
+    /// The registers of the bank, ordered by offset
+    pub registers: Vec<RegisterDescription>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The register banks read from a register description
+pub struct RegisterMap {
+    /// The banks of the register map
+    pub banks: Vec<BankDescription>,
+    /// The registers of the description which cannot be represented by a bank and were
+    /// skipped, with the reason they were skipped
+    pub skipped: Vec<String>,
+}
+
+/// Parse an integer in decimal, `0x` hexadecimal, or SVD `#` binary notation. Don't care
+/// bits (`x`) in binary values are read as zero.
+pub(crate) fn parse_int(text: &str) -> Result<u64> {
+    let text = text.trim();
+
+    let value = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(&hex.replace('_', ""), 16)
+    } else if let Some(bin) = text
+        .strip_prefix('#')
+        .or_else(|| text.strip_prefix("0b"))
+        .or_else(|| text.strip_prefix("0B"))
+    {
+        u64::from_str_radix(&bin.replace(['x', 'X'], "0"), 2)
+    } else if let Some((_, hex)) = text.split_once("'h") {
+        // Verilog style values are used by some IP-XACT generators
+        u64::from_str_radix(&hex.replace('_', ""), 16)
+    } else {
+        text.parse()
+    };
+
+    value.map_err(|e| anyhow!("Invalid integer {text:?}: {e}"))
+}
+
+/// Rust keywords which cannot be used as identifiers without escaping
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+    "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+    "use", "virtual", "where", "while", "yield",
+];
+
+/// Convert a name to a snake case identifier
+fn snake_case(name: &str) -> String {
+    let mut ident = String::with_capacity(name.len());
+    let mut previous: Option<char> = None;
+
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase()
+                && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+            {
+                ident.push('_');
+            }
+            ident.push(c.to_ascii_lowercase());
+        } else if !ident.is_empty() && !ident.ends_with('_') {
+            ident.push('_');
+        }
+        previous = Some(c);
+    }
+
+    let mut ident = ident.trim_end_matches('_').to_string();
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+
+    if KEYWORDS.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+
+    ident
+}
+
+/// Convert a name to an upper camel case identifier
+fn camel_case(name: &str) -> String {
+    let ident = snake_case(name)
+        .split('_')
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect::<String>();
+
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{ident}")
+    } else {
+        ident
+    }
+}
+
+/// Fields the `#[bank]` and `#[class]` macros add to the struct of a bank, which registers
+/// must not be named after
+const RESERVED_FIELDS: &[&str] = &["conf_object", "bank_instrumentation"];
+
+/// Record that `name` generates the identifier `ident`, failing with `what` if another name
+/// already generated it
+fn claim_ident(
+    seen: &mut HashMap<String, String>,
+    ident: String,
+    name: String,
+    what: &str,
+) -> Result<()> {
+    if let Some(other) = seen.insert(ident.clone(), name.clone()) {
+        bail!("{what} {other} and {name} both generate the identifier {ident}");
+    }
+
+    Ok(())
+}
+
+/// Join the lines of a description for use in a doc comment or class description. Quotes and
+/// backslashes are replaced, as class descriptions are emitted as C string literals.
+fn describe(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(['"', '\\'], "'")
+}
+
+impl RegisterMap {
+    /// Read a CMSIS-SVD device description
+    pub fn from_svd(text: &str) -> Result<Self> {
+        svd::parse(&xml::parse(text)?)
+    }
+
+    /// Read an IP-XACT (IEEE 1685-2009 or 1685-2014) component description
+    pub fn from_ipxact(text: &str) -> Result<Self> {
+        ipxact::parse(&xml::parse(text)?)
+    }
+
+    /// Read a register description, detecting whether it is a CMSIS-SVD or IP-XACT
+    /// description from its root element
+    pub fn from_file<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let root = xml::parse(&read_to_string(path)?)
+            .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+
+        match root.name.as_str() {
+            "device" => svd::parse(&root),
+            "component" => ipxact::parse(&root),
+            other => bail!(
+                "Unknown register description root element <{other}> in {}",
+                path.display()
+            ),
+        }
+    }
+
+    /// Check that the names of the banks, registers and fields are distinct once converted to
+    /// identifiers, and that no register is named after a field added by `#[bank]`, so the
+    /// generated source compiles
+    fn check_identifiers(&self) -> Result<()> {
+        let mut classes = HashMap::new();
+        let mut structs = HashMap::new();
+
+        for bank in &self.banks {
+            claim_ident(
+                &mut classes,
+                snake_case(&bank.name),
+                bank.name.clone(),
+                "Banks",
+            )?;
+            claim_ident(
+                &mut structs,
+                camel_case(&bank.name),
+                bank.name.clone(),
+                "Banks",
+            )?;
+
+            let mut registers = HashMap::new();
+            let mut accessors = HashMap::new();
+
+            for register in &bank.registers {
+                let ident = snake_case(&register.name);
+
+                if RESERVED_FIELDS.contains(&ident.as_str()) {
+                    bail!(
+                        "Register {} in {} generates the identifier {ident}, which is reserved \
+                         by #[bank]",
+                        register.name,
+                        bank.name
+                    );
+                }
+
+                if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    bail!(
+                        "Register {} in {} must start with a letter to be used as an attribute \
+                         name",
+                        register.name,
+                        bank.name
+                    );
+                }
+
+                claim_ident(
+                    &mut registers,
+                    ident.clone(),
+                    register.name.clone(),
+                    &format!("Registers in {}:", bank.name),
+                )?;
+
+                let mut fields = HashMap::new();
+
+                for field in &register.fields {
+                    let field_ident = snake_case(&field.name);
+
+                    claim_ident(
+                        &mut fields,
+                        field_ident.clone(),
+                        field.name.clone(),
+                        &format!("Fields of register {} in {}:", register.name, bank.name),
+                    )?;
+                    claim_ident(
+                        &mut accessors,
+                        format!("{ident}_{field_ident}"),
+                        format!("{}.{}", register.name, field.name),
+                        &format!("Fields in {}:", bank.name),
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the register map as Rust source, declaring a class with a register bank for
+    /// each bank. The source imports the macros it uses, so it should be included into a
+    /// module of its own.
+    pub fn to_rust(&self) -> Result<String> {
+        self.check_identifiers()?;
+
+        let mut source = String::new();
+
+        writeln!(source, "// Generated by simics-build-utils. Do not edit.")?;
+        writeln!(source)?;
+        writeln!(source, "#[allow(unused_imports)]")?;
+        writeln!(source, "use simics::{{bank, class, FromConfObject}};")?;
+
+        for bank in &self.banks {
+            let class_name = snake_case(&bank.name);
+            let description = if bank.description.is_empty() {
+                describe(&bank.name)
+            } else {
+                describe(&bank.description)
+            };
+
+            writeln!(source)?;
+            writeln!(source, "/// {description}")?;
+            writeln!(
+                source,
+                "#[class(name = \"{class_name}\", description = \"{description}\")]"
+            )?;
+            writeln!(source, "#[bank]")?;
+            writeln!(source, "#[derive(FromConfObject)]")?;
+            writeln!(source, "pub struct {} {{", camel_case(&bank.name))?;
+
+            for register in &bank.registers {
+                let ty = match register.size {
+                    1 => "u8",
+                    2 => "u16",
+                    3 | 4 => "u32",
+                    5..=8 => "u64",
+                    size => bail!(
+                        "Register {} of {size} bytes in {} is not between 1 and 8 bytes",
+                        register.name,
+                        bank.name
+                    ),
+                };
+
+                if !register.description.is_empty() {
+                    writeln!(source, "    /// {}", describe(&register.description))?;
+                }
+
+                writeln!(
+                    source,
+                    "    #[register(offset = {:#x}, size = {}, reset = {:#x})]",
+                    register.offset, register.size, register.reset
+                )?;
+
+                for field in &register.fields {
+                    writeln!(
+                        source,
+                        "    #[field(name = \"{}\", bits = {}..={}, access = \"{}\")]",
+                        snake_case(&field.name),
+                        field.lsb,
+                        field.msb,
+                        field.access.as_str()
+                    )?;
+                }
+
+                writeln!(source, "    pub {}: {ty},", snake_case(&register.name))?;
+            }
+
+            writeln!(source, "}}")?;
+        }
+
+        Ok(source)
+    }
+}
+
+/// Generate register bank models from a CMSIS-SVD or IP-XACT register description. This
+/// function should be called from a build script. The Rust source is written to the file
+/// `output` in `OUT_DIR`, and the build script is rerun when the description changes. A
+/// warning is emitted for each register which was skipped.
+pub fn generate_register_banks<P, Q>(input: P, output: Q) -> Result<PathBuf>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let input = input.as_ref();
+
+    println!("cargo:rerun-if-changed={}", input.display());
+
+    let output = PathBuf::from(var("OUT_DIR")?).join(output);
+
+    let map = RegisterMap::from_file(input)?;
+
+    for skipped in &map.skipped {
+        println!("cargo:warning=Skipping {skipped}");
+    }
+
+    write(&output, map.to_rust()?)?;
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_int() {
+        assert_eq!(parse_int("42").unwrap(), 42);
+        assert_eq!(parse_int(" 0x1F ").unwrap(), 0x1f);
+        assert_eq!(parse_int("#10x1").unwrap(), 0b1001);
+        assert_eq!(parse_int("32'h0000_00ff").unwrap(), 0xff);
+        assert!(parse_int("zero").is_err());
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(snake_case("CTRL_REG"), "ctrl_reg");
+        assert_eq!(snake_case("IntStatus"), "int_status");
+        assert_eq!(snake_case("type"), "type_");
+        assert_eq!(snake_case("0BIT"), "_0_bit");
+        assert_eq!(snake_case("DATA[3]"), "data_3");
+        assert_eq!(camel_case("uart0"), "Uart0");
+        assert_eq!(camel_case("GPIO_A"), "GpioA");
+    }
+
+    #[test]
+    fn test_to_rust() {
+        let map = RegisterMap {
+            skipped: Vec::new(),
+            banks: vec![BankDescription {
+                name: "UART0".to_string(),
+                description: "The first \"UART\"".to_string(),
+                registers: vec![RegisterDescription {
+                    name: "CTRL".to_string(),
+                    description: "Control\n    register".to_string(),
+                    offset: 0x10,
+                    size: 4,
+                    reset: 1,
+                    fields: vec![FieldDescription {
+                        name: "ERR".to_string(),
+                        description: String::new(),
+                        lsb: 3,
+                        msb: 5,
+                        access: FieldAccess::WriteOneToClear,
+                    }],
+                }],
+            }],
+        };
+
+        let source = map.to_rust().unwrap();
+
+        assert!(source.contains("/// The first 'UART'\n"));
+        assert!(source.contains("#[class(name = \"uart0\", description = \"The first 'UART'\")]"));
+        assert!(source.contains("pub struct Uart0 {"));
+        assert!(source.contains("    /// Control register\n"));
+        assert!(source.contains("#[register(offset = 0x10, size = 4, reset = 0x1)]"));
+        assert!(source.contains("#[field(name = \"err\", bits = 3..=5, access = \"rw1c\")]"));
+        assert!(source.contains("pub ctrl: u32,"));
+    }
+
+    fn register(name: &str, offset: u64, fields: &[&str]) -> RegisterDescription {
+        RegisterDescription {
+            name: name.to_string(),
+            description: String::new(),
+            offset,
+            size: 4,
+            reset: 0,
+            fields: fields
+                .iter()
+                .enumerate()
+                .map(|(bit, name)| FieldDescription {
+                    name: name.to_string(),
+                    description: String::new(),
+                    lsb: bit as u32,
+                    msb: bit as u32,
+                    access: FieldAccess::ReadWrite,
+                })
+                .collect(),
+        }
+    }
+
+    fn map(banks: &[(&str, Vec<RegisterDescription>)]) -> RegisterMap {
+        RegisterMap {
+            banks: banks
+                .iter()
+                .map(|(name, registers)| BankDescription {
+                    name: name.to_string(),
+                    description: String::new(),
+                    registers: registers.clone(),
+                })
+                .collect(),
+            skipped: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_to_rust_identifier_collisions() {
+        let error = |map: RegisterMap| map.to_rust().unwrap_err().to_string();
+
+        assert!(map(&[("UART", vec![register("CTRL", 0, &["EN", "ERR"])])])
+            .to_rust()
+            .is_ok());
+        assert_eq!(
+            error(map(&[("UART", vec![]), ("Uart", vec![])])),
+            "Banks UART and Uart both generate the identifier uart"
+        );
+        assert_eq!(
+            error(map(&[(
+                "UART",
+                vec![register("CTRL", 0, &[]), register("Ctrl", 4, &[])]
+            )])),
+            "Registers in UART: CTRL and Ctrl both generate the identifier ctrl"
+        );
+        assert!(error(map(&[(
+            "UART",
+            vec![register("BANK_INSTRUMENTATION", 0, &[])]
+        )]))
+        .contains("reserved"));
+        assert!(error(map(&[("UART", vec![register("0CTRL", 0, &[])])]))
+            .contains("must start with a letter"));
+        assert_eq!(
+            error(map(&[("UART", vec![register("CTRL", 0, &["EN", "En"])])])),
+            "Fields of register CTRL in UART: EN and En both generate the identifier en"
+        );
+        assert_eq!(
+            error(map(&[(
+                "UART",
+                vec![
+                    register("CTRL", 0, &["EN_A"]),
+                    register("CTRL_EN", 4, &["A"])
+                ]
+            )])),
+            "Fields in UART: CTRL.EN_A and CTRL_EN.A both generate the identifier ctrl_en_a"
+        );
+    }
+}
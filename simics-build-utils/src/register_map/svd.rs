@@ -0,0 +1,454 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reading of CMSIS-SVD device descriptions. Each peripheral is read as a bank.
+
+use super::{
+    parse_int, xml::Element, BankDescription, FieldAccess, FieldDescription, RegisterDescription,
+    RegisterMap,
+};
+use anyhow::{anyhow, bail, ensure, Result};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+/// Register properties which are inherited from the device, peripheral, and clusters by the
+/// registers they contain
+struct Properties {
+    size: Option<u64>,
+    access: Option<String>,
+    reset: Option<u64>,
+}
+
+impl Properties {
+    /// Override the inherited properties with those set on `element`
+    fn inherit(&self, element: &Element) -> Result<Self> {
+        Ok(Self {
+            size: element
+                .child_text("size")
+                .map(parse_int)
+                .transpose()?
+                .or(self.size),
+            access: element
+                .child_text("access")
+                .map(str::to_string)
+                .or_else(|| self.access.clone()),
+            reset: element
+                .child_text("resetValue")
+                .map(parse_int)
+                .transpose()?
+                .or(self.reset),
+        })
+    }
+}
+
+/// Get the access type of a field from its SVD access, modified write values and read action
+fn field_access(
+    access: Option<&str>,
+    modified_write_values: Option<&str>,
+    read_action: Option<&str>,
+) -> FieldAccess {
+    match (access, modified_write_values, read_action) {
+        (_, _, Some("clear")) => FieldAccess::ReadToClear,
+        (Some("read-only"), _, _) => FieldAccess::ReadOnly,
+        (_, Some("oneToClear"), _) => FieldAccess::WriteOneToClear,
+        (_, Some("oneToSet"), _) => FieldAccess::WriteOneToSet,
+        (Some("write-only" | "writeOnce"), _, _) => FieldAccess::WriteOnly,
+        _ => FieldAccess::ReadWrite,
+    }
+}
+
+/// Expand the `dim` array of an element into the names and offsets of its instances
+fn dim_instances(element: &Element, name: &str) -> Result<Vec<(String, u64)>> {
+    let Some(dim) = element.child_text("dim") else {
+        return Ok(vec![(name.to_string(), 0)]);
+    };
+
+    let dim = parse_int(dim)?;
+    let increment = element
+        .child_text("dimIncrement")
+        .map(parse_int)
+        .transpose()?
+        .ok_or_else(|| anyhow!("Array {name} has no dimIncrement"))?;
+
+    let indices = match element.child_text("dimIndex") {
+        Some(indices) => match indices.split_once('-') {
+            Some((first, last)) if first.parse::<u64>().is_ok() => (parse_int(first)?
+                ..=parse_int(last)?)
+                .map(|i| i.to_string())
+                .collect(),
+            Some((first, last)) if first.len() == 1 && last.len() == 1 => {
+                (first.chars().next().unwrap_or_default()..=last.chars().next().unwrap_or_default())
+                    .map(|c| c.to_string())
+                    .collect()
+            }
+            _ => indices
+                .split(',')
+                .map(|i| i.trim().to_string())
+                .collect::<Vec<_>>(),
+        },
+        None => (0..dim).map(|i| i.to_string()).collect(),
+    };
+
+    ensure!(
+        indices.len() as u64 == dim,
+        "Array {name} has {dim} elements but {} indices",
+        indices.len()
+    );
+
+    Ok(indices
+        .iter()
+        .enumerate()
+        .map(|(i, index)| {
+            (
+                name.replace("[%s]", index).replace("%s", index),
+                i as u64 * increment,
+            )
+        })
+        .collect())
+}
+
+/// Read the fields of a register which is `bits` bits wide
+fn fields(register: &Element, bits: u64, access: Option<&str>) -> Result<Vec<FieldDescription>> {
+    let Some(fields) = register.child("fields") else {
+        return Ok(Vec::new());
+    };
+
+    fields
+        .children("field")
+        .map(|field| {
+            let name = field
+                .child_text("name")
+                .ok_or_else(|| anyhow!("Field has no name"))?;
+
+            let (lsb, msb) = if let Some(range) = field.child_text("bitRange") {
+                let (msb, lsb) = range
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("Invalid bitRange {range} of field {name}"))?;
+                (parse_int(lsb)?, parse_int(msb)?)
+            } else if let (Some(lsb), Some(msb)) =
+                (field.child_text("lsb"), field.child_text("msb"))
+            {
+                (parse_int(lsb)?, parse_int(msb)?)
+            } else {
+                let offset = field
+                    .child_text("bitOffset")
+                    .map(parse_int)
+                    .transpose()?
+                    .ok_or_else(|| anyhow!("Field {name} has no bit position"))?;
+                let width = field
+                    .child_text("bitWidth")
+                    .map(parse_int)
+                    .transpose()?
+                    .unwrap_or(1);
+                ensure!(width > 0, "Field {name} has a width of 0");
+                (offset, offset.saturating_add(width - 1))
+            };
+
+            ensure!(lsb <= msb, "Field {name} has its lsb above its msb");
+            ensure!(msb < bits, "Field {name} does not fit in {bits} bits");
+
+            Ok(FieldDescription {
+                name: name.to_string(),
+                description: field
+                    .child_text("description")
+                    .unwrap_or_default()
+                    .to_string(),
+                lsb: lsb.try_into()?,
+                msb: msb.try_into()?,
+                access: field_access(
+                    field.child_text("access").or(access),
+                    field.child_text("modifiedWriteValues"),
+                    field.child_text("readAction"),
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Read the registers in a `registers` or `cluster` element, at `base` in the peripheral.
+/// Registers which cannot be represented are skipped and described in `skipped`.
+fn registers(
+    element: &Element,
+    base: u64,
+    prefix: &str,
+    properties: &Properties,
+    registers_out: &mut Vec<RegisterDescription>,
+    skipped: &mut Vec<String>,
+) -> Result<()> {
+    for child in &element.children {
+        if child.name != "register" && child.name != "cluster" {
+            continue;
+        }
+
+        let name = child
+            .child_text("name")
+            .ok_or_else(|| anyhow!("{} has no name", child.name))?;
+
+        // Alternate registers overlap the registers they are an alternate view of, which a
+        // bank cannot represent, so only the primary view is generated
+        if let Some(alternate) = child
+            .child_text("alternateRegister")
+            .or_else(|| child.child_text("alternateGroup"))
+        {
+            skipped.push(format!("{prefix}{name}, an alternate of {alternate}"));
+            continue;
+        }
+        let offset = child
+            .child_text("addressOffset")
+            .map(parse_int)
+            .transpose()?
+            .ok_or_else(|| anyhow!("{name} has no addressOffset"))?;
+        let properties = properties.inherit(child)?;
+
+        for (name, dim_offset) in dim_instances(child, name)? {
+            let name = format!("{prefix}{name}");
+            let offset = base + offset + dim_offset;
+
+            if child.name == "cluster" {
+                registers(
+                    child,
+                    offset,
+                    &format!("{name}_"),
+                    &properties,
+                    registers_out,
+                    skipped,
+                )?;
+                continue;
+            }
+
+            let bits = properties.size.unwrap_or(32);
+            ensure!(
+                bits % 8 == 0 && (8..=64).contains(&bits),
+                "Register {name} has an unsupported size of {bits} bits"
+            );
+            let size = bits / 8;
+            let mut fields = fields(child, bits, properties.access.as_deref())?;
+
+            let access = field_access(properties.access.as_deref(), None, None);
+            if fields.is_empty() && access != FieldAccess::ReadWrite {
+                fields.push(FieldDescription {
+                    name: "value".to_string(),
+                    description: String::new(),
+                    lsb: 0,
+                    msb: bits as u32 - 1,
+                    access,
+                });
+            }
+
+            registers_out.push(RegisterDescription {
+                name,
+                description: child
+                    .child_text("description")
+                    .unwrap_or_default()
+                    .to_string(),
+                offset,
+                size,
+                reset: properties.reset.unwrap_or(0) & (u64::MAX >> (64 - bits)),
+                fields,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a CMSIS-SVD device description
+pub(crate) fn parse(device: &Element) -> Result<RegisterMap> {
+    if device.name != "device" {
+        bail!("Expected an SVD <device> element, found <{}>", device.name);
+    }
+
+    let properties = Properties::default().inherit(device)?;
+
+    let peripherals = device
+        .child("peripherals")
+        .ok_or_else(|| anyhow!("Device has no peripherals"))?
+        .children("peripheral")
+        .collect::<Vec<_>>();
+
+    let by_name = peripherals
+        .iter()
+        .filter_map(|p| p.child_text("name").map(|n| (n, *p)))
+        .collect::<HashMap<_, _>>();
+
+    let mut banks = Vec::new();
+    let mut skipped = Vec::new();
+
+    for peripheral in &peripherals {
+        let name = peripheral
+            .child_text("name")
+            .ok_or_else(|| anyhow!("Peripheral has no name"))?;
+
+        // A derived peripheral uses the registers of the peripheral it derives from unless
+        // it declares its own
+        let base = match peripheral.attribute("derivedFrom") {
+            Some(base) => Some(
+                *by_name
+                    .get(base)
+                    .ok_or_else(|| anyhow!("Peripheral {name} derives from unknown {base}"))?,
+            ),
+            None => None,
+        };
+
+        let source = match base {
+            Some(base) if peripheral.child("registers").is_none() => base,
+            _ => peripheral,
+        };
+
+        let Some(registers_element) = source.child("registers") else {
+            continue;
+        };
+
+        let properties = match base {
+            Some(base) => properties.inherit(base)?.inherit(peripheral)?,
+            None => properties.inherit(peripheral)?,
+        };
+
+        let mut bank_registers = Vec::new();
+        let mut bank_skipped = Vec::new();
+        registers(
+            registers_element,
+            0,
+            "",
+            &properties,
+            &mut bank_registers,
+            &mut bank_skipped,
+        )?;
+        bank_registers.sort_by_key(|r| r.offset);
+        skipped.extend(
+            bank_skipped
+                .into_iter()
+                .map(|register| format!("register {name}.{register}")),
+        );
+
+        if bank_registers.is_empty() {
+            continue;
+        }
+
+        let description = peripheral
+            .child_text("description")
+            .or_else(|| base.and_then(|b| b.child_text("description")))
+            .unwrap_or_default();
+
+        banks.push(BankDescription {
+            name: name.to_string(),
+            description: description.to_string(),
+            registers: bank_registers,
+        });
+    }
+
+    Ok(RegisterMap { banks, skipped })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device schemaVersion="1.3">
+  <name>TEST</name>
+  <size>32</size>
+  <resetValue>0x0</resetValue>
+  <peripherals>
+    <peripheral>
+      <name>UART0</name>
+      <description>Serial port</description>
+      <baseAddress>0x40000000</baseAddress>
+      <registers>
+        <register>
+          <name>CTRL</name>
+          <description>Control register</description>
+          <addressOffset>0x0</addressOffset>
+          <resetValue>0x1</resetValue>
+          <fields>
+            <field>
+              <name>EN</name>
+              <bitRange>[0:0]</bitRange>
+            </field>
+            <field>
+              <name>ERR</name>
+              <bitOffset>3</bitOffset>
+              <bitWidth>3</bitWidth>
+              <modifiedWriteValues>oneToClear</modifiedWriteValues>
+            </field>
+          </fields>
+        </register>
+        <register>
+          <name>STATUS</name>
+          <addressOffset>0x4</addressOffset>
+          <size>16</size>
+          <access>read-only</access>
+        </register>
+        <register>
+          <name>STATUS_CLR</name>
+          <alternateRegister>STATUS</alternateRegister>
+          <addressOffset>0x4</addressOffset>
+          <size>16</size>
+          <access>write-only</access>
+        </register>
+        <register>
+          <dim>2</dim>
+          <dimIncrement>4</dimIncrement>
+          <name>DATA[%s]</name>
+          <addressOffset>0x8</addressOffset>
+        </register>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="UART0">
+      <name>UART1</name>
+      <baseAddress>0x40001000</baseAddress>
+    </peripheral>
+  </peripherals>
+</device>"#;
+
+    #[test]
+    fn test_parse_svd() {
+        let map = RegisterMap::from_svd(SVD).unwrap();
+
+        assert_eq!(map.banks.len(), 2);
+        let uart = &map.banks[0];
+        assert_eq!(uart.name, "UART0");
+        assert_eq!(
+            uart.registers
+                .iter()
+                .map(|r| (r.name.as_str(), r.offset, r.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ("CTRL", 0, 4),
+                ("STATUS", 4, 2),
+                ("DATA0", 8, 4),
+                ("DATA1", 12, 4)
+            ]
+        );
+
+        let ctrl = &uart.registers[0];
+        assert_eq!(ctrl.reset, 1);
+        assert_eq!(ctrl.fields[0].access, FieldAccess::ReadWrite);
+        assert_eq!((ctrl.fields[1].lsb, ctrl.fields[1].msb), (3, 5));
+        assert_eq!(ctrl.fields[1].access, FieldAccess::WriteOneToClear);
+
+        let status = &uart.registers[1];
+        assert_eq!(status.fields[0].access, FieldAccess::ReadOnly);
+        assert_eq!(status.fields[0].msb, 15);
+
+        assert_eq!(map.banks[1].name, "UART1");
+        assert_eq!(map.banks[1].description, "Serial port");
+        assert_eq!(map.banks[1].registers, uart.registers);
+
+        assert_eq!(
+            map.skipped,
+            vec![
+                "register UART0.STATUS_CLR, an alternate of STATUS",
+                "register UART1.STATUS_CLR, an alternate of STATUS"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_svd_field_out_of_range() {
+        let svd = SVD.replace("<bitRange>[0:0]</bitRange>", "<bitRange>[32:32]</bitRange>");
+        assert!(RegisterMap::from_svd(&svd).is_err());
+    }
+}
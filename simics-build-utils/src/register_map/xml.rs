@@ -0,0 +1,111 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! A simple tree of XML elements, read from a document parsed with `roxmltree`. Namespace
+//! prefixes are dropped from element and attribute names, so `spirit:register` and
+//! `ipxact:register` are both read as `register`.
+
+use anyhow::Result;
+use roxmltree::{Document, Node, ParsingOptions};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// An XML element with its attributes, child elements, and text content
+pub(crate) struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    /// Get the first child element named `name`
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Iterate over the child elements named `name`
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Get the trimmed text of the first child element named `name`
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|c| c.text.trim())
+    }
+
+    /// Get the value of the attribute named `name`
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl Element {
+    /// Convert a parsed element and its descendants
+    fn from_node(node: Node) -> Self {
+        Self {
+            name: node.tag_name().name().to_string(),
+            attributes: node
+                .attributes()
+                .map(|a| (a.name().to_string(), a.value().to_string()))
+                .collect(),
+            children: node
+                .children()
+                .filter(Node::is_element)
+                .map(Self::from_node)
+                .collect(),
+            text: node
+                .children()
+                .filter(Node::is_text)
+                .filter_map(|t| t.text())
+                .collect(),
+        }
+    }
+}
+
+/// Parse an XML document, returning its root element
+pub(crate) fn parse(text: &str) -> Result<Element> {
+    let document = Document::parse_with_options(
+        text,
+        ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(Element::from_node(document.root_element()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let root = parse(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <!-- A comment -->
+            <spirit:component xmlns:spirit="http://example.com" spirit:id='a &amp; b'>
+                <spirit:name>uart</spirit:name>
+                <empty/>
+                <text><![CDATA[<raw>]]> &lt;escaped&#x3e;</text>
+            </spirit:component>"#,
+        )
+        .unwrap();
+
+        assert_eq!(root.name, "component");
+        assert_eq!(root.attribute("id"), Some("a & b"));
+        assert_eq!(root.child_text("name"), Some("uart"));
+        assert!(root.child("empty").is_some());
+        assert_eq!(root.child_text("text"), Some("<raw> <escaped>"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse("<a><b></a>").is_err());
+        assert!(parse("<a>").is_err());
+        assert!(parse("text only").is_err());
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+use darling::{Error, FromDeriveInput, Result};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{parse_macro_input, DeriveInput, Generics, Ident};
+
+#[derive(Debug, FromDeriveInput)]
+#[darling(
+    attributes(atom),
+    supports(struct_any, enum_unit),
+    and_then = "Self::validate"
+)]
+struct AtomOpts {
+    ident: Ident,
+    generics: Generics,
+    /// The name of the atom type, which is shared by all models using the atom
+    name: String,
+}
+
+impl AtomOpts {
+    fn validate(self) -> Result<Self> {
+        // The atom id is cached in a static, which would be shared by all instantiations of a
+        // generic type
+        if !self.generics.params.is_empty() {
+            return Err(
+                Error::custom("`#[derive(Atom)]` does not support generic types")
+                    .with_span(&self.generics),
+            );
+        }
+
+        Ok(self)
+    }
+}
+
+impl ToTokens for AtomOpts {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let ident = &self.ident;
+        let name = &self.name;
+
+        tokens.extend(quote! {
+            impl simics::AtomType for #ident {
+                type Value = Self;
+                const NAME: &'static str = #name;
+
+                fn id() -> simics::Result<simics::AtomId> {
+                    static ID: std::sync::OnceLock<simics::AtomId> = std::sync::OnceLock::new();
+
+                    if let Some(id) = ID.get() {
+                        return Ok(*id);
+                    }
+
+                    let id = simics::get_atom_class_id(Self::NAME)?;
+                    Ok(*ID.get_or_init(|| id))
+                }
+            }
+
+            const _: () = assert!(
+                std::mem::size_of::<#ident>() <= std::mem::size_of::<usize>(),
+                "Atom values must be no larger than a pointer"
+            );
+        })
+    }
+}
+
+/// Derive macro for the `AtomType` trait
+pub fn atom_impl(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let args = match AtomOpts::from_derive_input(&input) {
+        Ok(opts) => opts,
+        Err(e) => return e.write_errors().into(),
+    };
+
+    quote!(#args).into()
+}
@@ -0,0 +1,646 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+use darling::{Error, FromMeta, Result};
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Expr, ExprLit, ExprRange, Field, Fields, Ident,
+    ItemStruct, Lit, Meta, RangeLimits, Type, Visibility,
+};
+
+#[derive(Debug, FromMeta)]
+#[darling(and_then = "Self::validate")]
+struct RegisterOpts {
+    /// The offset of the register in the bank
+    offset: u64,
+    /// The size of the register in bytes, by default the size of the field type. Registers
+    /// narrower than their field type use the low bytes of the field.
+    #[darling(default)]
+    size: Option<u64>,
+    /// The value of the register after reset
+    #[darling(default)]
+    reset: u64,
+    /// The name of the register, by default the name of the field
+    #[darling(default)]
+    name: Option<String>,
+    /// A method called with the stored value of the register when it is read, returning
+    /// the value to read
+    #[darling(default)]
+    on_read: Option<Ident>,
+    /// A method called with the old and new value of the register after it is written
+    #[darling(default)]
+    on_write: Option<Ident>,
+}
+
+impl RegisterOpts {
+    fn validate(self) -> Result<Self> {
+        if self.size.is_some_and(|s| s == 0 || s > 8) {
+            return Err(Error::custom("`size` must be between 1 and 8 bytes"));
+        }
+
+        Ok(self)
+    }
+}
+
+/// Whether `name` is a valid name for the attribute of a register
+fn is_attribute_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The access types accepted by `#[field(access = "...")]`
+const ACCESS_TYPES: &str = "`rw`, `ro`, `wo`, `rw1c`, `rw1s`, `rc`";
+
+#[derive(Debug, FromMeta)]
+struct FieldOpts {
+    /// The name of the field, used to name its accessors
+    name: Ident,
+    /// The bits of the field, either a single bit or an inclusive range `lsb..=msb`
+    bits: Expr,
+    /// The access type of the field, by default `"rw"`
+    #[darling(default)]
+    access: Option<String>,
+}
+
+#[derive(Debug)]
+struct BankField {
+    name: Ident,
+    lsb: u32,
+    msb: u32,
+    access: TokenStream2,
+}
+
+impl BankField {
+    fn from_opts(opts: FieldOpts) -> Result<Self> {
+        let (lsb, msb) = Self::parse_bits(&opts.bits)?;
+
+        let access = match opts.access.as_deref().unwrap_or("rw") {
+            "rw" => quote!(simics::FieldAccess::ReadWrite),
+            "ro" => quote!(simics::FieldAccess::ReadOnly),
+            "wo" => quote!(simics::FieldAccess::WriteOnly),
+            "rw1c" | "w1c" => quote!(simics::FieldAccess::WriteOneToClear),
+            "rw1s" | "w1s" => quote!(simics::FieldAccess::WriteOneToSet),
+            "rc" => quote!(simics::FieldAccess::ReadToClear),
+            other => {
+                return Err(Error::custom(format!(
+                    "Unknown field access `{other}`, expected one of {ACCESS_TYPES}"
+                )))
+            }
+        };
+
+        Ok(Self {
+            name: opts.name,
+            lsb,
+            msb,
+            access,
+        })
+    }
+
+    fn parse_bit(expr: &Expr) -> Result<u32> {
+        match expr {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(i), ..
+            }) => i.base10_parse().map_err(Error::from),
+            _ => Err(Error::custom("Field bits must be integer literals").with_span(expr)),
+        }
+    }
+
+    fn parse_bits(expr: &Expr) -> Result<(u32, u32)> {
+        let (lsb, msb) = match expr {
+            Expr::Range(ExprRange {
+                start: Some(start),
+                limits: RangeLimits::Closed(_),
+                end: Some(end),
+                ..
+            }) => (Self::parse_bit(start)?, Self::parse_bit(end)?),
+            Expr::Range(_) => {
+                return Err(
+                    Error::custom("Field bits must be an inclusive range `lsb..=msb`")
+                        .with_span(expr),
+                )
+            }
+            _ => {
+                let bit = Self::parse_bit(expr)?;
+                (bit, bit)
+            }
+        };
+
+        if lsb > msb {
+            return Err(
+                Error::custom("The first bit of a field must not be above its last bit")
+                    .with_span(expr),
+            );
+        }
+
+        Ok((lsb, msb))
+    }
+
+    fn mask(&self) -> u64 {
+        u64::MAX >> (63 - (self.msb - self.lsb))
+    }
+}
+
+#[derive(Debug)]
+struct BankRegister {
+    ident: Ident,
+    ty: Type,
+    vis: Visibility,
+    name: String,
+    description: String,
+    offset: u64,
+    size: u64,
+    reset: u64,
+    on_read: Option<Ident>,
+    on_write: Option<Ident>,
+    fields: Vec<BankField>,
+}
+
+impl BankRegister {
+    /// The size in bytes of the unsigned integer type `ty`, if it is one. Registers are
+    /// restricted to unsigned types so their values convert to and from `u64` without sign
+    /// extension.
+    fn ty_size(ty: &Type) -> Option<u64> {
+        let Type::Path(p) = ty else {
+            return None;
+        };
+
+        match p.path.get_ident()?.to_string().as_str() {
+            "u8" => Some(1),
+            "u16" => Some(2),
+            "u32" => Some(4),
+            "u64" => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Parse the `#[register]` and `#[field]` attributes of a struct field, removing them from
+    /// the field. Returns `None` if the field is not a register.
+    fn from_field(field: &mut Field) -> Result<Option<Self>> {
+        let (bank_attrs, attrs): (Vec<Attribute>, Vec<Attribute>) = field
+            .attrs
+            .drain(..)
+            .partition(|a| a.path().is_ident("register") || a.path().is_ident("field"));
+        field.attrs = attrs;
+
+        let Some(register_attr) = bank_attrs.iter().find(|a| a.path().is_ident("register")) else {
+            if let Some(a) = bank_attrs.first() {
+                return Err(Error::custom("`#[field]` can only be used on registers").with_span(a));
+            }
+
+            return Ok(None);
+        };
+
+        if bank_attrs
+            .iter()
+            .filter(|a| a.path().is_ident("register"))
+            .count()
+            > 1
+        {
+            return Err(Error::custom("Duplicate `#[register]` attribute").with_span(register_attr));
+        }
+
+        let ident = field
+            .ident
+            .clone()
+            .ok_or_else(|| Error::custom("Registers must be named fields"))?;
+
+        let opts = RegisterOpts::from_meta(&register_attr.meta)?;
+
+        let Some(ty_size) = Self::ty_size(&field.ty) else {
+            return Err(Error::custom(format!(
+                "Register `{ident}` must have one of the types `u8`, `u16`, `u32` or `u64`"
+            ))
+            .with_span(&field.ty));
+        };
+
+        let size = match opts.size {
+            Some(size) if size > ty_size => {
+                return Err(Error::custom(format!(
+                    "Register `{ident}` of {size} bytes does not fit in its field type"
+                ))
+                .with_span(register_attr))
+            }
+            Some(size) => size,
+            None => ty_size,
+        };
+
+        let name = opts.name.unwrap_or_else(|| ident.to_string());
+
+        if !is_attribute_name(&name) {
+            return Err(Error::custom(format!(
+                "Register name `{name}` is not a valid attribute name, which must start with a \
+                 letter and contain only letters, digits and underscores"
+            ))
+            .with_span(register_attr));
+        }
+
+        let mask = Self::size_mask(size);
+
+        if opts.reset & !mask != 0 {
+            return Err(Error::custom(format!(
+                "Reset value of register `{ident}` does not fit in {size} bytes"
+            ))
+            .with_span(register_attr));
+        }
+
+        let fields = bank_attrs
+            .iter()
+            .filter(|a| a.path().is_ident("field"))
+            .map(|a| {
+                FieldOpts::from_meta(&a.meta)
+                    .and_then(BankField::from_opts)
+                    .map_err(|e| e.with_span(a))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        for (i, f) in fields.iter().enumerate() {
+            if u64::from(f.msb) >= size * 8 {
+                return Err(Error::custom(format!(
+                    "Field `{}` does not fit in register `{ident}`",
+                    f.name
+                )));
+            }
+
+            if fields[..i].iter().any(|o| o.lsb <= f.msb && f.lsb <= o.msb) {
+                return Err(Error::custom(format!(
+                    "Field `{}` overlaps another field of register `{ident}`",
+                    f.name
+                )));
+            }
+        }
+
+        let description = field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("doc"))
+            .filter_map(|a| match &a.meta {
+                Meta::NameValue(m) => match &m.value {
+                    Expr::Lit(ExprLit {
+                        lit: Lit::Str(s), ..
+                    }) => Some(s.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Ok(Some(Self {
+            name,
+            ident,
+            ty: field.ty.clone(),
+            vis: field.vis.clone(),
+            description,
+            offset: opts.offset,
+            size,
+            reset: opts.reset,
+            on_read: opts.on_read,
+            on_write: opts.on_write,
+            fields,
+        }))
+    }
+
+    /// The mask of the bits of a register of `size` bytes
+    fn size_mask(size: u64) -> u64 {
+        u64::MAX >> (64 - size * 8)
+    }
+
+    fn info(&self) -> TokenStream2 {
+        let name = &self.name;
+        let description = &self.description;
+        let offset = self.offset;
+        let size = self.size;
+        let reset = self.reset;
+        let fields = self.fields.iter().map(|f| {
+            let name = f.name.to_string();
+            let lsb = f.lsb;
+            let msb = f.msb;
+            let access = &f.access;
+
+            quote! {
+                simics::FieldInfo {
+                    name: #name,
+                    lsb: #lsb,
+                    msb: #msb,
+                    access: #access,
+                }
+            }
+        });
+
+        quote! {
+            simics::RegisterInfo {
+                name: #name,
+                description: #description,
+                offset: #offset,
+                size: #size,
+                reset: #reset,
+                fields: &[#(#fields),*],
+            }
+        }
+    }
+
+    fn accessors(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let ty = &self.ty;
+        let vis = &self.vis;
+
+        let accessors = self.fields.iter().map(|f| {
+            let getter = format_ident!("{}_{}", ident, f.name);
+            let setter = format_ident!("set_{}_{}", ident, f.name);
+            let lsb = Literal::u32_unsuffixed(f.lsb);
+            let mask = Literal::u64_unsuffixed(f.mask());
+            let getter_doc = format!("Get the `{}` field of the `{}` register", f.name, self.name);
+            let setter_doc = format!(
+                "Set the `{}` field of the `{}` register without side effects",
+                f.name, self.name
+            );
+
+            quote! {
+                #[doc = #getter_doc]
+                #vis fn #getter(&self) -> #ty {
+                    (self.#ident >> #lsb) & #mask
+                }
+
+                #[doc = #setter_doc]
+                #vis fn #setter(&mut self, value: #ty) {
+                    self.#ident = (self.#ident & !(#mask << #lsb)) | ((value & #mask) << #lsb);
+                }
+            }
+        });
+
+        quote!(#(#accessors)*)
+    }
+}
+
+/// Attribute macro declaring a struct as a register bank
+pub fn bank_impl(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return Error::custom("`#[bank]` does not take any arguments")
+            .write_errors()
+            .into();
+    }
+
+    let mut input = parse_macro_input!(input as ItemStruct);
+
+    let is_class = input.attrs.iter().any(|a| a.path().is_ident("class"));
+
+    let Fields::Named(ref mut fields) = input.fields else {
+        return Error::custom("`#[bank]` can only be used on structs with named fields")
+            .with_span(&input)
+            .write_errors()
+            .into();
+    };
+
+    let mut registers = Vec::new();
+    let mut errors = Error::accumulator();
+
+    fields.named.iter_mut().for_each(|f| {
+        if let Some(Some(register)) = errors.handle(BankRegister::from_field(f)) {
+            registers.push(register);
+        }
+    });
+
+    if registers.is_empty() {
+        errors.push(
+            Error::custom("A bank must have at least one `#[register]`").with_span(&input.ident),
+        );
+    }
+
+    registers.iter().enumerate().for_each(|(i, r)| {
+        if let Some(o) = registers[..i]
+            .iter()
+            .find(|o| o.offset < r.offset + r.size && r.offset < o.offset + o.size)
+        {
+            errors.push(Error::custom(format!(
+                "Register `{}` overlaps register `{}`",
+                r.name, o.name
+            )));
+        }
+
+        // Each register is accessed through an attribute with the name of the register
+        if registers[..i].iter().any(|o| o.name == r.name) {
+            errors.push(
+                Error::custom(format!("Duplicate register name `{}`", r.name)).with_span(&r.ident),
+            );
+        }
+    });
+
+    if let Err(e) = errors.finish() {
+        return e.write_errors().into();
+    }
+
+    // When the bank is expanded before the class, the class has not yet inserted its
+    // `ConfObject` field, which must be initialized by the generated `Default` implementation
+    if is_class
+        && fields
+            .named
+            .first()
+            .is_some_and(|f| f.ty != parse_quote!(simics::ConfObject))
+    {
+        fields.named.insert(
+            0,
+            parse_quote! {
+                conf_object: simics::ConfObject
+            },
+        );
+    }
+
+    fields.named.push(parse_quote! {
+        bank_instrumentation: simics::BankInstrumentation
+    });
+
+    let defaults = fields
+        .named
+        .iter()
+        .filter_map(|f| {
+            let field_ident = f.ident.as_ref()?;
+
+            Some(
+                if let Some(register) = registers.iter().find(|r| &r.ident == field_ident) {
+                    let reset = Literal::u64_unsuffixed(register.reset);
+                    quote!(#field_ident: #reset)
+                } else {
+                    quote!(#field_ident: Default::default())
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let infos = registers.iter().map(|r| r.info());
+
+    let getters = registers.iter().enumerate().map(|(index, r)| {
+        let register_ident = &r.ident;
+        let mask = Literal::u64_unsuffixed(BankRegister::size_mask(r.size));
+        quote!(#index => (self.#register_ident as u64) & #mask)
+    });
+
+    let setters = registers.iter().enumerate().map(|(index, r)| {
+        let register_ident = &r.ident;
+        let ty = &r.ty;
+        let mask = Literal::u64_unsuffixed(BankRegister::size_mask(r.size));
+        quote!(#index => self.#register_ident = (value & #mask) as #ty)
+    });
+
+    let on_read = registers
+        .iter()
+        .enumerate()
+        .filter_map(|(index, r)| {
+            r.on_read
+                .as_ref()
+                .map(|hook| quote!(#index => self.#hook(value)))
+        })
+        .collect::<Vec<_>>();
+
+    let on_read = (!on_read.is_empty()).then(|| {
+        quote! {
+            fn on_read(&mut self, index: usize, value: u64) -> u64 {
+                match index {
+                    #(#on_read,)*
+                    _ => value,
+                }
+            }
+        }
+    });
+
+    let on_write = registers
+        .iter()
+        .enumerate()
+        .filter_map(|(index, r)| {
+            r.on_write
+                .as_ref()
+                .map(|hook| quote!(#index => self.#hook(old, new)))
+        })
+        .collect::<Vec<_>>();
+
+    let on_write = (!on_write.is_empty()).then(|| {
+        quote! {
+            fn on_write(&mut self, index: usize, old: u64, new: u64) {
+                match index {
+                    #(#on_write,)*
+                    _ => {}
+                }
+            }
+        }
+    });
+
+    let accessors = registers.iter().map(|r| r.accessors());
+
+    quote! {
+        #input
+
+        impl #impl_generics Default for #ident #ty_generics #where_clause {
+            fn default() -> Self {
+                Self {
+                    #(#defaults),*
+                }
+            }
+        }
+
+        #[allow(clippy::unnecessary_cast, clippy::single_match)]
+        impl #impl_generics simics::RegisterBank for #ident #ty_generics #where_clause {
+            const REGISTERS: &'static [simics::RegisterInfo] = &[#(#infos),*];
+
+            fn get_register(&self, index: usize) -> u64 {
+                match index {
+                    #(#getters,)*
+                    _ => 0,
+                }
+            }
+
+            fn set_register(&mut self, index: usize, value: u64) {
+                match index {
+                    #(#setters,)*
+                    _ => {}
+                }
+            }
+
+            fn instrumentation(&mut self) -> &mut simics::BankInstrumentation {
+                &mut self.bank_instrumentation
+            }
+
+            #on_read
+
+            #on_write
+        }
+
+        impl #impl_generics simics::TransactionTarget for #ident #ty_generics #where_clause {
+            fn issue(&mut self, t: &mut simics::Transaction, addr: u64) -> simics::ExceptionType {
+                <Self as simics::RegisterBank>::access(self, t, addr)
+            }
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #(#accessors)*
+        }
+    }
+    .into_token_stream()
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_attribute_name, BankField, BankRegister};
+    use quote::quote;
+    use syn::{parse_quote, Expr, Type};
+
+    fn field(bits: Expr) -> BankField {
+        let (lsb, msb) = BankField::parse_bits(&bits).expect("Failed to parse bits");
+
+        BankField {
+            name: parse_quote!(f),
+            lsb,
+            msb,
+            access: quote!(),
+        }
+    }
+
+    #[test]
+    fn test_field_mask() {
+        assert_eq!(field(parse_quote!(0)).mask(), 0x1);
+        assert_eq!(field(parse_quote!(3..=5)).mask(), 0x7);
+        assert_eq!(field(parse_quote!(0..=7)).mask(), 0xff);
+        assert_eq!(field(parse_quote!(0..=63)).mask(), u64::MAX);
+    }
+
+    #[test]
+    fn test_field_bits_invalid() {
+        assert!(BankField::parse_bits(&parse_quote!(5..=3)).is_err());
+        assert!(BankField::parse_bits(&parse_quote!(3..5)).is_err());
+        assert!(BankField::parse_bits(&parse_quote!(x)).is_err());
+    }
+
+    #[test]
+    fn test_register_types() {
+        let ty_size = |ty: Type| BankRegister::ty_size(&ty);
+
+        assert_eq!(ty_size(parse_quote!(u8)), Some(1));
+        assert_eq!(ty_size(parse_quote!(u64)), Some(8));
+        assert_eq!(ty_size(parse_quote!(i32)), None);
+        assert_eq!(ty_size(parse_quote!([u8; 4])), None);
+    }
+
+    #[test]
+    fn test_register_mask() {
+        assert_eq!(BankRegister::size_mask(1), 0xff);
+        assert_eq!(BankRegister::size_mask(3), 0xff_ffff);
+        assert_eq!(BankRegister::size_mask(8), u64::MAX);
+    }
+
+    #[test]
+    fn test_attribute_name() {
+        assert!(is_attribute_name("control"));
+        assert!(is_attribute_name("Status_2"));
+        assert!(!is_attribute_name(""));
+        assert!(!is_attribute_name("_control"));
+        assert!(!is_attribute_name("2control"));
+        assert!(!is_attribute_name("control-reg"));
+        assert!(!is_attribute_name("control.reg"));
+    }
+}
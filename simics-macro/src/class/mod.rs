@@ -20,6 +20,7 @@ struct ClassAttribute {
     required: Flag,
     optional: Flag,
     pseudo: Flag,
+    persistent: Flag,
     #[darling(default)]
     default: Option<Expr>,
 }
@@ -41,6 +42,13 @@ impl ClassAttribute {
             ));
         }
 
+        // Persistent state is saved, so pseudo attributes cannot be persistent
+        if self.pseudo.is_present() && self.persistent.is_present() {
+            return Err(Error::custom(
+                "`persistent` cannot be set if `pseudo` is set",
+            ));
+        }
+
         // Make sure default is not set if required is set
         if self.required.is_present() && self.default.is_some() {
             return Err(Error::custom(
@@ -52,7 +60,7 @@ impl ClassAttribute {
     }
 
     fn attr_type(&self) -> TokenStream2 {
-        if self.required.is_present() {
+        let attr_type = if self.required.is_present() {
             quote!(simics::AttrAttr::Sim_Attr_Required)
         } else if self.optional.is_present() {
             quote!(simics::AttrAttr::Sim_Attr_Optional)
@@ -60,10 +68,34 @@ impl ClassAttribute {
             quote!(simics::AttrAttr::Sim_Attr_Pseudo)
         } else {
             unreachable!("Attribute is known to have exactly one type")
+        };
+
+        if self.persistent.is_present() {
+            quote!(#attr_type | simics::AttrAttr::Sim_Attr_Persistent)
+        } else {
+            attr_type
         }
     }
 }
 
+#[derive(Debug, Clone, FromMeta)]
+/// A port object registered on each object of the class
+struct ClassPort {
+    name: String,
+    #[darling(default)]
+    class: Option<Type>,
+    #[darling(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, FromMeta)]
+/// A notifier type notified by objects of the class
+struct ClassNotifier {
+    name: String,
+    #[darling(default)]
+    description: Option<String>,
+}
+
 #[derive(Debug, FromField)]
 #[darling(attributes(class), forward_attrs(doc))]
 struct ClassField {
@@ -106,6 +138,13 @@ struct ClassDeriveOpts {
     short_description: Option<String>,
     #[darling(default)]
     kind: Option<Type>,
+    #[darling(multiple)]
+    port: Vec<ClassPort>,
+    #[darling(multiple)]
+    notifier: Vec<ClassNotifier>,
+    #[darling(default)]
+    parent: Option<Type>,
+    clock: Flag,
     skip_alloc: Flag,
     skip_init: Flag,
     skip_finalize: Flag,
@@ -493,6 +532,86 @@ impl ClassDeriveOpts {
         }
     }
 
+    fn impl_ports(&self) -> Vec<TokenStream2> {
+        self.port
+            .iter()
+            .map(|p| {
+                let name = &p.name;
+                let description = p.description.as_ref().unwrap_or(&p.name);
+
+                if let Some(class) = p.class.as_ref() {
+                    // The port class may already have been created, either because it is
+                    // used for more than one port or so its interfaces could be registered
+                    quote! {
+                        let port_cls = match simics::get_class(<#class>::NAME) {
+                            Ok(port_cls) if !port_cls.is_null() => port_cls,
+                            _ => <#class as simics::ClassCreate>::create()?,
+                        };
+                        simics::register_port(cls, #name, port_cls, #description)?;
+                    }
+                } else {
+                    quote! {
+                        simics::register_simple_port(cls, #name, #description)?;
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn impl_notifiers(&self) -> Vec<TokenStream2> {
+        self.notifier
+            .iter()
+            .map(|n| {
+                let name = &n.name;
+                let description = n.description.as_ref().unwrap_or(&n.name);
+
+                quote! {
+                    simics::register_notifier(cls, simics::notifier_type(#name)?, #description)?;
+                }
+            })
+            .collect()
+    }
+
+    fn impl_clock(&self) -> TokenStream2 {
+        if !self.clock.is_present() {
+            return quote!();
+        }
+
+        let ident = &self.ident;
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+
+        quote! {
+            <#ident #ty_generics as simics::Clock>::register_clock(cls)?;
+        }
+    }
+
+    fn impl_parent(&self) -> TokenStream2 {
+        let Some(parent) = self.parent.as_ref() else {
+            return quote!();
+        };
+
+        let ident = &self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+
+        quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                /// Get the object this port object was registered on
+                pub fn parent(&self) -> simics::Result<&#parent> {
+                    let obj = simics::port_object_parent(self as *const Self as *mut simics::ConfObject)?
+                        .ok_or(simics::Error::NotPortObject)?;
+                    Ok(unsafe { <#parent as simics::FromConfObject>::from_conf_object(obj) })
+                }
+
+                /// Get the object this port object was registered on mutably
+                pub fn parent_mut(&mut self) -> simics::Result<&mut #parent> {
+                    let obj = simics::port_object_parent(self as *mut Self as *mut simics::ConfObject)?
+                        .ok_or(simics::Error::NotPortObject)?;
+                    Ok(unsafe { <#parent as simics::FromConfObject>::from_conf_object_mut(obj) })
+                }
+            }
+        }
+    }
+
     fn impl_create(&self) -> TokenStream2 {
         let name = &self.ident;
         let alloc_fn_name = format_ident!("{}_alloc", &name);
@@ -529,6 +648,9 @@ impl ClassDeriveOpts {
             .unwrap_or_else(|_| unreachable!("Failed to parse C string literal"));
 
         let attributes_impl = self.impl_attributes();
+        let ports_impl = self.impl_ports();
+        let notifiers_impl = self.impl_notifiers();
+        let clock_impl = self.impl_clock();
 
         quote! {
 
@@ -550,6 +672,9 @@ impl ClassDeriveOpts {
                 fn create() -> simics::Result<*mut simics::ConfClass> {
                     let mut cls = simics::create_class(#class_name, #name::CLASS)?;
                     #( #attributes_impl )*
+                    #( #ports_impl )*
+                    #( #notifiers_impl )*
+                    #clock_impl
 
                     Ok(cls)
                 }
@@ -729,6 +854,7 @@ impl ToTokens for ClassDeriveOpts {
 
         let name_impl = self.impl_name();
         let new_impl = self.impl_new();
+        let parent_impl = self.impl_parent();
         let ffi_impl = self.impl_ffi();
         let ident = &self.ident;
         let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
@@ -736,6 +862,7 @@ impl ToTokens for ClassDeriveOpts {
         tokens.extend(quote! {
             #name_impl
             #new_impl
+            #parent_impl
             #ffi_impl
             impl #impl_generics simics::Class for #ident #ty_generics #where_clause {}
         });
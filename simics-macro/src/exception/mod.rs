@@ -47,7 +47,7 @@ pub fn simics_exception_impl(args: TokenStream, input: TokenStream) -> TokenStre
     let vis = input.vis.clone();
     let mut sig = input.sig.clone();
     let attrs = &input.attrs;
-    let doc_attrs = attrs
+    let forwarded_attrs = attrs
         .iter()
         .filter(|a| {
             if let Meta::NameValue(attr) = &a.meta {
@@ -57,7 +57,9 @@ pub fn simics_exception_impl(args: TokenStream, input: TokenStream) -> TokenStre
                     false
                 }
             } else {
-                false
+                // The wrapper must also be `#[track_caller]` for the caller location to
+                // reach the wrapped function
+                a.path().is_ident("track_caller")
             }
         })
         .collect::<Vec<_>>();
@@ -108,7 +110,7 @@ pub fn simics_exception_impl(args: TokenStream, input: TokenStream) -> TokenStre
     };
 
     let wrapper = quote! {
-        #(#doc_attrs)*
+        #(#forwarded_attrs)*
         #vis #sig {
             #[allow(deprecated)]
             let result = #inner_ident #maybe_ty_generics(#(#args),*);
@@ -0,0 +1,336 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+use darling::{ast::NestedMeta, Error, FromMeta, Result};
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_macro_input, Fields, Ident, ItemStruct, LitByteStr, Type};
+
+#[derive(Debug, FromMeta)]
+struct HapOpts {
+    /// The name of the hap type, for example `"My_Threshold_Reached"`
+    name: String,
+    /// A description of the index value of the hap, if the hap has one
+    #[darling(default)]
+    index: Option<String>,
+    /// A description of the hap, by default its name
+    #[darling(default)]
+    description: Option<String>,
+}
+
+/// A parameter of a hap, declared as a field of the hap struct
+struct HapParam {
+    name: Ident,
+    ty: Type,
+}
+
+impl HapParam {
+    /// The character describing the type of the parameter in the parameter string of the hap
+    /// type
+    fn type_char(&self) -> Result<char> {
+        let last_ident = |ty: &Type| match ty {
+            Type::Path(p) => p.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+
+        let type_char = match &self.ty {
+            // NOTE: The parameters are passed to the simulator as mutable pointers
+            Type::Ptr(p) if p.mutability.is_none() => None,
+            Type::Ptr(p) => match last_ident(&p.elem).as_deref() {
+                Some("ConfObject" | "conf_object_t") => Some('c'),
+                Some("c_char") => Some('s'),
+                Some("generic_transaction_t") => Some('m'),
+                Some("c_void") => Some('v'),
+                _ => None,
+            },
+            ty => match last_ident(ty).as_deref() {
+                Some("i32" | "c_int") => Some('i'),
+                Some("i64") => Some('I'),
+                _ => None,
+            },
+        };
+
+        type_char.ok_or_else(|| {
+            Error::custom(
+                "Hap parameters must be `i32`, `i64`, `*mut ConfObject`, `*mut c_char`, \
+                 `*mut generic_transaction_t` or `*mut c_void`",
+            )
+            .with_span(&self.ty)
+        })
+    }
+}
+
+struct Hap {
+    ident: Ident,
+    opts: HapOpts,
+    params: Vec<HapParam>,
+    param_chars: String,
+}
+
+impl Hap {
+    fn new(opts: HapOpts, input: &ItemStruct) -> Result<Self> {
+        if !input.generics.params.is_empty() {
+            return Err(Error::custom("`#[hap]` does not support generic structs")
+                .with_span(&input.generics));
+        }
+
+        let params = match &input.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .filter_map(|f| {
+                    f.ident.clone().map(|name| HapParam {
+                        name,
+                        ty: f.ty.clone(),
+                    })
+                })
+                .collect::<Vec<_>>(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => {
+                return Err(
+                    Error::custom("`#[hap]` can only be used on structs with named fields")
+                        .with_span(&input.ident),
+                )
+            }
+        };
+
+        let mut errors = Error::accumulator();
+
+        let param_chars = params
+            .iter()
+            .filter_map(|p| errors.handle(p.type_char()))
+            .collect::<String>();
+
+        errors.finish()?;
+
+        Ok(Self {
+            ident: input.ident.clone(),
+            opts,
+            params,
+            param_chars,
+        })
+    }
+}
+
+impl ToTokens for Hap {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        let ident = &self.ident;
+        let name = LitByteStr::new(
+            format!("{}\0", self.opts.name).as_bytes(),
+            Span::call_site(),
+        );
+        let params = &self.param_chars;
+        let param_desc = self
+            .params
+            .iter()
+            .map(|p| p.name.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let index = self
+            .opts
+            .index
+            .as_ref()
+            .map(|i| quote!(Some(#i)))
+            .unwrap_or(quote!(None));
+        let description = self.opts.description.as_ref().unwrap_or(&self.opts.name);
+        let param_names = self.params.iter().map(|p| &p.name).collect::<Vec<_>>();
+        let param_tys = self.params.iter().map(|p| &p.ty).collect::<Vec<_>>();
+        let userdata = format_ident!("__userdata");
+        let obj = format_ident!("__obj");
+
+        tokens.extend(quote! {
+            impl simics::Hap for #ident {
+                type Name = &'static [u8];
+                const NAME: Self::Name = #name;
+            }
+
+            impl simics::CustomHap for #ident {
+                const PARAMS: &'static str = #params;
+                const PARAM_DESC: &'static str = #param_desc;
+                const INDEX: Option<&'static str> = #index;
+                const DESCRIPTION: &'static str = #description;
+
+                fn hap_type() -> simics::Result<simics::HapType> {
+                    static HAP_TYPE: std::sync::OnceLock<simics::HapType> =
+                        std::sync::OnceLock::new();
+
+                    if let Some(hap_type) = HAP_TYPE.get() {
+                        return Ok(*hap_type);
+                    }
+
+                    let hap_type = simics::custom_hap_type::<Self>()?;
+                    Ok(*HAP_TYPE.get_or_init(|| hap_type))
+                }
+
+                unsafe fn occurred_raw(
+                    hap_type: simics::HapType,
+                    obj: *mut simics::ConfObject,
+                    index: i64,
+                    hap: Self,
+                    always: bool,
+                ) -> i32 {
+                    if always {
+                        unsafe {
+                            simics::sys::SIM_c_hap_occurred_always(
+                                hap_type,
+                                obj,
+                                index,
+                                #(hap.#param_names),*
+                            )
+                        }
+                    } else {
+                        unsafe {
+                            simics::sys::SIM_c_hap_occurred(
+                                hap_type,
+                                obj,
+                                index,
+                                #(hap.#param_names),*
+                            )
+                        }
+                    }
+                }
+
+                fn handler<F>() -> unsafe extern "C" fn()
+                where
+                    F: FnMut(*mut simics::ConfObject, Self) + 'static,
+                {
+                    extern "C" fn handler<F>(
+                        #userdata: *mut std::ffi::c_void,
+                        #obj: *mut simics::ConfObject,
+                        #(#param_names: #param_tys),*
+                    ) where
+                        F: FnMut(*mut simics::ConfObject, #ident) + 'static,
+                    {
+                        // NOTE: The closure is owned by its subscription and freed when it is
+                        // dropped and no longer running
+                        unsafe {
+                            simics::HapClosure::<F>::call(#userdata, move |closure| {
+                                closure(#obj, #ident { #(#param_names),* })
+                            })
+                        }
+                    }
+
+                    unsafe {
+                        std::mem::transmute::<usize, unsafe extern "C" fn()>(handler::<F> as usize)
+                    }
+                }
+            }
+        });
+    }
+}
+
+pub fn hap_impl(args: TokenStream, input: TokenStream) -> TokenStream {
+    let attr_args = match NestedMeta::parse_meta_list(args.into()) {
+        Ok(a) => a,
+        Err(e) => return TokenStream::from(Error::from(e).write_errors()),
+    };
+
+    let opts = match HapOpts::from_list(&attr_args) {
+        Ok(o) => o,
+        Err(e) => return TokenStream::from(e.write_errors()),
+    };
+
+    let input = parse_macro_input!(input as ItemStruct);
+
+    let hap = match Hap::new(opts, &input) {
+        Ok(h) => h,
+        Err(e) => return TokenStream::from(e.write_errors()),
+    };
+
+    quote! {
+        #input
+        #hap
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hap, HapOpts};
+    use syn::{parse_quote, ItemStruct};
+
+    fn hap(input: ItemStruct) -> darling::Result<Hap> {
+        Hap::new(
+            HapOpts {
+                name: "Test_Hap".to_string(),
+                index: None,
+                description: None,
+            },
+            &input,
+        )
+    }
+
+    #[test]
+    fn test_param_chars() {
+        let hap = hap(parse_quote! {
+            struct TestHap {
+                a: i32,
+                b: i64,
+                c: *mut ConfObject,
+                d: *mut std::ffi::c_char,
+                e: *mut simics::sys::generic_transaction_t,
+                f: *mut c_void,
+                g: std::ffi::c_int,
+                h: *mut conf_object_t,
+            }
+        })
+        .expect("Failed to parse hap");
+
+        assert_eq!(hap.param_chars, "iIcsmvic");
+        assert_eq!(
+            hap.params
+                .iter()
+                .map(|p| p.name.to_string())
+                .collect::<Vec<_>>(),
+            ["a", "b", "c", "d", "e", "f", "g", "h"]
+        );
+    }
+
+    #[test]
+    fn test_unit_struct() {
+        let hap = hap(parse_quote! {
+            struct TestHap;
+        })
+        .expect("Failed to parse hap");
+
+        assert!(hap.param_chars.is_empty());
+        assert!(hap.params.is_empty());
+    }
+
+    #[test]
+    fn test_const_pointer() {
+        assert!(hap(parse_quote! {
+            struct TestHap {
+                obj: *const ConfObject,
+            }
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        assert!(hap(parse_quote! {
+            struct TestHap {
+                value: u64,
+            }
+        })
+        .is_err());
+        assert!(hap(parse_quote! {
+            struct TestHap {
+                value: *mut u8,
+            }
+        })
+        .is_err());
+        assert!(hap(parse_quote! {
+            struct TestHap(i32);
+        })
+        .is_err());
+        assert!(hap(parse_quote! {
+            struct TestHap<T> {
+                value: T,
+            }
+        })
+        .is_err());
+    }
+}
@@ -9,7 +9,7 @@ use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use simics_api_sys::{SIM_VERSION, SIM_VERSION_COMPAT};
-use syn::{parse_macro_input, ItemFn, ReturnType, Type};
+use syn::{parse_macro_input, parse_str, ItemFn, Path, ReturnType, Type};
 
 #[derive(Debug, FromMeta)]
 pub struct SimicsInitOpts {
@@ -19,6 +19,14 @@ pub struct SimicsInitOpts {
     #[darling(multiple)]
     /// The list of classes to register. Classes not listed here will not be declared.
     pub class: Vec<String>,
+    #[darling(multiple)]
+    /// The list of hap types declared with `#[hap]` to register before the module is
+    /// initialized
+    pub hap: Vec<String>,
+    #[darling(multiple)]
+    /// The list of custom atom types deriving `Atom` to register before the module is
+    /// initialized
+    pub atom: Vec<String>,
     no_panic_hook: Flag,
 }
 
@@ -111,13 +119,6 @@ pub fn simics_init_impl(args: TokenStream, input: TokenStream) -> TokenStream {
     // Get the original ident and visibility before we change them
     let inner_ident = &input.sig.ident;
 
-    let maybe_expect = &input
-        .sig
-        .output
-        .is_result_type()
-        .then_some(quote!(.expect("Failed while executing init")))
-        .unwrap_or(quote!());
-
     let maybe_ty_generics = (!&input.sig.generics.params.is_empty()).then_some({
         let params = &input.sig.generics.params;
         quote!(::<#params>)
@@ -153,12 +154,70 @@ pub fn simics_init_impl(args: TokenStream, input: TokenStream) -> TokenStream {
         quote!()
     };
 
+    let haps = match opts
+        .hap
+        .iter()
+        .map(|h| parse_str::<Path>(h))
+        .collect::<syn::Result<Vec<_>>>()
+    {
+        Ok(h) => h,
+        Err(e) => return TokenStream::from(Error::from(e).write_errors()),
+    };
+
+    let atoms = match opts
+        .atom
+        .iter()
+        .map(|a| parse_str::<Path>(a))
+        .collect::<syn::Result<Vec<_>>>()
+    {
+        Ok(a) => a,
+        Err(e) => return TokenStream::from(Error::from(e).write_errors()),
+    };
+
+    let init = quote!(#inner_ident #maybe_ty_generics(#(#args),*));
+
+    // Types registered before the module is initialized fail initialization the same way
+    // an error returned from the init function does
+    let call_init = if haps.is_empty() && atoms.is_empty() {
+        if input.sig.output.is_result_type() {
+            quote!(#init.expect("Failed while executing init");)
+        } else {
+            quote!(#init;)
+        }
+    } else {
+        let register = quote! {
+            #(<#haps as simics::CustomHap>::register()?;)*
+            #(<#atoms as simics::AtomType>::register()?;)*
+        };
+
+        if input.sig.output.is_result_type() {
+            let output = &input.sig.output;
+
+            quote! {
+                (|| #output {
+                    #register
+                    #init
+                })()
+                .expect("Failed while executing init");
+            }
+        } else {
+            quote! {
+                (|| -> simics::Result<()> {
+                    #register
+                    Ok(())
+                })()
+                .expect("Failed while executing init");
+                #init;
+            }
+        }
+    };
+
     let wrapper = quote! {
         #[no_mangle]
         /// Exported symbol called by simics when module is loaded
         pub extern "C" fn _simics_module_init() {
             #maybe_set_panic_hook
-            #inner_ident #maybe_ty_generics(#(#args),*) #maybe_expect;
+            #call_init
         }
     };
 
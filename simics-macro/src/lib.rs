@@ -7,21 +7,27 @@
 #![deny(missing_docs)]
 #![forbid(unsafe_code)]
 
+use atom::atom_impl;
 use attr_value::{
     from_attr_value_dict_impl, from_attr_value_list_impl, into_attr_value_dict_impl,
     into_attr_value_list_impl,
 };
+use bank::bank_impl;
 use class::{class_derive_impl, class_impl};
 use conf_object::{as_conf_object_impl, from_conf_object_impl};
 use exception::simics_exception_impl;
+use hap::hap_impl;
 use init::simics_init_impl;
 use interface::interface_impl;
 use proc_macro::TokenStream;
 
+mod atom;
 mod attr_value;
+mod bank;
 mod class;
 mod conf_object;
 mod exception;
+mod hap;
 mod init;
 mod interface;
 
@@ -104,10 +110,113 @@ pub fn Class(input: TokenStream) -> TokenStream {
 
 #[proc_macro_attribute]
 /// Attribute macro for declaring a Simics class for a Rust struct type
+///
+/// # Attributes
+///
+/// Fields of the struct are registered as attributes of the class with
+/// `#[class(attribute(optional))]`, where exactly one of `required`, `optional` or `pseudo`
+/// must be given. Required and optional attributes may additionally be marked `persistent`,
+/// like `#[class(attribute(optional, persistent))]`, for state such as the contents of a
+/// disk image which is saved separately from the configuration by `write_persistent_state`.
+///
+/// # Ports
+///
+/// Port objects are declared with `#[class(port(name = "bank.regs", class = "RegsPort"))]`.
+/// Each object of the class gets an object of the port class as the child `bank.regs`. A
+/// name ending with an array size, like `port.irq[8]`, declares an array of port objects.
+/// The port class is created when the class is created unless a class with its name
+/// already exists, so interfaces may be registered on it beforehand. Without a `class`, a
+/// simple port with an anonymous class is registered, which can be retrieved with
+/// `class_port`. An optional `description` documents the port.
+///
+/// A port class declared with `#[class(parent = "Device")]` gets `parent` and `parent_mut`
+/// methods returning the `Device` object the port object belongs to.
+///
+/// # Notifiers
+///
+/// Notifier types notified by objects of the class are declared with
+/// `#[class(notifier(name = "threshold-reached", description = "..."))]`, which registers
+/// the notifier on the class when it is created. The notifier type is created if it does not
+/// already exist.
+///
+/// # Clocks
+///
+/// A class declared with `#[class(clock)]` is registered as a clock when it is created, so
+/// other objects can use its objects as their queue. The struct must implement the `Clock`
+/// trait, which provides the `cycle` interface and the `frequency` and `freq_mhz` attributes
+/// of the class.
 pub fn class(args: TokenStream, input: TokenStream) -> TokenStream {
     class_impl(args, input)
 }
 
+#[proc_macro_attribute]
+/// Attribute macro for declaring a Rust struct as a bank of memory mapped registers.
+///
+/// Each register is a field of the struct storing the value of the register, which must be
+/// of type `u8`, `u16`, `u32` or `u64`. The macro
+/// implements the `RegisterBank` trait, which dispatches reads and writes to the registers,
+/// and the `TransactionTarget` trait, through which the bank is accessed. The registers of
+/// the bank, its `transaction` and `bank_instrumentation_subscribe` interfaces, and an
+/// attribute for checkpointing each register are registered by `RegisterBank::register_bank`.
+///
+/// The macro also implements `Default` for the struct, initializing each register to its
+/// reset value, so the struct must not derive `Default`.
+///
+/// # Arguments
+///
+/// At the field level, the following attributes are supported:
+///
+/// * `#[register(offset = 0x10)]` - Declare the field as a register at the given offset in
+///   the bank. The register additionally accepts the following arguments:
+///   * `size = 4` - The size of the register in bytes, by default the size of the field
+///     type. A register narrower than its field type uses the low bytes of the field.
+///   * `reset = 0x1` - The value of the register after reset, by default 0
+///   * `name = "name"` - The name of the register, by default the name of the field
+///   * `on_read = "method"` - A method `fn(&mut self, value: u64) -> u64` called with the
+///     stored value of the register when it is read, returning the value to read
+///   * `on_write = "method"` - A method `fn(&mut self, old: u64, new: u64)` called after the
+///     register is written
+/// * `#[field(name = "name", bits = 3..=5, access = "rw1c")]` - Declare a field of a
+///   register, spanning a single bit or an inclusive range of bits. The access type is one of
+///   `rw` (the default), `ro`, `wo`, `rw1c`, `rw1s` or `rc`. Getter and setter methods named
+///   `{register}_{field}` and `set_{register}_{field}` are generated for each field.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[class(name = "my_device")]
+/// #[bank]
+/// #[derive(FromConfObject)]
+/// struct MyDevice {
+///     #[register(offset = 0x0, size = 4, reset = 0x1)]
+///     #[field(name = "enable", bits = 0, access = "rw")]
+///     #[field(name = "error", bits = 3..=5, access = "rw1c")]
+///     control: u32,
+/// }
+/// ```
+pub fn bank(args: TokenStream, input: TokenStream) -> TokenStream {
+    bank_impl(args, input)
+}
+
+#[allow(non_snake_case)]
+#[proc_macro_derive(Atom, attributes(atom))]
+/// Derive macro for registering a Rust type as a custom transaction atom type. This macro
+/// implements the `AtomType` trait for the annotated type, whose values are carried inline
+/// in the atoms of a transaction. The type must be `Copy` and no larger than a pointer.
+///
+/// # Arguments
+///
+/// At the item level (i.e. on the type deriving this attribute), the following attributes
+/// are supported:
+///
+/// * `#[atom(name = "name")]` - The name of the atom type. This is required.
+///
+/// The atom type must be registered when the module is loaded, by listing it in the `atom`
+/// option of `#[simics_init]` or by calling `AtomType::register`.
+pub fn Atom(input: TokenStream) -> TokenStream {
+    atom_impl(input)
+}
+
 #[allow(non_snake_case)]
 #[proc_macro_derive(AsConfObject, attributes(conf_object))]
 /// Derive macro for implementing conversion to raw `ConfObject` pointers.
@@ -177,12 +286,47 @@ pub fn simics_exception(args: TokenStream, input: TokenStream) -> TokenStream {
     simics_exception_impl(args, input)
 }
 
+#[proc_macro_attribute]
+/// Attribute macro for declaring a custom hap type, whose parameters are the fields of the
+/// annotated struct. This macro implements the `Hap` and `CustomHap` traits for the struct,
+/// providing typed `occurred`, `occurred_always` and `add_callback` methods.
+///
+/// Fields must be of type `i32`, `i64`, `*mut ConfObject`, `*mut c_char`,
+/// `*mut generic_transaction_t` or `*mut c_void`.
+///
+/// # Arguments
+///
+/// * `name = "name"` - The name of the hap type. This is required.
+/// * `index = "description"` - A description of the index value of the hap, if it has one
+/// * `description = "description"` - A description of the hap, by default its name
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// #[hap(name = "My_Threshold_Reached", index = "threshold")]
+/// struct ThresholdReached {
+///     value: i64,
+///     source: *mut ConfObject,
+/// }
+///
+/// ThresholdReached::occurred_always(Some(obj), 0, ThresholdReached { value: 10, source })?;
+/// ```
+pub fn hap(args: TokenStream, input: TokenStream) -> TokenStream {
+    hap_impl(args, input)
+}
+
 #[proc_macro_attribute]
 /// Mark a function as being the initializer of a Simics module.
 ///
 /// This function will be called on module load and should be used to initialize the
 /// module. This macro will add the requisite
 /// code to call the function on module load.
+///
+/// Hap types declared with `#[hap]` are registered before the function is called when
+/// they are listed in the arguments, like `#[simics_init(name = "module", hap = "MyHap")]`.
+/// Likewise, custom atom types deriving `Atom` are registered when they are listed like
+/// `#[simics_init(name = "module", atom = "MyAtom")]`. If registering a hap or atom type
+/// fails, initialization fails the same way as when the function returns an error.
 pub fn simics_init(args: TokenStream, input: TokenStream) -> TokenStream {
     simics_init_impl(args, input)
 }
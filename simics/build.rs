@@ -59,6 +59,7 @@ struct HapStruct {
     callback_attrs: Vec<Attribute>,
     struct_name: Ident,
     closure_param_names: Vec<Ident>,
+    closure_params: Vec<Type>,
     inputs: Vec<BareFnArg>,
     output: Type,
     userdata_name: Ident,
@@ -193,6 +194,7 @@ impl TryFrom<(&ItemConst, &ItemType)> for HapStruct {
             callback_attrs,
             struct_name,
             closure_param_names,
+            closure_params,
             inputs,
             output,
             userdata_name,
@@ -207,54 +209,60 @@ impl ToTokens for HapStruct {
         let add_callback_methods = quote! {
             /// Add a callback to be called on each occurrence of this HAP. The callback may capture its environment.
             ///
+            /// The callback is deleted and the closure is freed when the returned [`HapSubscription`](crate::api::simulator::hap_consumer::HapSubscription) is dropped,
+            /// unless the subscription is made permanent with [`HapSubscription::forget`](crate::api::simulator::hap_consumer::HapSubscription::forget).
+            ///
             /// # Arguments
             ///
-            /// * `callback` - The closure to fire as a callback. The closure will be doubly boxed. Any program state accessed inside
+            /// * `callback` - The closure to fire as a callback. The closure will be boxed. Any program state accessed inside
             ///   the closure must have the static lifetime. This is not enforced by the compiler, it is up to the programmer to ensure
             ///   the soundness of their callback code.
-            pub fn add_callback<F>(callback: F) -> crate::Result<crate::api::simulator::hap_consumer::HapHandle>
+            pub fn add_callback<F>(callback: F) -> crate::Result<crate::api::simulator::hap_consumer::HapSubscription>
             where
                 F: #(#callback_ty)+*,
             {
-                let callback = Box::new(callback);
-                let callback_box = Box::new(callback);
-                let callback_raw = Box::into_raw(callback_box);
+                let name = Self::NAME.as_raw_cstr()?;
+                let callback_raw = crate::api::simulator::hap_consumer::HapClosure::new(callback);
                 let handler: unsafe extern "C" fn() = unsafe { std::mem::transmute(#handler_name::<F> as usize) };
-                Ok(unsafe {
+                let handle = unsafe {
                     crate::api::sys::SIM_hap_add_callback(
-                        Self::NAME.as_raw_cstr()?,
+                        name,
                         Some(handler),
                         callback_raw as *mut std::ffi::c_void,
                     )
-                })
+                };
+                unsafe { crate::api::simulator::hap_consumer::HapSubscription::new::<Self, F>(handle, None, callback_raw) }
             }
 
             /// Add a callback to be called on each occurrence of this HAP for a specific object. The callback may capture its environment.
             ///
+            /// The callback is deleted and the closure is freed when the returned [`HapSubscription`](crate::api::simulator::hap_consumer::HapSubscription) is dropped,
+            /// unless the subscription is made permanent with [`HapSubscription::forget`](crate::api::simulator::hap_consumer::HapSubscription::forget).
+            ///
             /// # Arguments
             ///
-            /// * `callback` - The closure to fire as a callback. The closure will be doubly boxed. Any program state accessed inside
+            /// * `callback` - The closure to fire as a callback. The closure will be boxed. Any program state accessed inside
             ///   the closure must have the static lifetime. This is not enforced by the compiler, it is up to the programmer to ensure
             ///   the soundness of their callback code.
             /// * `obj` - The object to fire this callback for. This HAP will not trigger the callback when firing on any object other than
             ///   this one.
-            pub fn add_callback_object<F>(callback: F, obj: *mut crate::api::ConfObject) -> crate::Result<crate::api::simulator::hap_consumer::HapHandle>
+            pub fn add_callback_object<F>(callback: F, obj: *mut crate::api::ConfObject) -> crate::Result<crate::api::simulator::hap_consumer::HapSubscription>
             where
                 F: #(#callback_ty)+*,
             {
-                let callback = Box::new(callback);
-                let callback_box = Box::new(callback);
-                let callback_raw = Box::into_raw(callback_box);
+                let name = Self::NAME.as_raw_cstr()?;
+                let callback_raw = crate::api::simulator::hap_consumer::HapClosure::new(callback);
                 let handler: unsafe extern "C" fn() = unsafe { std::mem::transmute(#handler_name::<F> as usize) };
-                Ok(unsafe {
+                let handle = unsafe {
                     crate::api::sys::SIM_hap_add_callback_obj(
-                        Self::NAME.as_raw_cstr()?,
+                        name,
                         obj,
                         0,
                         Some(handler),
                         callback_raw as *mut std::ffi::c_void,
                     )
-                })
+                };
+                unsafe { crate::api::simulator::hap_consumer::HapSubscription::new::<Self, F>(handle, Some(obj), callback_raw) }
             }
         };
 
@@ -272,122 +280,133 @@ impl ToTokens for HapStruct {
             quote! {
                 /// Add a callback to be called on each occurrence of this HAP for a specific index value. The callback may capture its environment.
                 ///
+                /// The callback is deleted and the closure is freed when the returned [`HapSubscription`](crate::api::simulator::hap_consumer::HapSubscription) is dropped,
+                /// unless the subscription is made permanent with [`HapSubscription::forget`](crate::api::simulator::hap_consumer::HapSubscription::forget).
+                ///
                 /// Only HAPs which support an index may add a callback in this manner, and the index varies for each HAP. For example, the
                 /// [`CoreMagicInstructionHap`] supports an index equal to the magic value.
                 ///
                 /// # Arguments
                 ///
-                /// * `callback` - The closure to fire as a callback. The closure will be doubly boxed. Any program state accessed inside
+                /// * `callback` - The closure to fire as a callback. The closure will be boxed. Any program state accessed inside
                 ///   the closure must have the static lifetime. This is not enforced by the compiler, it is up to the programmer to ensure
                 ///   the soundness of their callback code.
                 #[doc = #index_doc]
-                pub fn add_callback_index<F>(callback: F, index: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapHandle>
+                pub fn add_callback_index<F>(callback: F, index: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapSubscription>
                 where
                     F: #(#callback_ty)+*,
                 {
-                    let callback = Box::new(callback);
-                    let callback_box = Box::new(callback);
-                    let callback_raw = Box::into_raw(callback_box);
+                    let name = Self::NAME.as_raw_cstr()?;
+                    let callback_raw = crate::api::simulator::hap_consumer::HapClosure::new(callback);
                     let handler: unsafe extern "C" fn() = unsafe { std::mem::transmute(#handler_name::<F> as usize) };
-                    Ok(unsafe {
+                    let handle = unsafe {
                         crate::api::sys::SIM_hap_add_callback_index(
-                            Self::NAME.as_raw_cstr()?,
+                            name,
                             Some(handler),
                             callback_raw as *mut std::ffi::c_void,
                             index
                         )
-                    })
+                    };
+                    unsafe { crate::api::simulator::hap_consumer::HapSubscription::new::<Self, F>(handle, None, callback_raw) }
                 }
 
                 /// Add a callback to be called on each occurrence of this HAP for a specific index value range. The callback may capture its environment.
                 ///
+                /// The callback is deleted and the closure is freed when the returned [`HapSubscription`](crate::api::simulator::hap_consumer::HapSubscription) is dropped,
+                /// unless the subscription is made permanent with [`HapSubscription::forget`](crate::api::simulator::hap_consumer::HapSubscription::forget).
+                ///
                 /// Only HAPs which support an index may add a callback in this manner, and the index varies for each HAP. For example, the
                 /// [`CoreMagicInstructionHap`] supports an index equal to the magic value.
                 ///
                 /// # Arguments
                 ///
-                /// * `callback` - The closure to fire as a callback. The closure will be doubly boxed. Any program state accessed inside
+                /// * `callback` - The closure to fire as a callback. The closure will be boxed. Any program state accessed inside
                 ///   the closure must have the static lifetime. This is not enforced by the compiler, it is up to the programmer to ensure
                 ///   the soundness of their callback code.
                 #[doc = #range_start_doc]
                 #[doc = #range_end_doc]
-                pub fn add_callback_range<F>(callback: F, start: i64, end: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapHandle>
+                pub fn add_callback_range<F>(callback: F, start: i64, end: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapSubscription>
                 where
                     F: #(#callback_ty)+*,
                 {
-                    let callback = Box::new(callback);
-                    let callback_box = Box::new(callback);
-                    let callback_raw = Box::into_raw(callback_box);
+                    let name = Self::NAME.as_raw_cstr()?;
+                    let callback_raw = crate::api::simulator::hap_consumer::HapClosure::new(callback);
                     let handler: unsafe extern "C" fn() = unsafe { std::mem::transmute(#handler_name::<F> as usize) };
-                    Ok(unsafe {
+                    let handle = unsafe {
                         crate::api::sys::SIM_hap_add_callback_range(
-                            Self::NAME.as_raw_cstr()?,
+                            name,
                             Some(handler),
                             callback_raw as *mut std::ffi::c_void,
                             start,
                             end,
                         )
-                    })
+                    };
+                    unsafe { crate::api::simulator::hap_consumer::HapSubscription::new::<Self, F>(handle, None, callback_raw) }
                 }
 
                 /// Add a callback to be called on each occurrence of this HAP on a specific object for a specific index value. The callback may capture its environment.
                 ///
+                /// The callback is deleted and the closure is freed when the returned [`HapSubscription`](crate::api::simulator::hap_consumer::HapSubscription) is dropped,
+                /// unless the subscription is made permanent with [`HapSubscription::forget`](crate::api::simulator::hap_consumer::HapSubscription::forget).
+                ///
                 /// Only HAPs which support an index may add a callback in this manner, and the index varies for each HAP. For example, the
                 /// [`CoreMagicInstructionHap`] supports an index equal to the magic value.
                 ///
                 /// # Arguments
                 ///
-                /// * `callback` - The closure to fire as a callback. The closure will be doubly boxed. Any program state accessed inside
+                /// * `callback` - The closure to fire as a callback. The closure will be boxed. Any program state accessed inside
                 ///   the closure must have the static lifetime. This is not enforced by the compiler, it is up to the programmer to ensure
                 ///   the soundness of their callback code.
                 /// * `obj` - The object to fire this callback for. This HAP will not trigger the callback when firing on any object other than
                 ///   this one.
                 #[doc = #index_doc]
-                pub fn add_callback_object_index<F>(callback: F, obj: *mut crate::api::ConfObject, index: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapHandle>
+                pub fn add_callback_object_index<F>(callback: F, obj: *mut crate::api::ConfObject, index: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapSubscription>
                 where
                     F: #(#callback_ty)+*,
                 {
-                    let callback = Box::new(callback);
-                    let callback_box = Box::new(callback);
-                    let callback_raw = Box::into_raw(callback_box);
+                    let name = Self::NAME.as_raw_cstr()?;
+                    let callback_raw = crate::api::simulator::hap_consumer::HapClosure::new(callback);
                     let handler: unsafe extern "C" fn() = unsafe { std::mem::transmute(#handler_name::<F> as usize) };
-                    Ok(unsafe {
+                    let handle = unsafe {
                         crate::api::sys::SIM_hap_add_callback_obj_index(
-                            Self::NAME.as_raw_cstr()?,
+                            name,
                             obj,
                             0,
                             Some(handler),
                             callback_raw as *mut std::ffi::c_void,
                             index
                         )
-                    })
+                    };
+                    unsafe { crate::api::simulator::hap_consumer::HapSubscription::new::<Self, F>(handle, Some(obj), callback_raw) }
                 }
 
                 /// Add a callback to be called on each occurrence of this HAP on a specific object for a specific index value range. The callback may capture its environment.
                 ///
+                /// The callback is deleted and the closure is freed when the returned [`HapSubscription`](crate::api::simulator::hap_consumer::HapSubscription) is dropped,
+                /// unless the subscription is made permanent with [`HapSubscription::forget`](crate::api::simulator::hap_consumer::HapSubscription::forget).
+                ///
                 /// Only HAPs which support an index may add a callback in this manner, and the index varies for each HAP. For example, the
                 /// [`CoreMagicInstructionHap`] supports an index equal to the magic value.
                 ///
                 /// # Arguments
                 ///
-                /// * `callback` - The closure to fire as a callback. The closure will be doubly boxed. Any program state accessed inside
+                /// * `callback` - The closure to fire as a callback. The closure will be boxed. Any program state accessed inside
                 ///   the closure must have the static lifetime. This is not enforced by the compiler, it is up to the programmer to ensure
                 ///   the soundness of their callback code.
                 /// * `obj` - The object to fire this callback for. This HAP will not trigger the callback when firing on any object other than
                 ///   this one.
                 #[doc = #range_start_doc]
                 #[doc = #range_end_doc]
-                pub fn add_callback_object_range<F>(callback: F, obj: *mut crate::api::ConfObject, start: i64, end: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapHandle>
+                pub fn add_callback_object_range<F>(callback: F, obj: *mut crate::api::ConfObject, start: i64, end: i64) -> crate::Result<crate::api::simulator::hap_consumer::HapSubscription>
                 where
                     F: #(#callback_ty)+*,
                 {
-                    let callback = Box::new(callback);
-                    let callback_box = Box::new(callback);
-                    let callback_raw = Box::into_raw(callback_box);
+                    let name = Self::NAME.as_raw_cstr()?;
+                    let callback_raw = crate::api::simulator::hap_consumer::HapClosure::new(callback);
                     let handler: unsafe extern "C" fn() = unsafe { std::mem::transmute(#handler_name::<F> as usize) };
-                    Ok(unsafe {
+                    let handle = unsafe {
                         crate::api::sys::SIM_hap_add_callback_obj_range(
-                            Self::NAME.as_raw_cstr()?,
+                            name,
                             obj,
                             0,
                             Some(handler),
@@ -395,8 +414,33 @@ impl ToTokens for HapStruct {
                             start,
                             end,
                         )
+                    };
+                    unsafe { crate::api::simulator::hap_consumer::HapSubscription::new::<Self, F>(handle, Some(obj), callback_raw) }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        // NOTE: Streams can only be created for HAPs whose callbacks do not return a value
+        let maybe_stream_methods = if matches!(&self.output, Type::Tuple(t) if t.elems.is_empty()) {
+            let closure_param_names = &self.closure_param_names;
+            let closure_params = &self.closure_params;
+
+            quote! {
+                /// Get a stream of the occurrences of this HAP, yielding the parameters of each occurrence. The callback feeding the
+                /// stream is deleted when the stream is dropped.
+                pub fn stream() -> crate::Result<crate::api::simulator::callback_stream::HapStream<(#(#closure_params,)*)>> {
+                    crate::api::simulator::callback_stream::CallbackStream::new(|sender| {
+                        Self::add_callback(move |#(#closure_param_names),*| sender.send((#(#closure_param_names,)*)))
                     })
                 }
+
+                /// Get a future resolving to the parameters of the next occurrence of this HAP. The callback feeding the future is
+                /// deleted when the future is dropped.
+                pub fn next_occurrence() -> crate::Result<crate::api::simulator::callback_stream::HapOccurrence<(#(#closure_params,)*)>> {
+                    Ok(Self::stream()?.next_occurrence())
+                }
             }
         } else {
             quote! {}
@@ -423,17 +467,23 @@ impl ToTokens for HapStruct {
             impl #struct_name {
                 #add_callback_methods
                 #maybe_index_callback_methods
+                #maybe_stream_methods
             }
 
-            /// The handler for HAPs of a specific type. Unboxes a boxed
-            /// closure and calls it with the correct HAP callback arguments
+            /// The handler for HAPs of a specific type. Calls the closure of a
+            /// callback with the correct HAP callback arguments
             extern "C" fn #handler_name<F>(#(#inputs),*) -> #output
             where
                 F: #(#callback_ty)+*,
             {
-                // NOTE: This box must be leaked, because we may call this closure again, we cannot drop it
-                let closure = Box::leak(unsafe { Box::from_raw(#userdata_name as *mut Box<F>) });
-                closure(#(#closure_param_names),*)
+                // NOTE: The closure is owned by its subscription and freed when it is dropped and
+                // no longer running
+                unsafe {
+                    crate::api::simulator::hap_consumer::HapClosure::<F>::call(
+                        #userdata_name as *mut std::ffi::c_void,
+                        move |closure| closure(#(#closure_param_names),*),
+                    )
+                }
             }
         });
     }
@@ -522,7 +572,11 @@ impl ToTokens for Haps {
 pub struct InterfaceMethod {
     vis: Visibility,
     wrapper_inputs: Vec<BareFnArg>,
+    input_names: Vec<Ident>,
     output: Type,
+    raw_output: ReturnType,
+    obj_ty: Option<Type>,
+    variadic: bool,
     some_name: Ident,
     name_ident: Ident,
     ok_value: Expr,
@@ -595,6 +649,10 @@ impl TryFrom<&Field> for InterfaceMethod {
             .cloned()
             .collect::<Vec<_>>();
 
+        let obj_ty = has_obj
+            .then(|| inputs.first().map(|f| f.ty.clone()))
+            .flatten();
+
         let (is_attr_value, output) = match &proto.output {
             ReturnType::Default => (false, parse_quote!(())),
             ReturnType::Type(_, t) => {
@@ -626,7 +684,11 @@ impl TryFrom<&Field> for InterfaceMethod {
         Ok(Self {
             vis,
             wrapper_inputs,
+            input_names,
             output,
+            raw_output: proto.output.clone(),
+            obj_ty,
+            variadic: proto.variadic.is_some(),
             some_name,
             name_ident,
             ok_value,
@@ -658,8 +720,73 @@ impl ToTokens for InterfaceMethod {
     }
 }
 
+impl InterfaceMethod {
+    /// Why the method cannot be implemented by a Rust object, if it cannot. This requires
+    /// that the method receives the object it is called on and is not variadic.
+    fn unimplementable_reason(&self) -> Option<&'static str> {
+        if self.obj_ty.is_none() {
+            Some("it does not receive the object it is called on")
+        } else if self.variadic {
+            Some("it is variadic")
+        } else {
+            None
+        }
+    }
+
+    /// Whether the method can be implemented by a Rust object
+    fn is_implementable(&self) -> bool {
+        self.unimplementable_reason().is_none()
+    }
+
+    /// Generate the declaration of this method in the implementer-side trait
+    fn impl_trait_method(&self) -> TokenStream2 {
+        let name_ident = &self.name_ident;
+        let wrapper_inputs = &self.wrapper_inputs;
+        let raw_output = &self.raw_output;
+
+        quote! {
+            /// Automatically generated method for implementing the interface
+            fn #name_ident(&mut self, #(#wrapper_inputs),*) #raw_output;
+        }
+    }
+
+    /// Generate the trampoline for this method, which dispatches a call through the C
+    /// interface to the implementation of the method for the Rust object `T`
+    fn impl_trampoline(&self, impl_trait_name: &Ident) -> TokenStream2 {
+        let name_ident = &self.name_ident;
+        let trampoline_ident = format_ident!("{}_trampoline", self.name_ident);
+        let wrapper_inputs = &self.wrapper_inputs;
+        let input_names = &self.input_names;
+        let raw_output = &self.raw_output;
+        let obj_ty = &self.obj_ty;
+
+        quote! {
+            extern "C" fn #trampoline_ident<T>(self_obj_ptr: #obj_ty, #(#wrapper_inputs),*) #raw_output
+            where
+                T: #impl_trait_name + crate::api::traits::FromConfObject,
+            {
+                let self_obj = unsafe {
+                    <T as crate::api::traits::FromConfObject>::from_conf_object_mut(
+                        self_obj_ptr as *mut crate::api::ConfObject,
+                    )
+                };
+                <T as #impl_trait_name>::#name_ident(self_obj, #(#input_names),*)
+            }
+        }
+    }
+
+    /// Generate the initializer of the interface structure field for this method
+    fn impl_field(&self) -> TokenStream2 {
+        let name_ident = &self.name_ident;
+        let trampoline_ident = format_ident!("{}_trampoline", self.name_ident);
+
+        quote!(#name_ident: Some(#trampoline_ident::<T>),)
+    }
+}
+
 pub struct InterfaceStruct {
     struct_name: Ident,
+    impl_trait_name: Ident,
     interface_ident: Ident,
     interface_methods: Vec<InterfaceMethod>,
     name_ident: Ident,
@@ -684,10 +811,15 @@ impl TryFrom<(&ItemConst, &ItemStruct)> for InterfaceStruct {
             .collect::<Vec<_>>();
         let camel_name = name.ident.to_string().snake_to_camel();
         let struct_name = format_ident!("{camel_name}");
+        let impl_trait_name = format_ident!(
+            "{}Impl",
+            camel_name.strip_suffix("Interface").unwrap_or(&camel_name)
+        );
         let interface_ident = interface.ident.clone();
         let name_ident = name.ident.clone();
         Ok(Self {
             struct_name,
+            impl_trait_name,
             interface_ident,
             interface_methods,
             name_ident,
@@ -698,9 +830,45 @@ impl TryFrom<(&ItemConst, &ItemStruct)> for InterfaceStruct {
 impl ToTokens for InterfaceStruct {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let struct_name = &self.struct_name;
+        let impl_trait_name = &self.impl_trait_name;
         let interface_ident = &self.interface_ident;
         let interface_methods = &self.interface_methods;
         let name_ident = &self.name_ident;
+        let implementable_methods = interface_methods
+            .iter()
+            .filter(|m| m.is_implementable())
+            .collect::<Vec<_>>();
+        let impl_trait_methods = implementable_methods
+            .iter()
+            .map(|m| m.impl_trait_method())
+            .collect::<Vec<_>>();
+        let impl_trampolines = implementable_methods
+            .iter()
+            .map(|m| m.impl_trampoline(impl_trait_name))
+            .collect::<Vec<_>>();
+        let impl_fields = implementable_methods
+            .iter()
+            .map(|m| m.impl_field())
+            .collect::<Vec<_>>();
+        // Methods which cannot be implemented are left unset in the interface structure, so
+        // they are listed in the documentation of the implementer-side trait
+        let unimplementable_docs = interface_methods
+            .iter()
+            .filter_map(|m| {
+                m.unimplementable_reason()
+                    .map(|reason| format!(" * `{}`, because {reason}", m.name))
+            })
+            .collect::<Vec<_>>();
+        let unimplementable_doc = (!unimplementable_docs.is_empty()).then(|| {
+            quote! {
+                ///
+                /// The following methods of the interface cannot be implemented by a Rust object
+                /// and are left unset (`NULL`) in the interface structure registered for it, so
+                /// callers must not call them on the object:
+                ///
+                #(#[doc = #unimplementable_docs])*
+            }
+        });
 
         tokens.extend(quote! {
             /// Automatically generated structure for the interface
@@ -723,6 +891,26 @@ impl ToTokens for InterfaceStruct {
                     Self { obj, interface }
                 }
             }
+
+            /// Automatically generated trait for implementing the interface on a Rust object
+            #unimplementable_doc
+            pub trait #impl_trait_name {
+                #(#impl_trait_methods)*
+            }
+
+            impl<T> crate::api::traits::BuiltinInterface<T> for #struct_name
+            where
+                T: #impl_trait_name + crate::api::traits::FromConfObject + 'static,
+            {
+                fn internal_interface() -> Self::InternalInterface {
+                    #(#impl_trampolines)*
+
+                    crate::api::sys::#interface_ident {
+                        #(#impl_fields)*
+                        ..Default::default()
+                    }
+                }
+            }
         });
     }
 }
@@ -776,7 +964,7 @@ impl ToTokens for Interfaces {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         let interface_structs = &self.interface_structs;
         tokens.extend(quote! {
-            #[allow(dead_code, non_snake_case)]
+            #[allow(dead_code, non_snake_case, clippy::needless_update)]
             /// Automatically generated interfaces from the base package
             pub mod interfaces {
                 use crate::api::sys::*;
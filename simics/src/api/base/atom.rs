@@ -0,0 +1,300 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transaction atoms
+//!
+//! A `transaction_t` carries its properties as a list of atoms. Each atom type has a name, an
+//! id assigned by the simulator, and a value which is stored inline in the atom.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    simics_exception,
+    sys::{
+        atom_id_t, atom_t, generic_transaction_t, pcie_at_t, pcie_ecs_t, pcie_message_type_t,
+        pcie_msg_route_t, pcie_pasid_info_t, pcie_type_t, transaction_completion_t, transaction_t,
+        SIM_register_python_atom_type, VT_get_atom_class_id, VT_lookup_transaction_atom,
+        VT_register_atom_class,
+    },
+    ConfObject, Result, TransactionFlags,
+};
+use raw_cstr::raw_cstr;
+use std::{
+    ffi::c_void,
+    mem::size_of,
+    ptr::{copy_nonoverlapping, null},
+};
+
+/// Alias for `atom_id_t`
+pub type AtomId = atom_id_t;
+
+#[simics_exception]
+/// Get the id of an atom type by name
+///
+/// # Arguments
+///
+/// * `name` - The name of the atom type, for example `"data"` or `"pcie_type"`
+///
+/// # Return Value
+///
+/// The id of the atom type
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_atom_class_id<S>(name: S) -> Result<AtomId>
+where
+    S: AsRef<str>,
+{
+    Ok(unsafe { VT_get_atom_class_id(raw_cstr(name)?) })
+}
+
+#[simics_exception]
+/// Register a new atom class with the simulator. The payload of atoms of this class is a
+/// plain value stored inline in the atom, which the simulator copies as is and never
+/// dereferences. Atoms of this class cannot be read or created from Python.
+///
+/// # Arguments
+///
+/// * `name` - The name of the atom class, which must not already be registered
+///
+/// # Context
+///
+/// Global Context
+pub fn register_atom_class<S>(name: S) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    unsafe { VT_register_atom_class(raw_cstr(name)?, null(), null()) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Register a new atom type whose payload is a Python object. The payload of atoms of this
+/// type is a `PyObject *`, which the simulator reference counts when transactions carrying
+/// the atom are copied, deferred or read from Python. Atom types carrying Rust values must be
+/// registered with [`register_atom_class`] instead.
+///
+/// # Arguments
+///
+/// * `name` - The name of the atom type, which must not already be registered
+///
+/// # Context
+///
+/// Global Context
+pub fn register_python_atom_type<S>(name: S) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    unsafe { SIM_register_python_atom_type(raw_cstr(name)?) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Look up an atom in a transaction or any of the transactions it is chained to
+///
+/// # Arguments
+///
+/// * `t` - The transaction to search
+/// * `id` - The id of the atom type to look up
+///
+/// # Return Value
+///
+/// A pointer to the atom, or `None` if the transaction does not contain an atom of this type
+///
+/// # Context
+///
+/// All Contexts
+pub fn lookup_transaction_atom(t: *mut transaction_t, id: AtomId) -> Option<*const atom_t> {
+    let atom = unsafe { VT_lookup_transaction_atom(t, id) };
+    (!atom.is_null()).then_some(atom)
+}
+
+/// Encode a value into the pointer-sized payload of an atom
+pub(crate) fn encode_atom_value<T>(value: T) -> usize
+where
+    T: Copy,
+{
+    const { assert!(size_of::<T>() <= size_of::<usize>()) };
+    let mut raw = 0usize;
+    unsafe {
+        copy_nonoverlapping(
+            &value as *const T as *const u8,
+            &mut raw as *mut usize as *mut u8,
+            size_of::<T>(),
+        )
+    };
+    raw
+}
+
+/// Decode a value from the pointer-sized payload of an atom
+///
+/// # Safety
+///
+/// `raw` must have been produced by encoding a valid value of type `T`
+pub(crate) unsafe fn decode_atom_value<T>(raw: usize) -> T
+where
+    T: Copy,
+{
+    const { assert!(size_of::<T>() <= size_of::<usize>()) };
+    (&raw as *const usize as *const T).read_unaligned()
+}
+
+/// A type of transaction atom. The value of an atom is stored inline in the atom, so it must
+/// be a `Copy` type no larger than a pointer.
+///
+/// Custom atom types can be declared by deriving [`AtomType`] with the `Atom` derive macro:
+///
+/// ```rust,ignore
+/// #[derive(Clone, Copy, Atom)]
+/// #[atom(name = "vendor_stream_id")]
+/// struct StreamId(u32);
+///
+/// #[simics_init(name = "interconnect", atom = "StreamId")]
+/// fn init() {}
+///
+/// let mut t = Transaction::builder().read(4).atom::<StreamId>(StreamId(7)).build()?;
+/// assert_eq!(t.get_atom::<StreamId>()?.map(|s| s.0), Some(7));
+/// ```
+pub trait AtomType {
+    /// The type of the value carried by atoms of this type
+    type Value: Copy;
+    /// The name of the atom type
+    const NAME: &'static str;
+
+    /// Get the id of this atom type
+    fn id() -> Result<AtomId>;
+
+    /// Register this atom type with the simulator and get its id. Custom atom types must be
+    /// registered once, when the module declaring them is loaded and before any transaction
+    /// carrying them is created, for example by listing them in the `atom` option of
+    /// `#[simics_init]`. Core atom types are registered by the simulator.
+    fn register() -> Result<AtomId> {
+        register_atom_class(Self::NAME)?;
+        Self::id()
+    }
+
+    /// Create a raw atom of this type carrying `value`
+    fn atom(value: Self::Value) -> Result<atom_t> {
+        Ok(atom_t {
+            id: Self::id()?,
+            ptr: encode_atom_value(value),
+        })
+    }
+}
+
+#[macro_export]
+/// Declare atom types by name. Each declared type implements [`AtomType`] and caches the id
+/// of the atom type the first time it is looked up.
+///
+/// ```rust,ignore
+/// atom_types! {
+///     /// The PCIe requester id of a transaction
+///     PcieRequesterIdAtom => "pcie_requester_id": u16,
+/// }
+/// ```
+macro_rules! atom_types {
+    ($($(#[$meta:meta])* $ty:ident => $name:literal : $value:ty),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            pub struct $ty;
+
+            impl $crate::AtomType for $ty {
+                type Value = $value;
+                const NAME: &'static str = $name;
+
+                fn id() -> $crate::Result<$crate::AtomId> {
+                    static ID: std::sync::OnceLock<$crate::AtomId> = std::sync::OnceLock::new();
+
+                    if let Some(id) = ID.get() {
+                        return Ok(*id);
+                    }
+
+                    let id = $crate::get_atom_class_id(Self::NAME)?;
+                    Ok(*ID.get_or_init(|| id))
+                }
+            }
+        )*
+    };
+}
+
+atom_types! {
+    /// Terminates an atom list
+    ListEndAtom => "list_end": usize,
+    /// Pointer to the data buffer of a transaction
+    DataAtom => "data": *mut u8,
+    /// The size of a transaction in bytes
+    SizeAtom => "size": u32,
+    /// The flags of a transaction
+    FlagsAtom => "flags": TransactionFlags,
+    /// The object which initiated a transaction
+    InitiatorAtom => "initiator": *mut ConfObject,
+    /// The object which owns a transaction, passed to its completion callback
+    OwnerAtom => "owner": *mut ConfObject,
+    /// The completion callback of a transaction
+    CompletionAtom => "completion": transaction_completion_t,
+    /// Arbitrary user data attached to a transaction
+    UserDataAtom => "user_data": *mut c_void,
+    /// The legacy memory operation a transaction was created from
+    MemopAtom => "memop": *mut generic_transaction_t,
+    /// The PCIe type of a transaction (memory, I/O, config, message)
+    PcieTypeAtom => "pcie_type": pcie_type_t,
+    /// The PCIe requester id of a transaction
+    PcieRequesterIdAtom => "pcie_requester_id": u16,
+    /// The PCIe device id targeted by a transaction
+    PcieDeviceIdAtom => "pcie_device_id": u16,
+    /// The PCIe message type of a message transaction
+    PcieMsgTypeAtom => "pcie_msg_type": pcie_message_type_t,
+    /// The PCIe routing of a message transaction
+    PcieMsgRouteAtom => "pcie_msg_route": pcie_msg_route_t,
+    /// The PCIe extended configuration space information of a transaction
+    PcieEcsAtom => "pcie_ecs": pcie_ecs_t,
+    /// The PCIe address translation type of a transaction
+    PcieAtAtom => "pcie_at": pcie_at_t,
+    /// The PCIe PASID information of a transaction
+    PciePasidAtom => "pcie_pasid": pcie_pasid_info_t,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_atom_value, encode_atom_value};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct StreamId(u32);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Security {
+        _Secure,
+        NonSecure,
+    }
+
+    #[test]
+    fn test_atom_value_round_trip() {
+        let raw = encode_atom_value(StreamId(0xdead_beef));
+        assert_eq!(
+            unsafe { decode_atom_value::<StreamId>(raw) },
+            StreamId(0xdead_beef)
+        );
+
+        let raw = encode_atom_value(Security::NonSecure);
+        assert_eq!(
+            unsafe { decode_atom_value::<Security>(raw) },
+            Security::NonSecure
+        );
+
+        let raw = encode_atom_value(u16::MAX);
+        assert_eq!(raw, u16::MAX as usize);
+        assert_eq!(unsafe { decode_atom_value::<u16>(raw) }, u16::MAX);
+    }
+
+    #[test]
+    fn test_atom_value_pointer() {
+        let mut data = [0u8; 4];
+        let raw = encode_atom_value(data.as_mut_ptr());
+        assert_eq!(raw, data.as_mut_ptr() as usize);
+        assert_eq!(
+            unsafe { decode_atom_value::<*mut u8>(raw) },
+            data.as_mut_ptr()
+        );
+    }
+}
@@ -0,0 +1,454 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Register bank instrumentation
+//!
+//! Register banks let instrumentation tools subscribe to accesses through the
+//! `bank_instrumentation_subscribe` interface. Subscribers are called before and after each
+//! read and write with a handle to the access, through which they can inspect and modify it
+//! using the `bank_before_read`, `bank_after_read`, `bank_before_write` and
+//! `bank_after_write` interfaces.
+
+use crate::{
+    sys::{
+        after_read_callback_t, after_write_callback_t, bank_access_t, bank_after_read_interface_t,
+        bank_after_write_interface_t, bank_before_read_interface_t, bank_before_write_interface_t,
+        bank_callback_handle_t, before_read_callback_t, before_write_callback_t,
+    },
+    ConfObject,
+};
+use std::{ffi::c_void, ptr::null_mut};
+
+/// Alias for `bank_callback_handle_t`
+pub type BankCallbackHandle = bank_callback_handle_t;
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+/// An access to a register bank. Instrumentation callbacks receive a pointer to the access as
+/// an opaque `bank_access_t` handle.
+pub struct BankAccess {
+    offset: u64,
+    size: u64,
+    value: u64,
+    initiator: *mut ConfObject,
+    inquiry: bool,
+    missed: bool,
+    suppressed: bool,
+}
+
+impl BankAccess {
+    /// Create a new access of `size` bytes at `offset` in a bank. For writes, `value` is the
+    /// value being written.
+    pub fn new(
+        offset: u64,
+        size: u64,
+        value: u64,
+        initiator: Option<*mut ConfObject>,
+        inquiry: bool,
+    ) -> Self {
+        Self {
+            offset,
+            size,
+            value,
+            initiator: initiator.unwrap_or(null_mut()),
+            inquiry,
+            missed: false,
+            suppressed: false,
+        }
+    }
+
+    /// Get a mutable reference to an access from its raw handle
+    ///
+    /// # Safety
+    ///
+    /// `handle` must point to a live [`BankAccess`]
+    pub unsafe fn from_raw_mut<'a>(handle: *mut bank_access_t) -> &'a mut Self {
+        &mut *(handle as *mut Self)
+    }
+
+    /// Get the raw handle of this access
+    pub fn as_mut_ptr(&mut self) -> *mut bank_access_t {
+        self as *mut Self as *mut bank_access_t
+    }
+
+    /// The offset of the access in the bank
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Change the offset of the access
+    pub fn set_offset(&mut self, offset: u64) {
+        self.offset = offset;
+    }
+
+    /// The size of the access in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// The value read or written by the access
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Change the value read or written by the access
+    pub fn set_value(&mut self, value: u64) {
+        self.value = value;
+    }
+
+    /// The object which initiated the access, if any
+    pub fn initiator(&self) -> Option<*mut ConfObject> {
+        (!self.initiator.is_null()).then_some(self.initiator)
+    }
+
+    /// Whether the access is an inquiry access, which must not have side effects
+    pub fn is_inquiry(&self) -> bool {
+        self.inquiry
+    }
+
+    /// Turn the access into an inquiry access
+    pub fn inquire(&mut self) {
+        self.inquiry = true;
+    }
+
+    /// Whether the access did not hit any register
+    pub fn missed(&self) -> bool {
+        self.missed
+    }
+
+    /// Change whether the access is reported as missing the bank
+    pub fn set_missed(&mut self, missed: bool) {
+        self.missed = missed;
+    }
+
+    /// Whether the write was suppressed by an instrumentation callback
+    pub fn suppressed(&self) -> bool {
+        self.suppressed
+    }
+
+    /// Suppress the write, so that it does not update the bank
+    pub fn suppress(&mut self) {
+        self.suppressed = true;
+    }
+}
+
+extern "C" fn bank_access_offset(handle: *mut bank_access_t) -> u64 {
+    unsafe { BankAccess::from_raw_mut(handle) }.offset()
+}
+
+extern "C" fn bank_access_size(handle: *mut bank_access_t) -> u64 {
+    unsafe { BankAccess::from_raw_mut(handle) }.size()
+}
+
+extern "C" fn bank_access_set_offset(handle: *mut bank_access_t, offset: u64) {
+    unsafe { BankAccess::from_raw_mut(handle) }.set_offset(offset)
+}
+
+extern "C" fn bank_access_value(handle: *mut bank_access_t) -> u64 {
+    unsafe { BankAccess::from_raw_mut(handle) }.value()
+}
+
+extern "C" fn bank_access_set_value(handle: *mut bank_access_t, value: u64) {
+    unsafe { BankAccess::from_raw_mut(handle) }.set_value(value)
+}
+
+extern "C" fn bank_access_inquire(handle: *mut bank_access_t) {
+    unsafe { BankAccess::from_raw_mut(handle) }.inquire()
+}
+
+extern "C" fn bank_access_is_inquiry(handle: *mut bank_access_t) -> bool {
+    unsafe { BankAccess::from_raw_mut(handle) }.is_inquiry()
+}
+
+extern "C" fn bank_access_missed(handle: *mut bank_access_t) -> bool {
+    unsafe { BankAccess::from_raw_mut(handle) }.missed()
+}
+
+extern "C" fn bank_access_set_missed(handle: *mut bank_access_t, missed: bool) {
+    unsafe { BankAccess::from_raw_mut(handle) }.set_missed(missed)
+}
+
+extern "C" fn bank_access_suppress(handle: *mut bank_access_t) {
+    unsafe { BankAccess::from_raw_mut(handle) }.suppress()
+}
+
+extern "C" fn bank_access_initiator(handle: *mut bank_access_t) -> *mut ConfObject {
+    unsafe { BankAccess::from_raw_mut(handle) }.initiator
+}
+
+static BANK_BEFORE_READ: bank_before_read_interface_t = bank_before_read_interface_t {
+    offset: Some(bank_access_offset),
+    size: Some(bank_access_size),
+    set_offset: Some(bank_access_set_offset),
+    inquire: Some(bank_access_inquire),
+    initiator: Some(bank_access_initiator),
+};
+
+static BANK_AFTER_READ: bank_after_read_interface_t = bank_after_read_interface_t {
+    offset: Some(bank_access_offset),
+    size: Some(bank_access_size),
+    value: Some(bank_access_value),
+    set_value: Some(bank_access_set_value),
+    missed: Some(bank_access_missed),
+    set_missed: Some(bank_access_set_missed),
+    is_inquiry: Some(bank_access_is_inquiry),
+    initiator: Some(bank_access_initiator),
+};
+
+static BANK_BEFORE_WRITE: bank_before_write_interface_t = bank_before_write_interface_t {
+    offset: Some(bank_access_offset),
+    size: Some(bank_access_size),
+    value: Some(bank_access_value),
+    suppress: Some(bank_access_suppress),
+    set_offset: Some(bank_access_set_offset),
+    set_value: Some(bank_access_set_value),
+    initiator: Some(bank_access_initiator),
+};
+
+static BANK_AFTER_WRITE: bank_after_write_interface_t = bank_after_write_interface_t {
+    offset: Some(bank_access_offset),
+    size: Some(bank_access_size),
+    missed: Some(bank_access_missed),
+    set_missed: Some(bank_access_set_missed),
+    is_inquiry: Some(bank_access_is_inquiry),
+    initiator: Some(bank_access_initiator),
+};
+
+#[derive(Debug, Clone, Copy)]
+enum BankCallback {
+    BeforeRead(before_read_callback_t),
+    AfterRead(after_read_callback_t),
+    BeforeWrite(before_write_callback_t),
+    AfterWrite(after_write_callback_t),
+}
+
+#[derive(Debug, Clone)]
+struct BankSubscription {
+    handle: BankCallbackHandle,
+    connection: *mut ConfObject,
+    offset: u64,
+    size: u64,
+    callback: BankCallback,
+    user_data: *mut c_void,
+}
+
+impl BankSubscription {
+    /// Whether this subscription covers an access. A subscription with size 0 covers the
+    /// whole bank.
+    fn covers(&self, access: &BankAccess) -> bool {
+        self.size == 0
+            || (access.offset() < self.offset.saturating_add(self.size)
+                && self.offset < access.offset().saturating_add(access.size()))
+    }
+}
+
+#[derive(Debug, Default)]
+/// The instrumentation callbacks subscribed to accesses to a register bank
+pub struct BankInstrumentation {
+    subscriptions: Vec<BankSubscription>,
+    disabled: Vec<*mut ConfObject>,
+    last_handle: BankCallbackHandle,
+}
+
+impl BankInstrumentation {
+    fn subscribe(
+        &mut self,
+        connection: *mut ConfObject,
+        offset: u64,
+        size: u64,
+        callback: BankCallback,
+        user_data: *mut c_void,
+    ) -> BankCallbackHandle {
+        self.last_handle += 1;
+
+        self.subscriptions.push(BankSubscription {
+            handle: self.last_handle,
+            connection,
+            offset,
+            size,
+            callback,
+            user_data,
+        });
+
+        self.last_handle
+    }
+
+    /// Subscribe to reads of `size` bytes at `offset` before they are performed. A size of 0
+    /// subscribes to all reads of the bank.
+    pub fn register_before_read(
+        &mut self,
+        connection: *mut ConfObject,
+        offset: u64,
+        size: u64,
+        before_read: before_read_callback_t,
+        user_data: *mut c_void,
+    ) -> BankCallbackHandle {
+        self.subscribe(
+            connection,
+            offset,
+            size,
+            BankCallback::BeforeRead(before_read),
+            user_data,
+        )
+    }
+
+    /// Subscribe to reads of `size` bytes at `offset` after they are performed. A size of 0
+    /// subscribes to all reads of the bank.
+    pub fn register_after_read(
+        &mut self,
+        connection: *mut ConfObject,
+        offset: u64,
+        size: u64,
+        after_read: after_read_callback_t,
+        user_data: *mut c_void,
+    ) -> BankCallbackHandle {
+        self.subscribe(
+            connection,
+            offset,
+            size,
+            BankCallback::AfterRead(after_read),
+            user_data,
+        )
+    }
+
+    /// Subscribe to writes of `size` bytes at `offset` before they are performed. A size of 0
+    /// subscribes to all writes to the bank.
+    pub fn register_before_write(
+        &mut self,
+        connection: *mut ConfObject,
+        offset: u64,
+        size: u64,
+        before_write: before_write_callback_t,
+        user_data: *mut c_void,
+    ) -> BankCallbackHandle {
+        self.subscribe(
+            connection,
+            offset,
+            size,
+            BankCallback::BeforeWrite(before_write),
+            user_data,
+        )
+    }
+
+    /// Subscribe to writes of `size` bytes at `offset` after they are performed. A size of 0
+    /// subscribes to all writes to the bank.
+    pub fn register_after_write(
+        &mut self,
+        connection: *mut ConfObject,
+        offset: u64,
+        size: u64,
+        after_write: after_write_callback_t,
+        user_data: *mut c_void,
+    ) -> BankCallbackHandle {
+        self.subscribe(
+            connection,
+            offset,
+            size,
+            BankCallback::AfterWrite(after_write),
+            user_data,
+        )
+    }
+
+    /// Remove a callback by the handle returned when it was registered
+    pub fn remove_callback(&mut self, handle: BankCallbackHandle) {
+        self.subscriptions.retain(|s| s.handle != handle);
+    }
+
+    /// Remove all callbacks registered by a connection
+    pub fn remove_connection_callbacks(&mut self, connection: *const ConfObject) {
+        self.subscriptions
+            .retain(|s| s.connection as *const ConfObject != connection);
+        self.enable_connection_callbacks(connection);
+    }
+
+    /// Enable the callbacks registered by a connection after they were disabled
+    pub fn enable_connection_callbacks(&mut self, connection: *const ConfObject) {
+        self.disabled
+            .retain(|c| *c as *const ConfObject != connection);
+    }
+
+    /// Temporarily disable the callbacks registered by a connection
+    pub fn disable_connection_callbacks(&mut self, connection: *const ConfObject) {
+        if !self
+            .disabled
+            .iter()
+            .any(|c| *c as *const ConfObject == connection)
+        {
+            self.disabled.push(connection as *mut ConfObject);
+        }
+    }
+
+    /// Collect the enabled subscriptions covering an access. The subscriptions are copied
+    /// out so callbacks may add or remove subscriptions while they run.
+    fn covering(&self, access: &BankAccess) -> Vec<BankSubscription> {
+        self.subscriptions
+            .iter()
+            .filter(|s| !self.disabled.contains(&s.connection) && s.covers(access))
+            .cloned()
+            .collect()
+    }
+
+    /// Call the before read callbacks covering an access
+    pub fn before_read(&self, access: &mut BankAccess) {
+        self.covering(access).into_iter().for_each(|s| {
+            if let BankCallback::BeforeRead(Some(callback)) = s.callback {
+                unsafe {
+                    callback(
+                        s.connection,
+                        &BANK_BEFORE_READ as *const _ as *mut _,
+                        access.as_mut_ptr(),
+                        s.user_data,
+                    )
+                }
+            }
+        });
+    }
+
+    /// Call the after read callbacks covering an access
+    pub fn after_read(&self, access: &mut BankAccess) {
+        self.covering(access).into_iter().for_each(|s| {
+            if let BankCallback::AfterRead(Some(callback)) = s.callback {
+                unsafe {
+                    callback(
+                        s.connection,
+                        &BANK_AFTER_READ as *const _ as *mut _,
+                        access.as_mut_ptr(),
+                        s.user_data,
+                    )
+                }
+            }
+        });
+    }
+
+    /// Call the before write callbacks covering an access
+    pub fn before_write(&self, access: &mut BankAccess) {
+        self.covering(access).into_iter().for_each(|s| {
+            if let BankCallback::BeforeWrite(Some(callback)) = s.callback {
+                unsafe {
+                    callback(
+                        s.connection,
+                        &BANK_BEFORE_WRITE as *const _ as *mut _,
+                        access.as_mut_ptr(),
+                        s.user_data,
+                    )
+                }
+            }
+        });
+    }
+
+    /// Call the after write callbacks covering an access
+    pub fn after_write(&self, access: &mut BankAccess) {
+        self.covering(access).into_iter().for_each(|s| {
+            if let BankCallback::AfterWrite(Some(callback)) = s.callback {
+                unsafe {
+                    callback(
+                        s.connection,
+                        &BANK_AFTER_WRITE as *const _ as *mut _,
+                        access.as_mut_ptr(),
+                        s.user_data,
+                    )
+                }
+            }
+        });
+    }
+}
@@ -105,16 +105,19 @@ use crate::{
     sys::{
         attr_attr_t, attr_value_t, class_data_t, class_info_t, class_kind_t, conf_class_t,
         conf_object_t, get_attr_t, get_class_attr_t, object_iter_t, set_attr_t, set_class_attr_t,
-        set_error_t, SIM_attribute_error, SIM_copy_class, SIM_create_class, SIM_extend_class,
-        SIM_extension_data, SIM_get_class_data, SIM_get_class_interface, SIM_get_class_name,
-        SIM_get_interface, SIM_marked_for_deletion, SIM_object_data, SIM_object_descendant,
-        SIM_object_id, SIM_object_is_configured, SIM_object_iterator_next, SIM_object_name,
-        SIM_object_parent, SIM_register_attribute_with_user_data, SIM_register_class_alias,
-        SIM_register_class_attribute_with_user_data, SIM_register_interface,
-        SIM_register_typed_attribute, SIM_register_typed_class_attribute, SIM_require_object,
-        SIM_set_class_data, SIM_set_object_configured,
+        set_error_t, SIM_attribute_error, SIM_c_get_port_interface, SIM_copy_class,
+        SIM_create_class, SIM_extend_class, SIM_extension_data, SIM_get_class_data,
+        SIM_get_class_interface, SIM_get_class_name, SIM_get_class_port_interface,
+        SIM_get_interface, SIM_get_port_interface, SIM_marked_for_deletion, SIM_object_data,
+        SIM_object_descendant, SIM_object_id, SIM_object_is_configured, SIM_object_iterator_next,
+        SIM_object_name, SIM_object_parent, SIM_port_object_parent,
+        SIM_register_attribute_with_user_data, SIM_register_class_alias,
+        SIM_register_class_attribute_with_user_data, SIM_register_interface, SIM_register_port,
+        SIM_register_port_interface, SIM_register_simple_port, SIM_register_typed_attribute,
+        SIM_register_typed_class_attribute, SIM_require_object, SIM_set_class_data,
+        SIM_set_object_configured,
     },
-    AttrValue, Error, Interface, Result,
+    AttrValue, BuiltinInterface, Error, Interface, Result,
 };
 use raw_cstr::{raw_cstr, AsRawCstr};
 use std::{
@@ -398,6 +401,72 @@ where
     Ok(unsafe { SIM_copy_class(raw_cstr(name)?, src_cls, raw_cstr(desc)?) })
 }
 
+#[simics_exception]
+/// Register a port named `name` on the class `cls`. Each object of `cls` gets a port object
+/// of class `port_cls` as a child, reachable as `obj.name`. The name may contain dots to
+/// nest the port under a namespace object, for example `bank.regs`, and may end in an array
+/// size, for example `port.irq[8]`, to create an array of port objects.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the port on
+/// * `name` - The name of the port object, relative to objects of `cls`
+/// * `port_cls` - The class of the port object
+/// * `desc` - The description of the port
+///
+/// # Context
+///
+/// Global Context
+pub fn register_port<S, D>(
+    cls: *mut ConfClass,
+    name: S,
+    port_cls: *mut ConfClass,
+    desc: D,
+) -> Result<()>
+where
+    S: AsRef<str>,
+    D: AsRef<str>,
+{
+    unsafe { SIM_register_port(cls, raw_cstr(name)?, port_cls, raw_cstr(desc)?) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Register a port named `name` on the class `cls`, using a new anonymous port class for the
+/// port objects. Interfaces implemented by the port should be registered on the returned
+/// class.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the port on
+/// * `name` - The name of the port object, relative to objects of `cls`
+/// * `desc` - The description of the port
+///
+/// # Return Value
+///
+/// The newly created port class
+///
+/// # Context
+///
+/// Global Context
+pub fn register_simple_port<S, D>(cls: *mut ConfClass, name: S, desc: D) -> Result<*mut ConfClass>
+where
+    S: AsRef<str>,
+    D: AsRef<str>,
+{
+    let port_cls =
+        unsafe { SIM_register_simple_port(cls, raw_cstr(name.as_ref())?, raw_cstr(desc)?) };
+
+    if port_cls.is_null() {
+        Err(Error::CreateClass {
+            name: name.as_ref().to_string(),
+            message: last_error(),
+        })
+    } else {
+        Ok(port_cls)
+    }
+}
+
 #[simics_exception]
 /// Get the name of a class. The name is copied, which differs from the
 /// C API.
@@ -656,6 +725,30 @@ pub fn object_parent(obj: *mut ConfObject) -> Option<*mut ConfObject> {
     }
 }
 
+#[simics_exception]
+/// Retrieve the object a port object belongs to, if `obj` is a port object.
+///
+/// # Arguments
+///
+/// * `obj` - The port object to get the parent object for
+///
+/// # Return Value
+///
+/// A pointer to the object the port was registered on, or None if `obj` is not a port object
+///
+/// # Context
+///
+/// All Contexts
+pub fn port_object_parent(obj: *mut ConfObject) -> Option<*mut ConfObject> {
+    let ptr = unsafe { SIM_port_object_parent(obj) };
+
+    if ptr.is_null() {
+        None
+    } else {
+        Some(ptr)
+    }
+}
+
 #[simics_exception]
 /// Retrieve an object's descendant with a name, if one exists.
 ///
@@ -1587,7 +1680,133 @@ where
     Ok(unsafe { SIM_register_interface(cls, name_raw, iface_raw as *mut _) })
 }
 
-// TODO: Port & compatible interfaces
+#[simics_exception]
+/// Register that cls implements the built-in interface `I`, with the methods of the
+/// interface dispatching to the implementation of the interface for the Rust type `T`.
+/// The objects of `cls` must be of type `T`.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the interface for
+///
+/// # Return value
+///
+/// Non-zero on failure, 0 on success
+///
+/// # Exceptions
+///
+/// * [`SimException::SimExc_General`] Thrown if the interface name is illegal, or if
+/// this interface has already been registered for this class.
+///
+/// # Context
+///
+/// Global Context
+pub fn register_builtin_interface<T, I>(cls: *mut ConfClass) -> Result<i32>
+where
+    I: BuiltinInterface<T>,
+{
+    let name_raw = I::NAME.as_raw_cstr()?;
+    // Note: This allocates and never frees. This is *required* by SIMICS and it is an error to
+    // free this pointer
+    let iface_raw = Box::into_raw(Box::new(I::internal_interface()));
+
+    Ok(unsafe { SIM_register_interface(cls, name_raw, iface_raw as *mut _) })
+}
+
+#[simics_exception]
+/// Register that cls implements interface `I` on the port named `portname`. The port
+/// interface can be retrieved from objects of `cls` with [`get_port_interface`].
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the interface for
+/// * `portname` - The name of the port to register the interface on
+/// * `desc` - The description of the port
+///
+/// # Return value
+///
+/// Non-zero on failure, 0 on success
+///
+/// # Exceptions
+///
+/// * [`SimException::SimExc_General`] Thrown if the interface name is illegal, or if
+/// this interface has already been registered for this port on this class.
+///
+/// # Context
+///
+/// Global Context
+pub fn register_port_interface<I, S, D>(cls: *mut ConfClass, portname: S, desc: D) -> Result<i32>
+where
+    I: Interface,
+    S: AsRef<str>,
+    D: AsRef<str>,
+{
+    let name_raw = I::NAME.as_raw_cstr()?;
+    // Note: This allocates and never frees. This is *required* by SIMICS and it is an error to
+    // free this pointer
+    let iface_raw = Box::into_raw(Box::<I::InternalInterface>::default());
+
+    Ok(unsafe {
+        SIM_register_port_interface(
+            cls,
+            name_raw,
+            iface_raw as *mut _,
+            raw_cstr(portname)?,
+            raw_cstr(desc)?,
+        )
+    })
+}
+
+#[simics_exception]
+/// Register that cls implements the built-in interface `I` on the port named `portname`,
+/// with the methods of the interface dispatching to the implementation of the interface for
+/// the Rust type `T`. The objects of `cls` must be of type `T`.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the interface for
+/// * `portname` - The name of the port to register the interface on
+/// * `desc` - The description of the port
+///
+/// # Return value
+///
+/// Non-zero on failure, 0 on success
+///
+/// # Exceptions
+///
+/// * [`SimException::SimExc_General`] Thrown if the interface name is illegal, or if
+/// this interface has already been registered for this port on this class.
+///
+/// # Context
+///
+/// Global Context
+pub fn register_builtin_port_interface<T, I, S, D>(
+    cls: *mut ConfClass,
+    portname: S,
+    desc: D,
+) -> Result<i32>
+where
+    I: BuiltinInterface<T>,
+    S: AsRef<str>,
+    D: AsRef<str>,
+{
+    let name_raw = I::NAME.as_raw_cstr()?;
+    // Note: This allocates and never frees. This is *required* by SIMICS and it is an error to
+    // free this pointer
+    let iface_raw = Box::into_raw(Box::new(I::internal_interface()));
+
+    Ok(unsafe {
+        SIM_register_port_interface(
+            cls,
+            name_raw,
+            iface_raw as *mut _,
+            raw_cstr(portname)?,
+            raw_cstr(desc)?,
+        )
+    })
+}
+
+// TODO: Compatible interfaces
 
 #[simics_exception]
 /// Get an interface on an object
@@ -1644,7 +1863,110 @@ where
     })
 }
 
-// TODO: Add Port Interfaces
+#[simics_exception]
+/// Get an interface on the port named `portname` of an object
+///
+/// # Arguments
+///
+/// * `obj` - The object to get an interface on
+/// * `portname` - The name of the port to get the interface on
+///
+/// # Return Value
+///
+/// The interface requested, or an error if the object does not implement the interface on
+/// the port.
+///
+/// # Exceptions
+///
+/// * [`SimException::SimExc_Lookup`] Thrown if the interface is not implemented on the
+/// port.
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_port_interface<I, S>(obj: *mut ConfObject, portname: S) -> Result<I>
+where
+    I: Interface,
+    S: AsRef<str>,
+{
+    Ok(I::new(obj, unsafe {
+        SIM_get_port_interface(
+            obj as *const ConfObject,
+            I::NAME.as_raw_cstr()?,
+            raw_cstr(portname)?,
+        ) as *mut I::InternalInterface
+    }))
+}
+
+#[simics_exception]
+/// Get an interface on the port named `portname` of an object if the object implements it.
+/// Unlike [`get_port_interface`], no exception is raised if the interface is not
+/// implemented.
+///
+/// # Arguments
+///
+/// * `obj` - The object to get an interface on
+/// * `portname` - The name of the port to get the interface on
+///
+/// # Return Value
+///
+/// The interface requested, or `None` if the object does not implement the interface on the
+/// port.
+///
+/// # Context
+///
+/// All Contexts
+pub fn c_get_port_interface<I, S>(obj: *mut ConfObject, portname: S) -> Result<Option<I>>
+where
+    I: Interface,
+    S: AsRef<str>,
+{
+    let iface = unsafe {
+        SIM_c_get_port_interface(
+            obj as *const ConfObject,
+            I::NAME.as_raw_cstr()?,
+            raw_cstr(portname)?,
+        )
+    };
+
+    if iface.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(I::new(obj, iface as *mut I::InternalInterface)))
+    }
+}
+
+#[simics_exception]
+/// Get an interface on the port named `portname` of a class
+///
+/// # Arguments
+///
+/// * `cls` - The class to get an interface on
+/// * `portname` - The name of the port to get the interface on
+///
+/// # Return Value
+///
+/// The interface requested, or an error if invalid.
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_class_port_interface<I, S>(
+    cls: *mut ConfClass,
+    portname: S,
+) -> Result<*mut I::InternalInterface>
+where
+    I: Interface,
+    S: AsRef<str>,
+{
+    Ok(unsafe {
+        SIM_get_class_port_interface(
+            cls as *const ConfClass,
+            I::NAME.as_raw_cstr()?,
+            raw_cstr(portname)?,
+        ) as *mut I::InternalInterface
+    })
+}
 
 #[simics_exception]
 /// Indicates if the given object is being deleted. This information can be useful by
@@ -0,0 +1,188 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Global messages
+//!
+//! A global message is delivered to its receivers in Global Context at a deterministic point
+//! in the simulation, regardless of which cell or thread triggered it. Receivers are notified
+//! of every global message through the `Sim_Global_Notify_Message` global notifier, and use
+//! the reference the message was triggered with to pick out their own messages.
+//!
+//! The typed `GlobalMessage` channels require the `global-message` feature.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+#[cfg(feature = "global-message")]
+use crate::{
+    add_global_notifier, get_object, log_error, Error, GlobalNotifierSubscription,
+    GlobalNotifierType,
+};
+use crate::{
+    simics_exception,
+    sys::{SIM_get_global_message, SIM_trigger_global_message},
+    Result,
+};
+#[cfg(feature = "global-message")]
+use serde::{de::DeserializeOwned, Serialize};
+use std::ffi::{c_void, CStr, CString};
+#[cfg(feature = "global-message")]
+use std::{
+    marker::PhantomData,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "global-message")]
+/// The reference identifying the messages of the next [`GlobalMessage`] channel
+static NEXT_REFERENCE: AtomicUsize = AtomicUsize::new(1);
+
+#[simics_exception]
+/// Trigger a global message. The message is delivered to the subscribers of the
+/// `Sim_Global_Notify_Message` global notifier, which can retrieve it with
+/// [`get_global_message`] using the same reference.
+///
+/// # Arguments
+///
+/// * `msg` - The message
+/// * `reference` - A reference identifying the message to its receivers
+///
+/// # Context
+///
+/// All Contexts
+pub fn trigger_global_message<S>(msg: S, reference: *mut c_void) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    let msg = CString::new(msg.as_ref())?;
+    unsafe { SIM_trigger_global_message(msg.as_ptr(), reference) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Get the global message currently being delivered, if it was triggered with the reference
+/// `reference`. This should be called from a `Sim_Global_Notify_Message` global notifier
+/// callback.
+///
+/// # Arguments
+///
+/// * `reference` - The reference the message was triggered with
+///
+/// # Return Value
+///
+/// The message, or `None` if the message being delivered has a different reference
+///
+/// # Context
+///
+/// Global Context
+pub fn get_global_message(reference: *mut c_void) -> Result<Option<String>> {
+    let msg = unsafe { SIM_get_global_message(reference) };
+
+    if msg.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(unsafe { CStr::from_ptr(msg) }.to_str()?.to_string()))
+    }
+}
+
+#[cfg(feature = "global-message")]
+/// A channel of typed global messages. Values triggered on the channel from any cell are
+/// serialized, delivered in Global Context, and passed to the receiver of the channel. A
+/// message which cannot be deserialized, or which the receiver fails to handle, is logged as
+/// an error and dropped.
+///
+/// ```rust,ignore
+/// let messages = GlobalMessage::new(|mode: Mode| {
+///     set_simulation_mode(mode);
+///     Ok(())
+/// })?;
+///
+/// messages.trigger(&Mode::Fast)?;
+/// ```
+pub struct GlobalMessage<T> {
+    reference: usize,
+    _subscription: GlobalNotifierSubscription,
+    _marker: PhantomData<fn(T)>,
+}
+
+#[cfg(feature = "global-message")]
+impl<T> GlobalMessage<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Create a new channel, calling `receiver` with each value triggered on it until the
+    /// channel is dropped
+    pub fn new<F>(mut receiver: F) -> Result<Self>
+    where
+        F: FnMut(T) -> Result<()> + 'static,
+    {
+        let reference = NEXT_REFERENCE.fetch_add(1, Ordering::Relaxed);
+
+        let subscription = add_global_notifier(
+            GlobalNotifierType::Sim_Global_Notify_Message,
+            None,
+            move || {
+                let Some(msg) = get_global_message(reference as *mut c_void)? else {
+                    return Ok(());
+                };
+
+                if let Err(e) = serde_json::from_str(&msg)
+                    .map_err(Error::from)
+                    .and_then(&mut receiver)
+                {
+                    log_error(get_object("sim")?, format!("Dropped global message: {e}"))?;
+                }
+
+                Ok(())
+            },
+        )?;
+
+        Ok(Self {
+            reference,
+            _subscription: subscription,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Trigger a message with a value on this channel. The value is delivered to the receiver
+    /// later, in Global Context.
+    pub fn trigger(&self, value: &T) -> Result<()> {
+        self.sender().trigger(value)
+    }
+
+    /// Get a sender for this channel, which can be moved to other threads to trigger messages
+    /// from other cells. Messages triggered after the channel is dropped are not received.
+    pub fn sender(&self) -> GlobalMessageSender<T> {
+        GlobalMessageSender {
+            reference: self.reference,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "global-message")]
+/// A sender of typed global messages on a [`GlobalMessage`] channel
+pub struct GlobalMessageSender<T> {
+    reference: usize,
+    _marker: PhantomData<fn(T)>,
+}
+
+#[cfg(feature = "global-message")]
+impl<T> Clone for GlobalMessageSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            reference: self.reference,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "global-message")]
+impl<T> GlobalMessageSender<T>
+where
+    T: Serialize,
+{
+    /// Trigger a message with a value on the channel of this sender. The value is delivered to
+    /// the receiver later, in Global Context.
+    pub fn trigger(&self, value: &T) -> Result<()> {
+        trigger_global_message(serde_json::to_string(value)?, self.reference as *mut c_void)
+    }
+}
@@ -0,0 +1,291 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Map targets, which issue transactions to memory spaces, ports and other objects
+//! implementing memory interfaces
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    simics_exception,
+    sys::{
+        map_target_t, transaction_t, SIM_free_map_target, SIM_issue_transaction,
+        SIM_map_target_flush, SIM_map_target_object, SIM_map_target_port, SIM_map_target_target,
+        SIM_new_map_target,
+    },
+    Access, ConfObject, Error, ExceptionType, Result, Transaction,
+};
+use raw_cstr::raw_cstr;
+use std::{
+    ffi::CStr,
+    ptr::{null, null_mut},
+};
+
+#[simics_exception]
+/// Create a new map target for an object. The map target can be used to issue transactions
+/// to the object through whichever memory interface it implements.
+///
+/// # Arguments
+///
+/// * `obj` - The object to create a map target for
+/// * `port` - The port of the object to target, if any
+/// * `chained_target` - A map target to use as the default target of `obj`, if any. This is
+///   only used when `obj` is a translator.
+///
+/// # Return Value
+///
+/// The new map target, which must be freed with [`free_map_target`]
+///
+/// # Context
+///
+/// Global Context
+pub fn new_map_target(
+    obj: *mut ConfObject,
+    port: Option<&str>,
+    chained_target: Option<*const map_target_t>,
+) -> Result<*mut map_target_t> {
+    let map_target = unsafe {
+        SIM_new_map_target(
+            obj,
+            port.map(raw_cstr).transpose()?.unwrap_or(null_mut()),
+            chained_target.unwrap_or(null()),
+        )
+    };
+
+    if map_target.is_null() {
+        Err(Error::CreateMapTarget)
+    } else {
+        Ok(map_target)
+    }
+}
+
+#[simics_exception]
+/// Free a map target created with [`new_map_target`]
+///
+/// # Arguments
+///
+/// * `map_target` - The map target to free
+///
+/// # Context
+///
+/// Global Context
+pub fn free_map_target(map_target: *mut map_target_t) {
+    unsafe { SIM_free_map_target(map_target) }
+}
+
+#[simics_exception]
+/// Issue a transaction to a map target
+///
+/// # Arguments
+///
+/// * `map_target` - The map target to issue the transaction to
+/// * `t` - The transaction to issue
+/// * `addr` - The address to issue the transaction at
+///
+/// # Return Value
+///
+/// The status of the transaction, which is `Sim_PE_Deferred` if the target deferred it
+///
+/// # Context
+///
+/// Cell Context
+pub fn issue_transaction(
+    map_target: *const map_target_t,
+    t: *mut transaction_t,
+    addr: u64,
+) -> ExceptionType {
+    unsafe { SIM_issue_transaction(map_target, t, addr) }
+}
+
+#[simics_exception]
+/// Get the object a map target was created for
+///
+/// # Arguments
+///
+/// * `map_target` - The map target
+///
+/// # Return Value
+///
+/// The object the map target was created for
+///
+/// # Context
+///
+/// All Contexts
+pub fn map_target_object(map_target: *const map_target_t) -> *mut ConfObject {
+    unsafe { SIM_map_target_object(map_target) }
+}
+
+#[simics_exception]
+/// Get the port a map target was created for
+///
+/// # Arguments
+///
+/// * `map_target` - The map target
+///
+/// # Return Value
+///
+/// The port the map target was created for, or `None` if it targets the object itself
+///
+/// # Context
+///
+/// All Contexts
+pub fn map_target_port(map_target: *const map_target_t) -> Result<Option<String>> {
+    let port = unsafe { SIM_map_target_port(map_target) };
+
+    if port.is_null() {
+        Ok(None)
+    } else {
+        Ok(Some(unsafe { CStr::from_ptr(port) }.to_str()?.to_string()))
+    }
+}
+
+#[simics_exception]
+/// Get the chained target of a map target
+///
+/// # Arguments
+///
+/// * `map_target` - The map target
+///
+/// # Return Value
+///
+/// The chained target the map target was created with, if any
+///
+/// # Context
+///
+/// All Contexts
+pub fn map_target_target(map_target: *const map_target_t) -> Option<*const map_target_t> {
+    let target = unsafe { SIM_map_target_target(map_target) };
+    (!target.is_null()).then_some(target)
+}
+
+#[simics_exception]
+/// Flush any cached translations for a range of a map target
+///
+/// # Arguments
+///
+/// * `map_target` - The map target to flush
+/// * `base` - The start of the range to flush
+/// * `size` - The size of the range to flush
+/// * `access` - The access types to flush translations for
+///
+/// # Return Value
+///
+/// Whether the flush succeeded
+///
+/// # Context
+///
+/// Cell Context
+pub fn map_target_flush(
+    map_target: *const map_target_t,
+    base: u64,
+    size: u64,
+    access: Access,
+) -> bool {
+    unsafe { SIM_map_target_flush(map_target, base, size, access) }
+}
+
+/// A map target which is freed when dropped. Transactions issued to a map target are
+/// routed through the memory interfaces of its object, so a map target for a memory space
+/// can be used to issue accesses anywhere in the memory space, including to devices.
+///
+/// ```rust,ignore
+/// let target = MapTarget::new(memory_space, None, None)?;
+/// let data = target.read(0x1000, 64)?;
+/// target.write(0x2000, &data)?;
+/// ```
+pub struct MapTarget {
+    map_target: *mut map_target_t,
+    // NOTE: Fields are dropped after `drop` runs, so the chained target outlives the map
+    // target using it
+    _chained_target: Option<Box<MapTarget>>,
+}
+
+impl MapTarget {
+    /// Create a new map target for `obj`, optionally targeting one of its ports. If `obj`
+    /// is a translator, `chained_target` is used as its default target. The new map target
+    /// owns its chained target, which is freed when it is dropped.
+    pub fn new(
+        obj: *mut ConfObject,
+        port: Option<&str>,
+        chained_target: Option<MapTarget>,
+    ) -> Result<Self> {
+        let chained_target = chained_target.map(Box::new);
+
+        Ok(Self {
+            map_target: new_map_target(obj, port, chained_target.as_ref().map(|t| t.as_ptr()))?,
+            _chained_target: chained_target,
+        })
+    }
+
+    /// Get the raw pointer to the map target
+    pub fn as_ptr(&self) -> *const map_target_t {
+        self.map_target
+    }
+
+    /// The object this map target was created for
+    pub fn object(&self) -> Result<*mut ConfObject> {
+        map_target_object(self.map_target)
+    }
+
+    /// The port of the object this map target was created for, if any
+    pub fn port(&self) -> Result<Option<String>> {
+        map_target_port(self.map_target)
+    }
+
+    /// The chained target of this map target, if any
+    pub fn target(&self) -> Result<Option<*const map_target_t>> {
+        map_target_target(self.map_target)
+    }
+
+    /// Flush any cached translations for the range `base..base + size`
+    pub fn flush(&self, base: u64, size: u64, access: Access) -> Result<bool> {
+        map_target_flush(self.map_target, base, size, access)
+    }
+
+    /// Issue a transaction at `addr`. If the target defers the transaction, the returned
+    /// status is `Sim_PE_Deferred` and the transaction must be waited for or monitored.
+    pub fn issue(&self, t: &mut Transaction, addr: u64) -> Result<ExceptionType> {
+        issue_transaction(self.map_target, t.as_mut_ptr(), addr)
+    }
+
+    /// Check the final status of a transaction issued by one of the helper methods
+    fn check_status(ex: ExceptionType) -> Result<()> {
+        if ex == ExceptionType::Sim_PE_No_Exception {
+            Ok(())
+        } else {
+            Err(Error::TransactionFailed { exception: ex })
+        }
+    }
+
+    /// Read `size` bytes at `addr`, waiting for the transaction if the target defers it.
+    /// This must be called from Cell Context.
+    pub fn read(&self, addr: u64, size: usize) -> Result<Vec<u8>> {
+        let mut t = Transaction::builder().read(size).deferrable().build()?;
+        let ex = t.issue(self, addr)?;
+        Self::check_status(t.wait(ex)?)?;
+        t.into_data()
+    }
+
+    /// Write `data` at `addr`, waiting for the transaction if the target defers it. This
+    /// must be called from Cell Context.
+    pub fn write(&self, addr: u64, data: &[u8]) -> Result<()> {
+        let mut t = Transaction::builder().write(data).deferrable().build()?;
+        let ex = t.issue(self, addr)?;
+        Self::check_status(t.wait(ex)?)
+    }
+
+    /// Read `size` bytes at `addr` without side effects on the target
+    pub fn inquiry_read(&self, addr: u64, size: usize) -> Result<Vec<u8>> {
+        let mut t = Transaction::builder().read(size).inquiry().build()?;
+        Self::check_status(t.issue(self, addr)?)?;
+        t.into_data()
+    }
+}
+
+impl Drop for MapTarget {
+    fn drop(&mut self) {
+        // NOTE: Errors cannot be reported from drop, and freeing a valid map target does
+        // not fail
+        let _ = free_map_target(self.map_target);
+    }
+}
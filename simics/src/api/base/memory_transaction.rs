@@ -2,8 +2,679 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Memory transactions
+//!
+//! High level APIs for the legacy `generic_transaction_t` memory operation type, which is
+//! still used by the `io_memory` interface and timing models.
 
-use crate::sys::generic_transaction_t;
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    simics_exception,
+    sys::{
+        arm_memory_transaction_t, buffer_t, bytes_t, generic_transaction_t, mem_op_type_t,
+        mips_memory_transaction_t, pci_memory_transaction_t, ppc_memory_transaction_t,
+        x86_memory_transaction_t, SIM_arm_mem_trans_from_generic, SIM_c_get_mem_op_value_buf,
+        SIM_c_set_mem_op_value_buf, SIM_get_mem_op_ignore, SIM_get_mem_op_type_name,
+        SIM_get_mem_op_value_be, SIM_get_mem_op_value_cpu, SIM_get_mem_op_value_le,
+        SIM_make_mem_op_read, SIM_make_mem_op_write, SIM_mips_mem_trans_from_generic,
+        SIM_pci_mem_trans_from_generic, SIM_ppc_mem_trans_from_generic, SIM_set_mem_op_ignore,
+        SIM_set_mem_op_value_be, SIM_set_mem_op_value_cpu, SIM_set_mem_op_value_le,
+        SIM_x86_mem_trans_from_generic, Sim_Trn_Control, Sim_Trn_Instr, Sim_Trn_Prefetch,
+        Sim_Trn_Write,
+    },
+    ConfObject, Error, ExceptionType, LogicalAddress, PhysicalAddress, Result,
+};
+use std::{ffi::CStr, ptr::null_mut};
 
 /// Alias for `generic_transaction_t`
 pub type GenericTransaction = generic_transaction_t;
+/// Alias for `mem_op_type_t`
+pub type MemOpType = mem_op_type_t;
+/// Alias for `x86_memory_transaction_t`
+pub type X86MemoryTransaction = x86_memory_transaction_t;
+/// Alias for `arm_memory_transaction_t`
+pub type ArmMemoryTransaction = arm_memory_transaction_t;
+/// Alias for `mips_memory_transaction_t`
+pub type MipsMemoryTransaction = mips_memory_transaction_t;
+/// Alias for `ppc_memory_transaction_t`
+pub type PpcMemoryTransaction = ppc_memory_transaction_t;
+/// Alias for `pci_memory_transaction_t`
+pub type PciMemoryTransaction = pci_memory_transaction_t;
+
+/// The maximum size of a memory operation whose value can be accessed as an integer
+const MEM_OP_VALUE_MAX_SIZE: usize = std::mem::size_of::<u64>();
+
+/// Check that the value of a memory operation fits in an integer
+fn check_mem_op_value_size(mop: *mut GenericTransaction) -> Result<()> {
+    let size = unsafe { (*mop).size } as usize;
+
+    if size > MEM_OP_VALUE_MAX_SIZE {
+        return Err(Error::ValueTooLarge {
+            expected: MEM_OP_VALUE_MAX_SIZE,
+            actual: size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that a buffer of `len` bytes covers exactly the whole memory operation
+fn check_mem_op_len(mop: *mut GenericTransaction, len: usize) -> Result<()> {
+    let size = unsafe { (*mop).size } as usize;
+
+    if len != size {
+        return Err(Error::TransactionSizeMismatch {
+            expected: size,
+            actual: len,
+        });
+    }
+
+    Ok(())
+}
+
+#[simics_exception]
+/// Get the value of a memory operation interpreted as a little endian integer. The size of
+/// the memory operation must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to get the value of
+///
+/// # Return Value
+///
+/// The value of the memory operation
+///
+/// # Context
+///
+/// Cell Context
+pub fn get_mem_op_value_le(mop: *mut GenericTransaction) -> Result<u64> {
+    check_mem_op_value_size(mop)?;
+    Ok(unsafe { SIM_get_mem_op_value_le(mop) })
+}
+
+#[simics_exception]
+/// Get the value of a memory operation interpreted as a big endian integer. The size of the
+/// memory operation must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to get the value of
+///
+/// # Return Value
+///
+/// The value of the memory operation
+///
+/// # Context
+///
+/// Cell Context
+pub fn get_mem_op_value_be(mop: *mut GenericTransaction) -> Result<u64> {
+    check_mem_op_value_size(mop)?;
+    Ok(unsafe { SIM_get_mem_op_value_be(mop) })
+}
+
+#[simics_exception]
+/// Get the value of a memory operation interpreted as an integer in the byte order of the
+/// processor which initiated it. The size of the memory operation must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to get the value of
+///
+/// # Return Value
+///
+/// The value of the memory operation
+///
+/// # Context
+///
+/// Cell Context
+pub fn get_mem_op_value_cpu(mop: *mut GenericTransaction) -> Result<u64> {
+    check_mem_op_value_size(mop)?;
+    Ok(unsafe { SIM_get_mem_op_value_cpu(mop) })
+}
+
+#[simics_exception]
+/// Set the value of a memory operation, encoded as a little endian integer of the size of
+/// the memory operation. The size of the memory operation must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to set the value of
+/// * `value` - The value to set
+///
+/// # Context
+///
+/// Cell Context
+pub fn set_mem_op_value_le(mop: *mut GenericTransaction, value: u64) -> Result<()> {
+    check_mem_op_value_size(mop)?;
+    unsafe { SIM_set_mem_op_value_le(mop, value) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Set the value of a memory operation, encoded as a big endian integer of the size of the
+/// memory operation. The size of the memory operation must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to set the value of
+/// * `value` - The value to set
+///
+/// # Context
+///
+/// Cell Context
+pub fn set_mem_op_value_be(mop: *mut GenericTransaction, value: u64) -> Result<()> {
+    check_mem_op_value_size(mop)?;
+    unsafe { SIM_set_mem_op_value_be(mop, value) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Set the value of a memory operation, encoded as an integer in the byte order of the
+/// processor which initiated it. The size of the memory operation must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to set the value of
+/// * `value` - The value to set
+///
+/// # Context
+///
+/// Cell Context
+pub fn set_mem_op_value_cpu(mop: *mut GenericTransaction, value: u64) -> Result<()> {
+    check_mem_op_value_size(mop)?;
+    unsafe { SIM_set_mem_op_value_cpu(mop, value) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Copy the data of a memory operation into a buffer. The buffer must be exactly the size of
+/// the memory operation.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to get the data of
+/// * `buf` - The buffer to copy the data into
+///
+/// # Context
+///
+/// Cell Context
+pub fn get_mem_op_value_buf(mop: *mut GenericTransaction, buf: &mut [u8]) -> Result<()> {
+    check_mem_op_len(mop, buf.len())?;
+    unsafe { SIM_c_get_mem_op_value_buf(mop, buf.as_mut_ptr()) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Copy data from a buffer into a memory operation. The buffer must be exactly the size of
+/// the memory operation.
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to set the data of
+/// * `buf` - The data to copy into the memory operation
+///
+/// # Context
+///
+/// Cell Context
+pub fn set_mem_op_value_buf(mop: *mut GenericTransaction, buf: &[u8]) -> Result<()> {
+    check_mem_op_len(mop, buf.len())?;
+    unsafe { SIM_c_set_mem_op_value_buf(mop, buf.as_ptr()) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Create a memory operation reading `buf.len()` bytes at `addr` into `buf`
+///
+/// # Safety
+///
+/// The memory operation refers to `buf` without borrowing it, so `buf` must outlive every
+/// use of the returned memory operation and must not be accessed while the memory operation
+/// is in use
+///
+/// # Arguments
+///
+/// * `addr` - The physical address to read from
+/// * `buf` - The buffer to read the data into
+/// * `inquiry` - Whether the read is an inquiry access, without side effects
+/// * `initiator` - The object initiating the read, if any
+///
+/// # Return Value
+///
+/// The memory operation
+///
+/// # Context
+///
+/// Cell Context
+pub unsafe fn make_mem_op_read(
+    addr: PhysicalAddress,
+    buf: &mut [u8],
+    inquiry: bool,
+    initiator: Option<*mut ConfObject>,
+) -> GenericTransaction {
+    unsafe {
+        SIM_make_mem_op_read(
+            addr,
+            buffer_t {
+                data: buf.as_mut_ptr(),
+                len: buf.len(),
+            },
+            inquiry,
+            initiator.unwrap_or(null_mut()),
+        )
+    }
+}
+
+#[simics_exception]
+/// Create a memory operation writing the bytes of `buf` to `addr`
+///
+/// # Safety
+///
+/// The memory operation refers to `buf` without borrowing it, so `buf` must outlive every
+/// use of the returned memory operation
+///
+/// # Arguments
+///
+/// * `addr` - The physical address to write to
+/// * `buf` - The data to write
+/// * `inquiry` - Whether the write is an inquiry access, without side effects
+/// * `initiator` - The object initiating the write, if any
+///
+/// # Return Value
+///
+/// The memory operation
+///
+/// # Context
+///
+/// Cell Context
+pub unsafe fn make_mem_op_write(
+    addr: PhysicalAddress,
+    buf: &[u8],
+    inquiry: bool,
+    initiator: Option<*mut ConfObject>,
+) -> GenericTransaction {
+    unsafe {
+        SIM_make_mem_op_write(
+            addr,
+            bytes_t {
+                data: buf.as_ptr(),
+                len: buf.len(),
+            },
+            inquiry,
+            initiator.unwrap_or(null_mut()),
+        )
+    }
+}
+
+#[simics_exception]
+/// Get the name of a memory operation type, for example `"Load"`
+///
+/// # Arguments
+///
+/// * `ty` - The memory operation type
+///
+/// # Return Value
+///
+/// The name of the memory operation type
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_mem_op_type_name(ty: MemOpType) -> Result<String> {
+    let name = unsafe { SIM_get_mem_op_type_name(ty) };
+
+    if name.is_null() {
+        Ok(String::new())
+    } else {
+        Ok(unsafe { CStr::from_ptr(name) }.to_str()?.to_string())
+    }
+}
+
+#[simics_exception]
+/// Check whether a memory operation should be ignored by the memory hierarchy
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to check
+///
+/// # Return Value
+///
+/// Whether the memory operation is ignored
+///
+/// # Context
+///
+/// Cell Context
+pub fn get_mem_op_ignore(mop: *mut GenericTransaction) -> bool {
+    unsafe { SIM_get_mem_op_ignore(mop) }
+}
+
+#[simics_exception]
+/// Set whether a memory operation should be ignored by the memory hierarchy
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation to modify
+/// * `ignore` - Whether the memory operation is ignored
+///
+/// # Context
+///
+/// Cell Context
+pub fn set_mem_op_ignore(mop: *mut GenericTransaction, ignore: bool) {
+    unsafe { SIM_set_mem_op_ignore(mop, ignore) };
+}
+
+#[simics_exception]
+/// Get the x86 specific memory transaction containing a memory operation
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation
+///
+/// # Return Value
+///
+/// The x86 memory transaction, or `None` if the memory operation is not part of one
+///
+/// # Context
+///
+/// Cell Context
+pub fn x86_mem_trans_from_generic(
+    mop: *mut GenericTransaction,
+) -> Option<*mut X86MemoryTransaction> {
+    let trans = unsafe { SIM_x86_mem_trans_from_generic(mop) };
+    (!trans.is_null()).then_some(trans)
+}
+
+#[simics_exception]
+/// Get the ARM specific memory transaction containing a memory operation
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation
+///
+/// # Return Value
+///
+/// The ARM memory transaction, or `None` if the memory operation is not part of one
+///
+/// # Context
+///
+/// Cell Context
+pub fn arm_mem_trans_from_generic(
+    mop: *mut GenericTransaction,
+) -> Option<*mut ArmMemoryTransaction> {
+    let trans = unsafe { SIM_arm_mem_trans_from_generic(mop) };
+    (!trans.is_null()).then_some(trans)
+}
+
+#[simics_exception]
+/// Get the MIPS specific memory transaction containing a memory operation
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation
+///
+/// # Return Value
+///
+/// The MIPS memory transaction, or `None` if the memory operation is not part of one
+///
+/// # Context
+///
+/// Cell Context
+pub fn mips_mem_trans_from_generic(
+    mop: *mut GenericTransaction,
+) -> Option<*mut MipsMemoryTransaction> {
+    let trans = unsafe { SIM_mips_mem_trans_from_generic(mop) };
+    (!trans.is_null()).then_some(trans)
+}
+
+#[simics_exception]
+/// Get the PowerPC specific memory transaction containing a memory operation
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation
+///
+/// # Return Value
+///
+/// The PowerPC memory transaction, or `None` if the memory operation is not part of one
+///
+/// # Context
+///
+/// Cell Context
+pub fn ppc_mem_trans_from_generic(
+    mop: *mut GenericTransaction,
+) -> Option<*mut PpcMemoryTransaction> {
+    let trans = unsafe { SIM_ppc_mem_trans_from_generic(mop) };
+    (!trans.is_null()).then_some(trans)
+}
+
+#[simics_exception]
+/// Get the PCI specific memory transaction containing a memory operation
+///
+/// # Arguments
+///
+/// * `mop` - The memory operation
+///
+/// # Return Value
+///
+/// The PCI memory transaction, or `None` if the memory operation is not part of one
+///
+/// # Context
+///
+/// Cell Context
+pub fn pci_mem_trans_from_generic(
+    mop: *mut GenericTransaction,
+) -> Option<*mut PciMemoryTransaction> {
+    let trans = unsafe { SIM_pci_mem_trans_from_generic(mop) };
+    (!trans.is_null()).then_some(trans)
+}
+
+#[repr(transparent)]
+/// A legacy memory operation. This is a safe view of a `generic_transaction_t` received from
+/// the simulator, for example in the `operation` method of the `io_memory` interface or in a
+/// timing model.
+pub struct MemOp(generic_transaction_t);
+
+impl MemOp {
+    /// Create a shared reference to a memory operation from a raw pointer
+    ///
+    /// # Safety
+    ///
+    /// `mop` must be a valid, non-null pointer to a `generic_transaction_t` which outlives the
+    /// returned reference
+    pub unsafe fn from_raw<'a>(mop: *const generic_transaction_t) -> &'a Self {
+        &*(mop as *const Self)
+    }
+
+    /// Create a mutable reference to a memory operation from a raw pointer
+    ///
+    /// # Safety
+    ///
+    /// `mop` must be a valid, non-null pointer to a `generic_transaction_t` which outlives the
+    /// returned reference and which is not otherwise referenced while the returned reference
+    /// exists
+    pub unsafe fn from_raw_mut<'a>(mop: *mut generic_transaction_t) -> &'a mut Self {
+        &mut *(mop as *mut Self)
+    }
+
+    /// Get a raw pointer to the underlying `generic_transaction_t`
+    pub fn as_ptr(&self) -> *const generic_transaction_t {
+        &self.0
+    }
+
+    /// Get a mutable raw pointer to the underlying `generic_transaction_t`
+    pub fn as_mut_ptr(&mut self) -> *mut generic_transaction_t {
+        &mut self.0
+    }
+
+    /// Get a raw pointer to the underlying `generic_transaction_t` for the read-only accessors
+    fn ptr(&self) -> *mut generic_transaction_t {
+        &self.0 as *const generic_transaction_t as *mut generic_transaction_t
+    }
+
+    /// The physical address of this memory operation
+    pub fn physical_address(&self) -> PhysicalAddress {
+        self.0.physical_address
+    }
+
+    /// The logical address of this memory operation
+    pub fn logical_address(&self) -> LogicalAddress {
+        self.0.logical_address
+    }
+
+    /// The size of this memory operation in bytes
+    pub fn size(&self) -> usize {
+        self.0.size as usize
+    }
+
+    /// The type of this memory operation
+    pub fn mem_op_type(&self) -> MemOpType {
+        self.0.type_
+    }
+
+    /// The name of the type of this memory operation
+    pub fn type_name(&self) -> Result<String> {
+        get_mem_op_type_name(self.mem_op_type())
+    }
+
+    /// Whether any of the `Sim_Trn_*` bits `bits` are set in the type of this memory
+    /// operation
+    fn has_type_bits(&self, bits: u32) -> bool {
+        self.0.type_ as u32 & bits != 0
+    }
+
+    /// Whether this memory operation is a read, including instruction fetches. Control
+    /// operations such as prefetches and cache operations are not reads.
+    pub fn is_read(&self) -> bool {
+        matches!(
+            self.0.type_,
+            MemOpType::Sim_Trans_Load | MemOpType::Sim_Trans_Instr_Fetch
+        )
+    }
+
+    /// Whether this memory operation is a write
+    pub fn is_write(&self) -> bool {
+        self.has_type_bits(Sim_Trn_Write as _)
+    }
+
+    /// Whether this memory operation is an instruction fetch
+    pub fn is_instruction(&self) -> bool {
+        self.has_type_bits(Sim_Trn_Instr as _)
+    }
+
+    /// Whether this memory operation is a data access
+    pub fn is_data(&self) -> bool {
+        !self.is_instruction()
+    }
+
+    /// Whether this memory operation is a control operation, which does not transfer data
+    pub fn is_control(&self) -> bool {
+        self.has_type_bits(Sim_Trn_Control as _)
+    }
+
+    /// Whether this memory operation is a prefetch
+    pub fn is_prefetch(&self) -> bool {
+        self.has_type_bits(Sim_Trn_Prefetch as _)
+    }
+
+    /// Whether this memory operation is an inquiry (an access without side effects)
+    pub fn is_inquiry(&self) -> bool {
+        self.0.inquiry() != 0
+    }
+
+    /// The initiator of this memory operation, if any
+    pub fn initiator(&self) -> Option<*mut ConfObject> {
+        (!self.0.ini_ptr.is_null()).then_some(self.0.ini_ptr)
+    }
+
+    /// The exception this memory operation completed with
+    pub fn exception(&self) -> ExceptionType {
+        self.0.exception
+    }
+
+    /// Whether this memory operation should be ignored by the memory hierarchy
+    pub fn ignore(&self) -> Result<bool> {
+        get_mem_op_ignore(self.ptr())
+    }
+
+    /// Set whether this memory operation should be ignored by the memory hierarchy
+    pub fn set_ignore(&mut self, ignore: bool) -> Result<()> {
+        set_mem_op_ignore(self.as_mut_ptr(), ignore)
+    }
+
+    /// The value of this memory operation as a little endian integer. The memory operation
+    /// must be at most 8 bytes.
+    pub fn value_le(&self) -> Result<u64> {
+        get_mem_op_value_le(self.ptr())
+    }
+
+    /// The value of this memory operation as a big endian integer. The memory operation must
+    /// be at most 8 bytes.
+    pub fn value_be(&self) -> Result<u64> {
+        get_mem_op_value_be(self.ptr())
+    }
+
+    /// The value of this memory operation as an integer in the byte order of its initiating
+    /// processor. The memory operation must be at most 8 bytes.
+    pub fn value_cpu(&self) -> Result<u64> {
+        get_mem_op_value_cpu(self.ptr())
+    }
+
+    /// Set the value of this memory operation as a little endian integer. The memory
+    /// operation must be at most 8 bytes.
+    pub fn set_value_le(&mut self, value: u64) -> Result<()> {
+        set_mem_op_value_le(self.as_mut_ptr(), value)
+    }
+
+    /// Set the value of this memory operation as a big endian integer. The memory operation
+    /// must be at most 8 bytes.
+    pub fn set_value_be(&mut self, value: u64) -> Result<()> {
+        set_mem_op_value_be(self.as_mut_ptr(), value)
+    }
+
+    /// Set the value of this memory operation as an integer in the byte order of its
+    /// initiating processor. The memory operation must be at most 8 bytes.
+    pub fn set_value_cpu(&mut self, value: u64) -> Result<()> {
+        set_mem_op_value_cpu(self.as_mut_ptr(), value)
+    }
+
+    /// The data of this memory operation
+    pub fn bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = vec![0; self.size()];
+        self.get_bytes(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Copy the data of this memory operation into `buf`, which must be exactly its size
+    pub fn get_bytes(&self, buf: &mut [u8]) -> Result<()> {
+        get_mem_op_value_buf(self.ptr(), buf)
+    }
+
+    /// Set the data of this memory operation from `bytes`, which must be exactly its size
+    pub fn set_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        set_mem_op_value_buf(self.as_mut_ptr(), bytes)
+    }
+
+    /// The x86 specific memory transaction this memory operation is part of, if any
+    pub fn as_x86(&mut self) -> Result<Option<&mut X86MemoryTransaction>> {
+        Ok(x86_mem_trans_from_generic(self.as_mut_ptr())?.map(|t| unsafe { &mut *t }))
+    }
+
+    /// The ARM specific memory transaction this memory operation is part of, if any
+    pub fn as_arm(&mut self) -> Result<Option<&mut ArmMemoryTransaction>> {
+        Ok(arm_mem_trans_from_generic(self.as_mut_ptr())?.map(|t| unsafe { &mut *t }))
+    }
+
+    /// The MIPS specific memory transaction this memory operation is part of, if any
+    pub fn as_mips(&mut self) -> Result<Option<&mut MipsMemoryTransaction>> {
+        Ok(mips_mem_trans_from_generic(self.as_mut_ptr())?.map(|t| unsafe { &mut *t }))
+    }
+
+    /// The PowerPC specific memory transaction this memory operation is part of, if any
+    pub fn as_ppc(&mut self) -> Result<Option<&mut PpcMemoryTransaction>> {
+        Ok(ppc_mem_trans_from_generic(self.as_mut_ptr())?.map(|t| unsafe { &mut *t }))
+    }
+
+    /// The PCI specific memory transaction this memory operation is part of, if any
+    pub fn as_pci(&mut self) -> Result<Option<&mut PciMemoryTransaction>> {
+        Ok(pci_mem_trans_from_generic(self.as_mut_ptr())?.map(|t| unsafe { &mut *t }))
+    }
+}
@@ -3,20 +3,32 @@
 
 //! Base API
 
+pub mod atom;
 pub mod attr_value;
+pub mod bank_instrumentation;
 pub mod conf_object;
 pub mod event;
+pub mod global_message;
+pub mod map_target;
 pub mod memory_transaction;
+pub mod notifier;
 pub mod sim_exception;
 pub mod sobject;
 pub mod time;
+pub mod transaction;
 pub mod version;
 
+pub use atom::*;
 pub use attr_value::*;
+pub use bank_instrumentation::*;
 pub use conf_object::*;
 pub use event::*;
+pub use global_message::*;
+pub use map_target::*;
 pub use memory_transaction::*;
+pub use notifier::*;
 pub use sim_exception::*;
 pub use sobject::*;
 pub use time::*;
+pub use transaction::*;
 pub use version::*;
@@ -0,0 +1,643 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Notifiers
+//!
+//! A notifier is an event which an object can notify subscribers of. Each notifier type has a
+//! name and a type id assigned by the simulator, and must be registered on the classes whose
+//! objects notify it.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    get_object, last_error, log_error, simics_exception,
+    sys::{
+        global_notifier_callback_t, global_notifier_type_t, notifier_handle_t, notifier_type_t,
+        SIM_add_global_notifier, SIM_add_global_notifier_once, SIM_add_notifier,
+        SIM_class_has_notifier, SIM_delete_global_notifier, SIM_delete_notifier, SIM_has_notifier,
+        SIM_notifier_description, SIM_notifier_type, SIM_notify, SIM_register_notifier,
+        SIM_register_tracked_notifier,
+    },
+    CallbackStream, ConfClass, ConfObject, Error, Result,
+};
+use raw_cstr::raw_cstr;
+use std::{
+    ffi::{c_void, CStr},
+    ptr::null_mut,
+};
+
+/// Alias for `notifier_type_t`
+pub type NotifierType = notifier_type_t;
+/// Alias for `notifier_handle_t`
+pub type NotifierHandle = notifier_handle_t;
+/// Alias for `global_notifier_type_t`
+pub type GlobalNotifierType = global_notifier_type_t;
+/// Alias for `global_notifier_callback_t`
+pub type GlobalNotifierHandle = global_notifier_callback_t;
+
+/// The type of a callback which is called when the first subscriber to a tracked notifier is
+/// added or the last subscriber is removed
+pub type NotifierSubscribedChangedCallback =
+    unsafe extern "C" fn(obj: *mut ConfObject, what: NotifierType, has_subscribers: bool);
+
+type NotifierCallback = Box<dyn FnMut(*mut ConfObject) + 'static>;
+
+#[derive(Debug)]
+/// A subscription to the deletion of an object, recording whether the object has been
+/// deleted. The simulator removes subscriptions to and by an object when it is deleted, so
+/// this is used to find out whether a subscription still needs to be removed.
+pub(crate) struct ObjectDeleteWatch {
+    obj: *mut ConfObject,
+    handle: *mut NotifierHandle,
+    deleted: *mut bool,
+}
+
+impl ObjectDeleteWatch {
+    pub(crate) fn new(obj: *mut ConfObject) -> Result<Self> {
+        let deleted = Box::into_raw(Box::new(false));
+
+        let handle = unsafe {
+            SIM_add_notifier(
+                obj,
+                NotifierType::Sim_Notify_Object_Delete,
+                null_mut(),
+                Some(handle_object_delete),
+                deleted as *mut c_void,
+            )
+        };
+
+        if handle.is_null() {
+            drop(unsafe { Box::from_raw(deleted) });
+            Err(Error::AddNotifier {
+                message: last_error(),
+            })
+        } else {
+            Ok(Self {
+                obj,
+                handle,
+                deleted,
+            })
+        }
+    }
+
+    pub(crate) fn is_deleted(&self) -> bool {
+        unsafe { *self.deleted }
+    }
+}
+
+impl Drop for ObjectDeleteWatch {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.is_deleted() {
+                SIM_delete_notifier(self.obj, self.handle);
+            }
+            drop(Box::from_raw(self.deleted));
+        }
+    }
+}
+
+extern "C" fn handle_object_delete(
+    _subscriber: *mut ConfObject,
+    _notifier: *mut ConfObject,
+    data: *mut c_void,
+) {
+    // NOTE: The flag is owned by its watch and freed when it is dropped
+    unsafe { *(data as *mut bool) = true };
+}
+
+/// A subscription to a notifier of an object. The subscription is removed and its callback is
+/// freed when it is dropped.
+///
+/// The simulator removes the subscription itself when the notifying object or the subscriber
+/// is deleted, after which dropping the subscription only frees its callback.
+#[derive(Debug)]
+#[must_use = "the notifier callback is removed when the subscription is dropped"]
+pub struct NotifierSubscription {
+    obj: *mut ConfObject,
+    handle: *mut NotifierHandle,
+    callback: *mut NotifierCallback,
+    // NOTE: Fields are dropped after `drop` runs, so the watches outlive the check of whether
+    // the subscription is still active
+    watches: Vec<ObjectDeleteWatch>,
+}
+
+impl NotifierSubscription {
+    /// Get the object the subscription was added to
+    pub fn object(&self) -> *mut ConfObject {
+        self.obj
+    }
+
+    /// Get the raw handle of the subscription
+    pub fn handle(&self) -> *mut NotifierHandle {
+        self.handle
+    }
+
+    /// Check whether the subscription is still active. A subscription is inactive once the
+    /// notifying object or the subscriber has been deleted.
+    pub fn is_active(&self) -> bool {
+        self.watches.iter().all(|w| !w.is_deleted())
+    }
+
+    /// Keep the subscription for the rest of the simulation, or until the notifying object
+    /// or the subscriber is deleted. Its callback is never freed.
+    pub fn forget(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for NotifierSubscription {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_active() {
+                SIM_delete_notifier(self.obj, self.handle);
+            }
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+/// The callback of a global notifier subscription. The callback of a one-shot subscription
+/// is removed once it has been called, at which point the simulator has also removed the
+/// subscription.
+struct GlobalNotifierCallback {
+    callback: Option<Box<dyn FnMut() -> Result<()> + 'static>>,
+    subscriber: *mut ConfObject,
+    once: bool,
+}
+
+/// A subscription to a global notifier. The subscription is removed and its callback is freed
+/// when it is dropped.
+///
+/// The simulator removes the subscription itself when the subscriber is deleted, after which
+/// dropping the subscription only frees its callback.
+#[derive(Debug)]
+#[must_use = "the global notifier callback is removed when the subscription is dropped"]
+pub struct GlobalNotifierSubscription {
+    handle: *mut GlobalNotifierHandle,
+    callback: *mut GlobalNotifierCallback,
+    // NOTE: Fields are dropped after `drop` runs, so the watch outlives the check of whether
+    // the subscription is still active
+    watch: Option<ObjectDeleteWatch>,
+}
+
+impl GlobalNotifierSubscription {
+    /// Get the raw handle of the subscription
+    pub fn handle(&self) -> *mut GlobalNotifierHandle {
+        self.handle
+    }
+
+    /// Check whether the subscription is still active. A one-shot subscription is inactive
+    /// once its callback has been called, and any subscription is inactive once its
+    /// subscriber has been deleted.
+    pub fn is_active(&self) -> bool {
+        let pending = unsafe { (*self.callback).callback.is_some() };
+        pending && self.watch.as_ref().is_none_or(|w| !w.is_deleted())
+    }
+
+    /// Keep the subscription for the rest of the simulation, or until the subscriber is
+    /// deleted. Its callback is never freed.
+    pub fn forget(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for GlobalNotifierSubscription {
+    fn drop(&mut self) {
+        unsafe {
+            if self.is_active() {
+                SIM_delete_global_notifier(self.handle);
+            }
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+extern "C" fn handle_global_notifier_callback(_subscriber: *mut ConfObject, data: *mut c_void) {
+    // NOTE: The callback is owned by its subscription and freed when it is dropped
+    let state = unsafe { &mut *(data as *mut GlobalNotifierCallback) };
+
+    if let Some(Err(e)) = state.callback.as_mut().map(|callback| callback()) {
+        // NOTE: Errors cannot be returned to the simulator, so they are logged on the
+        // subscriber, or the sim object if there is none
+        let obj = (!state.subscriber.is_null())
+            .then_some(state.subscriber)
+            .or_else(|| get_object("sim").ok());
+
+        if let Some(obj) = obj {
+            log_error(obj, format!("Global notifier callback failed: {e}")).ok();
+        }
+    }
+
+    if state.once {
+        state.callback = None;
+    }
+}
+
+extern "C" fn handle_notifier_callback(
+    _subscriber: *mut ConfObject,
+    notifier: *mut ConfObject,
+    data: *mut c_void,
+) {
+    // NOTE: The callback is owned by its subscription and freed when it is dropped
+    let callback = unsafe { &mut *(data as *mut NotifierCallback) };
+    callback(notifier)
+}
+
+#[simics_exception]
+/// Get the notifier type with a name, creating a new notifier type if none exists
+///
+/// # Arguments
+///
+/// * `name` - The name of the notifier type, for example `"frequency-change"`
+///
+/// # Return Value
+///
+/// The notifier type
+///
+/// # Context
+///
+/// Global Context
+pub fn notifier_type<S>(name: S) -> Result<NotifierType>
+where
+    S: AsRef<str>,
+{
+    Ok(unsafe { SIM_notifier_type(raw_cstr(name)?) })
+}
+
+#[simics_exception]
+/// Get the description of a notifier type
+///
+/// # Arguments
+///
+/// * `what` - The notifier type to get the description of
+///
+/// # Return Value
+///
+/// The description of the notifier type
+///
+/// # Context
+///
+/// Global Context
+pub fn notifier_description(what: NotifierType) -> Result<String> {
+    let desc = unsafe { SIM_notifier_description(what) };
+
+    if desc.is_null() {
+        Ok(String::new())
+    } else {
+        Ok(unsafe { CStr::from_ptr(desc) }.to_str()?.to_string())
+    }
+}
+
+#[simics_exception]
+/// Register that objects of a class notify the notifier type `what`
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the notifier on
+/// * `what` - The notifier type
+/// * `desc` - A description of when objects of the class notify the notifier
+///
+/// # Context
+///
+/// Global Context
+pub fn register_notifier<S>(cls: *mut ConfClass, what: NotifierType, desc: S) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    unsafe { SIM_register_notifier(cls, what, raw_cstr(desc)?) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Register that objects of a class notify the notifier type `what`, and should be told when
+/// the notifier gains its first subscriber or loses its last one. Objects can use this to
+/// avoid doing work to notify a notifier nobody is subscribed to.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the notifier on
+/// * `what` - The notifier type
+/// * `desc` - A description of when objects of the class notify the notifier
+/// * `subscribed_changed` - A callback called when the subscription state changes
+///
+/// # Context
+///
+/// Global Context
+pub fn register_tracked_notifier<S>(
+    cls: *mut ConfClass,
+    what: NotifierType,
+    desc: S,
+    subscribed_changed: Option<NotifierSubscribedChangedCallback>,
+) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    unsafe { SIM_register_tracked_notifier(cls, what, raw_cstr(desc)?, subscribed_changed) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Subscribe to the notifier `what` of an object. The callback is called with the notifying
+/// object each time the object notifies the notifier, until the returned subscription is
+/// dropped.
+///
+/// # Arguments
+///
+/// * `obj` - The object to subscribe to the notifier of
+/// * `what` - The notifier type
+/// * `subscriber` - The object subscribing, if any. The subscription is removed when the
+///   subscriber is deleted.
+/// * `callback` - The closure to call when the object notifies the notifier
+///
+/// # Return Value
+///
+/// The subscription, which unsubscribes when dropped
+///
+/// # Context
+///
+/// Global Context
+pub fn add_notifier<F>(
+    obj: *mut ConfObject,
+    what: NotifierType,
+    subscriber: Option<*mut ConfObject>,
+    callback: F,
+) -> Result<NotifierSubscription>
+where
+    F: FnMut(*mut ConfObject) + 'static,
+{
+    let watches = std::iter::once(obj)
+        .chain(subscriber.filter(|s| *s != obj))
+        .map(ObjectDeleteWatch::new)
+        .collect::<Result<Vec<_>>>()?;
+
+    let callback: *mut NotifierCallback = Box::into_raw(Box::new(Box::new(callback)));
+
+    let handle = unsafe {
+        SIM_add_notifier(
+            obj,
+            what,
+            subscriber.unwrap_or(null_mut()),
+            Some(handle_notifier_callback),
+            callback as *mut c_void,
+        )
+    };
+
+    if handle.is_null() {
+        drop(unsafe { Box::from_raw(callback) });
+        Err(Error::AddNotifier {
+            message: last_error(),
+        })
+    } else {
+        Ok(NotifierSubscription {
+            obj,
+            handle,
+            callback,
+            watches,
+        })
+    }
+}
+
+/// Subscribe to the notifier `what` of an object as a stream, which yields the notifying
+/// object each time the object notifies the notifier, until the stream is dropped.
+///
+/// # Arguments
+///
+/// * `obj` - The object to subscribe to the notifier of
+/// * `what` - The notifier type
+/// * `subscriber` - The object subscribing, if any. The subscription is removed when the
+///   subscriber is deleted.
+///
+/// # Context
+///
+/// Global Context
+pub fn notifier_stream(
+    obj: *mut ConfObject,
+    what: NotifierType,
+    subscriber: Option<*mut ConfObject>,
+) -> Result<CallbackStream<*mut ConfObject, NotifierSubscription>> {
+    CallbackStream::new(|sender| add_notifier(obj, what, subscriber, move |obj| sender.send(obj)))
+}
+
+#[simics_exception]
+/// Remove a notifier subscription by its raw handle. The callback of the subscription is not
+/// freed, so this should only be used on subscriptions which have been forgotten.
+///
+/// # Arguments
+///
+/// * `obj` - The object the subscription was added to
+/// * `handle` - The handle of the subscription
+///
+/// # Context
+///
+/// Global Context
+pub fn delete_notifier(obj: *mut ConfObject, handle: *mut NotifierHandle) {
+    unsafe { SIM_delete_notifier(obj, handle) };
+}
+
+#[simics_exception]
+/// Notify the subscribers of the notifier `what` of an object
+///
+/// # Arguments
+///
+/// * `obj` - The notifying object
+/// * `what` - The notifier type
+///
+/// # Context
+///
+/// Cell Context
+pub fn notify(obj: *mut ConfObject, what: NotifierType) {
+    unsafe { SIM_notify(obj, what) };
+}
+
+#[simics_exception]
+/// Check whether an object has registered the notifier `what`
+///
+/// # Arguments
+///
+/// * `obj` - The object to check
+/// * `what` - The notifier type
+///
+/// # Return Value
+///
+/// Whether the object's class registered the notifier
+///
+/// # Context
+///
+/// All Contexts
+pub fn has_notifier(obj: *mut ConfObject, what: NotifierType) -> bool {
+    unsafe { SIM_has_notifier(obj, what) }
+}
+
+#[simics_exception]
+/// Check whether a class has registered the notifier `what`
+///
+/// # Arguments
+///
+/// * `cls` - The class to check
+/// * `what` - The notifier type
+///
+/// # Return Value
+///
+/// Whether the class registered the notifier
+///
+/// # Context
+///
+/// All Contexts
+pub fn class_has_notifier(cls: *mut ConfClass, what: NotifierType) -> bool {
+    unsafe { SIM_class_has_notifier(cls, what) }
+}
+
+/// Add a subscription to a global notifier, using the one-shot variant if `once` is set
+fn add_global_notifier_callback(
+    what: GlobalNotifierType,
+    subscriber: Option<*mut ConfObject>,
+    callback: Box<dyn FnMut() -> Result<()> + 'static>,
+    once: bool,
+) -> Result<GlobalNotifierSubscription> {
+    let watch = subscriber.map(ObjectDeleteWatch::new).transpose()?;
+    let subscriber = subscriber.unwrap_or(null_mut());
+    let callback = Box::into_raw(Box::new(GlobalNotifierCallback {
+        callback: Some(callback),
+        subscriber,
+        once,
+    }));
+
+    let handle = unsafe {
+        if once {
+            SIM_add_global_notifier_once(
+                what,
+                subscriber,
+                Some(handle_global_notifier_callback),
+                callback as *mut c_void,
+            )
+        } else {
+            SIM_add_global_notifier(
+                what,
+                subscriber,
+                Some(handle_global_notifier_callback),
+                callback as *mut c_void,
+            )
+        }
+    };
+
+    if handle.is_null() {
+        drop(unsafe { Box::from_raw(callback) });
+        Err(Error::AddNotifier {
+            message: last_error(),
+        })
+    } else {
+        Ok(GlobalNotifierSubscription {
+            handle,
+            callback,
+            watch,
+        })
+    }
+}
+
+#[simics_exception]
+/// Subscribe to a global notifier. The callback is called each time the global notifier is
+/// notified, until the returned subscription is dropped.
+///
+/// # Arguments
+///
+/// * `what` - The global notifier type, for example
+///   [`GlobalNotifierType::Sim_Global_Notify_Object_Delete`]
+/// * `subscriber` - The object subscribing, if any. The subscription is removed when the
+///   subscriber is deleted.
+/// * `cb` - The closure to call when the global notifier is notified
+///
+/// # Return Value
+///
+/// The subscription, which unsubscribes when dropped
+///
+/// # Context
+///
+/// Global Context
+/// Callback: Global Context
+pub fn add_global_notifier<F>(
+    what: GlobalNotifierType,
+    subscriber: Option<*mut ConfObject>,
+    cb: F,
+) -> Result<GlobalNotifierSubscription>
+where
+    F: FnMut() -> Result<()> + 'static,
+{
+    add_global_notifier_callback(what, subscriber, Box::new(cb), false)
+}
+
+#[simics_exception]
+/// Subscribe to the next notification of a global notifier. The callback is called the first
+/// time the global notifier is notified, after which the subscription is removed. Dropping
+/// the returned subscription before then unsubscribes without calling the callback.
+///
+/// # Arguments
+///
+/// * `what` - The global notifier type, for example
+///   [`GlobalNotifierType::Sim_Global_Notify_Before_Snapshot_Restore`]
+/// * `subscriber` - The object subscribing, if any. The subscription is removed when the
+///   subscriber is deleted.
+/// * `cb` - The closure to call when the global notifier is notified
+///
+/// # Return Value
+///
+/// The subscription, which unsubscribes when dropped
+///
+/// # Context
+///
+/// Global Context
+/// Callback: Global Context
+pub fn add_global_notifier_once<F>(
+    what: GlobalNotifierType,
+    subscriber: Option<*mut ConfObject>,
+    cb: F,
+) -> Result<GlobalNotifierSubscription>
+where
+    F: FnOnce() -> Result<()> + 'static,
+{
+    let mut cb = Some(cb);
+    add_global_notifier_callback(
+        what,
+        subscriber,
+        Box::new(move || cb.take().map_or(Ok(()), |cb| cb())),
+        true,
+    )
+}
+
+/// Subscribe to a global notifier as a stream, which yields each time the global notifier is
+/// notified, until the stream is dropped.
+///
+/// # Arguments
+///
+/// * `what` - The global notifier type
+/// * `subscriber` - The object subscribing, if any. The subscription is removed when the
+///   subscriber is deleted.
+///
+/// # Context
+///
+/// Global Context
+pub fn global_notifier_stream(
+    what: GlobalNotifierType,
+    subscriber: Option<*mut ConfObject>,
+) -> Result<CallbackStream<(), GlobalNotifierSubscription>> {
+    CallbackStream::new(|sender| {
+        add_global_notifier(what, subscriber, move || {
+            sender.send(());
+            Ok(())
+        })
+    })
+}
+
+#[simics_exception]
+/// Remove a global notifier subscription by its raw handle. The callback of the subscription
+/// is not freed, so this should only be used on subscriptions which have been forgotten.
+///
+/// # Arguments
+///
+/// * `handle` - The handle of the subscription
+///
+/// # Context
+///
+/// Global Context
+pub fn delete_global_notifier(handle: *mut GlobalNotifierHandle) {
+    unsafe { SIM_delete_global_notifier(handle) };
+}
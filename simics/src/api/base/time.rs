@@ -4,12 +4,13 @@
 //! Time management APIs
 
 use crate::{
-    simics_exception,
+    last_error, simics_exception,
     sys::{
-        SIM_cycle_count, SIM_object_clock, SIM_picosecond_clock, SIM_stall, SIM_stall_count,
-        SIM_stall_cycle, SIM_stalled_until, SIM_time,
+        cycle_interface_t, SIM_cycle_count, SIM_object_clock, SIM_picosecond_clock,
+        SIM_register_clock, SIM_stall, SIM_stall_count, SIM_stall_cycle, SIM_stalled_until,
+        SIM_time,
     },
-    ConfObject, Cycles,
+    ConfClass, ConfObject, Cycles, Error, Result,
 };
 
 #[simics_exception]
@@ -130,3 +131,28 @@ pub fn object_clock(obj: *const ConfObject) -> *mut ConfObject {
 pub fn picosecond_clock(obj: *mut ConfObject) -> *mut ConfObject {
     unsafe { SIM_picosecond_clock(obj) }
 }
+
+#[simics_exception]
+/// Register a class as a class of device clocks. In addition to the `cycle` interface
+/// `iface`, the simulator registers the interfaces, attributes and port objects which
+/// objects of the class need to keep time and run events, such as the `cycle_event`
+/// interface and the `vtime` port objects.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register as a clock
+/// * `iface` - The `cycle` interface of the class, which must not be freed
+///
+/// # Context
+///
+/// Global Context
+pub fn register_clock(cls: *mut ConfClass, iface: *const cycle_interface_t) -> Result<()> {
+    if unsafe { SIM_register_clock(cls, iface) } != 0 {
+        Err(Error::RegisterInterface {
+            name: "cycle".to_string(),
+            message: last_error(),
+        })
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,1327 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! High level APIs for the `transaction_t` memory transaction type
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    api::base::atom::{decode_atom_value, encode_atom_value, lookup_transaction_atom},
+    get_object, log_error, simics_exception,
+    sys::{
+        atom_t, buffer_t, bytes_t, exception_type_t, transaction_t, SIM_complete_transaction,
+        SIM_defer_owned_transaction, SIM_defer_transaction, SIM_get_transaction_bytes,
+        SIM_get_transaction_bytes_offs, SIM_get_transaction_id, SIM_get_transaction_value_be,
+        SIM_get_transaction_value_le, SIM_monitor_transaction, SIM_poll_transaction,
+        SIM_reconnect_transaction, SIM_set_transaction_bytes, SIM_set_transaction_bytes_constant,
+        SIM_set_transaction_bytes_offs, SIM_set_transaction_value_be, SIM_set_transaction_value_le,
+        SIM_transaction_flags, SIM_transaction_initiator, SIM_transaction_is_deferrable,
+        SIM_transaction_is_fetch, SIM_transaction_is_inquiry, SIM_transaction_is_read,
+        SIM_transaction_is_write, SIM_transaction_size, SIM_transaction_wait,
+    },
+    AtomType, CompletionAtom, ConfObject, DataAtom, Error, FlagsAtom, InitiatorAtom, ListEndAtom,
+    MapTarget, OwnerAtom, Result, SizeAtom,
+};
+use std::{
+    cell::Cell,
+    mem::{forget, take, ManuallyDrop},
+    ops::{Deref, DerefMut},
+    ptr::null_mut,
+    rc::Rc,
+};
+
+/// Flags of a transaction
+pub use crate::api::sys::transaction_flags_t as TransactionFlags;
+/// Alias for `exception_type_t`, the result of issuing a transaction
+pub type ExceptionType = exception_type_t;
+
+/// The maximum size of a transaction whose value can be accessed as an integer
+const TRANSACTION_VALUE_MAX_SIZE: usize = std::mem::size_of::<u64>();
+
+#[simics_exception]
+/// Check whether a transaction is a read transaction
+///
+/// # Arguments
+///
+/// * `t` - The transaction to check
+///
+/// # Return Value
+///
+/// Whether the transaction is a read transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_is_read(t: *mut transaction_t) -> bool {
+    unsafe { SIM_transaction_is_read(t) }
+}
+
+#[simics_exception]
+/// Check whether a transaction is a write transaction
+///
+/// # Arguments
+///
+/// * `t` - The transaction to check
+///
+/// # Return Value
+///
+/// Whether the transaction is a write transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_is_write(t: *mut transaction_t) -> bool {
+    unsafe { SIM_transaction_is_write(t) }
+}
+
+#[simics_exception]
+/// Check whether a transaction is an instruction fetch transaction
+///
+/// # Arguments
+///
+/// * `t` - The transaction to check
+///
+/// # Return Value
+///
+/// Whether the transaction is an instruction fetch transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_is_fetch(t: *mut transaction_t) -> bool {
+    unsafe { SIM_transaction_is_fetch(t) }
+}
+
+#[simics_exception]
+/// Check whether a transaction is an inquiry transaction. Inquiry transactions must not have
+/// any side effects on the target.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to check
+///
+/// # Return Value
+///
+/// Whether the transaction is an inquiry transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_is_inquiry(t: *mut transaction_t) -> bool {
+    unsafe { SIM_transaction_is_inquiry(t) }
+}
+
+#[simics_exception]
+/// Check whether a transaction may be deferred with `SIM_defer_transaction`
+///
+/// # Arguments
+///
+/// * `t` - The transaction to check
+///
+/// # Return Value
+///
+/// Whether the transaction is deferrable
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_is_deferrable(t: *mut transaction_t) -> bool {
+    unsafe { SIM_transaction_is_deferrable(t) }
+}
+
+#[simics_exception]
+/// Get the size of a transaction in bytes
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the size of
+///
+/// # Return Value
+///
+/// The size of the transaction in bytes
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_size(t: *mut transaction_t) -> u32 {
+    unsafe { SIM_transaction_size(t) }
+}
+
+#[simics_exception]
+/// Get the flags of a transaction
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the flags of
+///
+/// # Return Value
+///
+/// The flags set on the transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_flags(t: *mut transaction_t) -> TransactionFlags {
+    unsafe { SIM_transaction_flags(t) }
+}
+
+#[simics_exception]
+/// Get the initiator of a transaction, if one is set
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the initiator of
+///
+/// # Return Value
+///
+/// The initiator of the transaction, or `None` if the transaction has no initiator
+///
+/// # Context
+///
+/// All Contexts
+pub fn transaction_initiator(t: *mut transaction_t) -> Option<*mut ConfObject> {
+    let initiator = unsafe { SIM_transaction_initiator(t) };
+    (!initiator.is_null()).then_some(initiator)
+}
+
+#[simics_exception]
+/// Get the value of a transaction interpreted as a little endian integer. The size of the
+/// transaction must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the value of
+///
+/// # Return Value
+///
+/// The value of the transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_transaction_value_le(t: *mut transaction_t) -> Result<u64> {
+    check_transaction_value_size(t)?;
+    Ok(unsafe { SIM_get_transaction_value_le(t) })
+}
+
+#[simics_exception]
+/// Get the value of a transaction interpreted as a big endian integer. The size of the
+/// transaction must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the value of
+///
+/// # Return Value
+///
+/// The value of the transaction
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_transaction_value_be(t: *mut transaction_t) -> Result<u64> {
+    check_transaction_value_size(t)?;
+    Ok(unsafe { SIM_get_transaction_value_be(t) })
+}
+
+#[simics_exception]
+/// Set the value of a transaction, encoded as a little endian integer of the size of the
+/// transaction. The size of the transaction must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to set the value of
+/// * `value` - The value to set
+///
+/// # Context
+///
+/// All Contexts
+pub fn set_transaction_value_le(t: *mut transaction_t, value: u64) -> Result<()> {
+    check_transaction_value_size(t)?;
+    unsafe { SIM_set_transaction_value_le(t, value) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Set the value of a transaction, encoded as a big endian integer of the size of the
+/// transaction. The size of the transaction must be at most 8 bytes.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to set the value of
+/// * `value` - The value to set
+///
+/// # Context
+///
+/// All Contexts
+pub fn set_transaction_value_be(t: *mut transaction_t, value: u64) -> Result<()> {
+    check_transaction_value_size(t)?;
+    unsafe { SIM_set_transaction_value_be(t, value) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Copy the data of a transaction into a buffer. The buffer must be exactly the size of the
+/// transaction.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the data of
+/// * `buf` - The buffer to copy the data into
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_transaction_bytes(t: *mut transaction_t, buf: &mut [u8]) -> Result<()> {
+    check_transaction_len(t, buf.len())?;
+    unsafe {
+        SIM_get_transaction_bytes(
+            t,
+            buffer_t {
+                data: buf.as_mut_ptr(),
+                len: buf.len(),
+            },
+        )
+    };
+    Ok(())
+}
+
+#[simics_exception]
+/// Copy part of the data of a transaction, starting at offset `offs`, into a buffer. The
+/// range must be contained in the transaction. If `zerofill_holes` is set, bytes not
+/// provided by the transaction are filled with zeroes.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to get the data of
+/// * `offs` - The offset into the transaction to start copying from
+/// * `buf` - The buffer to copy the data into
+/// * `zerofill_holes` - Whether to zero-fill holes in the transaction data
+///
+/// # Context
+///
+/// All Contexts
+pub fn get_transaction_bytes_offs(
+    t: *mut transaction_t,
+    offs: u32,
+    buf: &mut [u8],
+    zerofill_holes: bool,
+) -> Result<()> {
+    check_transaction_range(t, offs as usize, buf.len())?;
+    unsafe {
+        SIM_get_transaction_bytes_offs(
+            t,
+            offs,
+            buffer_t {
+                data: buf.as_mut_ptr(),
+                len: buf.len(),
+            },
+            zerofill_holes,
+        )
+    };
+    Ok(())
+}
+
+#[simics_exception]
+/// Set the data of a transaction from a byte slice. The slice must be exactly the size of
+/// the transaction.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to set the data of
+/// * `bytes` - The data to set
+///
+/// # Context
+///
+/// All Contexts
+pub fn set_transaction_bytes(t: *mut transaction_t, bytes: &[u8]) -> Result<()> {
+    check_transaction_len(t, bytes.len())?;
+    unsafe {
+        SIM_set_transaction_bytes(
+            t,
+            bytes_t {
+                data: bytes.as_ptr(),
+                len: bytes.len(),
+            },
+        )
+    };
+    Ok(())
+}
+
+#[simics_exception]
+/// Set part of the data of a transaction, starting at offset `offs`, from a byte slice. The
+/// range must be contained in the transaction.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to set the data of
+/// * `offs` - The offset into the transaction to start writing at
+/// * `bytes` - The data to set
+///
+/// # Context
+///
+/// All Contexts
+pub fn set_transaction_bytes_offs(t: *mut transaction_t, offs: u32, bytes: &[u8]) -> Result<()> {
+    check_transaction_range(t, offs as usize, bytes.len())?;
+    unsafe {
+        SIM_set_transaction_bytes_offs(
+            t,
+            offs,
+            bytes_t {
+                data: bytes.as_ptr(),
+                len: bytes.len(),
+            },
+        )
+    };
+    Ok(())
+}
+
+#[simics_exception]
+/// Set every byte of the data of a transaction to `value`
+///
+/// # Arguments
+///
+/// * `t` - The transaction to set the data of
+/// * `value` - The byte value to fill the transaction with
+///
+/// # Context
+///
+/// All Contexts
+pub fn set_transaction_bytes_constant(t: *mut transaction_t, value: u8) {
+    unsafe { SIM_set_transaction_bytes_constant(t, value) }
+}
+
+#[simics_exception]
+/// Defer a transaction received by a target, so that it can be completed later with
+/// [`complete_transaction`]. The returned transaction must be used in place of `t` from
+/// then on.
+///
+/// # Arguments
+///
+/// * `obj` - The object deferring the transaction, which is typically the target
+/// * `t` - The transaction to defer
+///
+/// # Return Value
+///
+/// The deferred transaction, or `None` if the transaction cannot be deferred
+///
+/// # Context
+///
+/// Cell Context
+pub fn defer_transaction(
+    obj: *mut ConfObject,
+    t: *mut transaction_t,
+) -> Option<*mut transaction_t> {
+    let deferred = unsafe { SIM_defer_transaction(obj, t) };
+    (!deferred.is_null()).then_some(deferred)
+}
+
+#[simics_exception]
+/// Defer a transaction owned by the caller, such as a transaction created by the initiator
+/// itself. The returned transaction must be completed with [`complete_transaction`].
+///
+/// # Arguments
+///
+/// * `t` - The transaction to defer
+///
+/// # Return Value
+///
+/// The deferred transaction, or `None` if the transaction cannot be deferred
+///
+/// # Context
+///
+/// Cell Context
+pub fn defer_owned_transaction(t: *mut transaction_t) -> Option<*mut transaction_t> {
+    let deferred = unsafe { SIM_defer_owned_transaction(t) };
+    (!deferred.is_null()).then_some(deferred)
+}
+
+#[simics_exception]
+/// Complete a deferred transaction. The transaction must not be accessed after it is
+/// completed.
+///
+/// # Arguments
+///
+/// * `t` - The deferred transaction to complete
+/// * `ex` - The completion status of the transaction
+///
+/// # Context
+///
+/// Cell Context
+pub fn complete_transaction(t: *mut transaction_t, ex: ExceptionType) {
+    unsafe { SIM_complete_transaction(t, ex) }
+}
+
+#[simics_exception]
+/// Wait for a transaction to complete. If the transaction was deferred, the simulation
+/// continues running until it completes.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to wait for
+/// * `ex` - The status returned when the transaction was issued
+///
+/// # Return Value
+///
+/// The completion status of the transaction
+///
+/// # Context
+///
+/// Cell Context
+pub fn transaction_wait(t: *mut transaction_t, ex: ExceptionType) -> ExceptionType {
+    unsafe { SIM_transaction_wait(t, ex) }
+}
+
+#[simics_exception]
+/// Check whether a deferred transaction has completed
+///
+/// # Arguments
+///
+/// * `t` - The transaction to poll
+///
+/// # Return Value
+///
+/// The completion status of the transaction, or `Sim_PE_Deferred` if it has not completed
+///
+/// # Context
+///
+/// Cell Context
+pub fn poll_transaction(t: *mut transaction_t) -> ExceptionType {
+    unsafe { SIM_poll_transaction(t) }
+}
+
+#[simics_exception]
+/// Monitor a transaction for completion. If the transaction was deferred, its completion
+/// callback is called when it completes, otherwise it is called immediately.
+///
+/// # Arguments
+///
+/// * `t` - The transaction to monitor
+/// * `ex` - The status returned when the transaction was issued
+///
+/// # Return Value
+///
+/// The status to return from the issuing function
+///
+/// # Context
+///
+/// Cell Context
+pub fn monitor_transaction(t: *mut transaction_t, ex: ExceptionType) -> ExceptionType {
+    unsafe { SIM_monitor_transaction(t, ex) }
+}
+
+#[simics_exception]
+/// Get the id of a deferred transaction, which can be saved in a checkpoint and later used
+/// to reconnect the transaction with [`reconnect_transaction`]
+///
+/// # Arguments
+///
+/// * `t` - The deferred transaction
+///
+/// # Return Value
+///
+/// The id of the transaction
+///
+/// # Context
+///
+/// Cell Context
+pub fn get_transaction_id(t: *mut transaction_t) -> i64 {
+    unsafe { SIM_get_transaction_id(t) }
+}
+
+#[simics_exception]
+/// Reconnect a transaction restored from a checkpoint with the deferred transaction with
+/// the id `id`
+///
+/// # Arguments
+///
+/// * `t` - The transaction to reconnect
+/// * `id` - The id of the deferred transaction, as returned by [`get_transaction_id`]
+///
+/// # Context
+///
+/// Cell Context
+pub fn reconnect_transaction(t: *mut transaction_t, id: i64) {
+    unsafe { SIM_reconnect_transaction(t, id) }
+}
+
+/// Check that the transaction is small enough to have its value accessed as an integer
+fn check_transaction_value_size(t: *mut transaction_t) -> Result<()> {
+    let size = unsafe { SIM_transaction_size(t) } as usize;
+
+    if size > TRANSACTION_VALUE_MAX_SIZE {
+        return Err(Error::ValueTooLarge {
+            expected: TRANSACTION_VALUE_MAX_SIZE,
+            actual: size,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that a buffer of `len` bytes covers exactly the whole transaction
+fn check_transaction_len(t: *mut transaction_t, len: usize) -> Result<()> {
+    let size = unsafe { SIM_transaction_size(t) } as usize;
+
+    if len != size {
+        return Err(Error::TransactionSizeMismatch {
+            expected: size,
+            actual: len,
+        });
+    }
+
+    Ok(())
+}
+
+/// Check that `len` bytes starting at `offset` are contained in the transaction
+fn check_transaction_range(t: *mut transaction_t, offset: usize, len: usize) -> Result<()> {
+    let size = unsafe { SIM_transaction_size(t) } as usize;
+
+    if offset.saturating_add(len) > size {
+        return Err(Error::TransactionRange { offset, len, size });
+    }
+
+    Ok(())
+}
+
+/// An integer type which can be read from and written to a transaction of the same size
+pub trait TransactionValue: Sized + Copy {
+    /// Convert the little endian value of a transaction to this type
+    fn from_le(value: u64) -> Self;
+    /// Convert the big endian value of a transaction to this type
+    fn from_be(value: u64) -> Self;
+    /// Convert this value to an integer which can be set as the value of a transaction
+    fn into_value(self) -> u64;
+}
+
+macro_rules! impl_transaction_value {
+    ($($ty:ty => $unsigned:ty),* $(,)?) => {
+        $(
+            impl TransactionValue for $ty {
+                fn from_le(value: u64) -> Self {
+                    value as $unsigned as $ty
+                }
+
+                fn from_be(value: u64) -> Self {
+                    value as $unsigned as $ty
+                }
+
+                fn into_value(self) -> u64 {
+                    self as $unsigned as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_transaction_value! {
+    u8 => u8,
+    u16 => u16,
+    u32 => u32,
+    u64 => u64,
+    i8 => u8,
+    i16 => u16,
+    i32 => u32,
+    i64 => u64,
+}
+
+#[repr(transparent)]
+/// A memory transaction. This is a safe view of a `transaction_t` received from or passed to
+/// the simulator, and is typically obtained in the `issue` method of a transaction target.
+pub struct Transaction(transaction_t);
+
+impl Transaction {
+    /// Create a shared reference to a transaction from a raw pointer
+    ///
+    /// # Safety
+    ///
+    /// `t` must be a valid, non-null pointer to a `transaction_t` which outlives the returned
+    /// reference
+    pub unsafe fn from_raw<'a>(t: *const transaction_t) -> &'a Self {
+        &*(t as *const Self)
+    }
+
+    /// Create a mutable reference to a transaction from a raw pointer
+    ///
+    /// # Safety
+    ///
+    /// `t` must be a valid, non-null pointer to a `transaction_t` which outlives the returned
+    /// reference and which is not otherwise referenced while the returned reference exists
+    pub unsafe fn from_raw_mut<'a>(t: *mut transaction_t) -> &'a mut Self {
+        &mut *(t as *mut Self)
+    }
+
+    /// Create a new [`TransactionBuilder`] to build an [`OwnedTransaction`]
+    pub fn builder() -> TransactionBuilder {
+        TransactionBuilder::new()
+    }
+
+    /// Get a raw pointer to the underlying `transaction_t`
+    pub fn as_ptr(&self) -> *const transaction_t {
+        &self.0
+    }
+
+    /// Get a mutable raw pointer to the underlying `transaction_t`
+    pub fn as_mut_ptr(&mut self) -> *mut transaction_t {
+        &mut self.0
+    }
+
+    /// Get a raw pointer to the underlying `transaction_t` for the read-only accessors
+    fn ptr(&self) -> *mut transaction_t {
+        &self.0 as *const transaction_t as *mut transaction_t
+    }
+
+    /// Whether this transaction is a read
+    pub fn is_read(&self) -> Result<bool> {
+        transaction_is_read(self.ptr())
+    }
+
+    /// Whether this transaction is a write
+    pub fn is_write(&self) -> Result<bool> {
+        transaction_is_write(self.ptr())
+    }
+
+    /// Whether this transaction is an instruction fetch
+    pub fn is_fetch(&self) -> Result<bool> {
+        transaction_is_fetch(self.ptr())
+    }
+
+    /// Whether this transaction is an inquiry (an access without side effects)
+    pub fn is_inquiry(&self) -> Result<bool> {
+        transaction_is_inquiry(self.ptr())
+    }
+
+    /// Whether this transaction may be deferred
+    pub fn is_deferrable(&self) -> Result<bool> {
+        transaction_is_deferrable(self.ptr())
+    }
+
+    /// The size of this transaction in bytes
+    pub fn size(&self) -> Result<usize> {
+        Ok(transaction_size(self.ptr())? as usize)
+    }
+
+    /// The flags of this transaction
+    pub fn flags(&self) -> Result<TransactionFlags> {
+        transaction_flags(self.ptr())
+    }
+
+    /// The initiator of this transaction, if any
+    pub fn initiator(&self) -> Result<Option<*mut ConfObject>> {
+        transaction_initiator(self.ptr())
+    }
+
+    /// The value of this transaction as a little endian integer. The transaction must be at
+    /// most 8 bytes.
+    pub fn value_le(&self) -> Result<u64> {
+        get_transaction_value_le(self.ptr())
+    }
+
+    /// The value of this transaction as a big endian integer. The transaction must be at most
+    /// 8 bytes.
+    pub fn value_be(&self) -> Result<u64> {
+        get_transaction_value_be(self.ptr())
+    }
+
+    /// Set the value of this transaction as a little endian integer. The transaction must be
+    /// at most 8 bytes.
+    pub fn set_value_le(&mut self, value: u64) -> Result<()> {
+        set_transaction_value_le(self.as_mut_ptr(), value)
+    }
+
+    /// Set the value of this transaction as a big endian integer. The transaction must be at
+    /// most 8 bytes.
+    pub fn set_value_be(&mut self, value: u64) -> Result<()> {
+        set_transaction_value_be(self.as_mut_ptr(), value)
+    }
+
+    /// Check that the size of this transaction is exactly the size of `T`
+    fn check_value_type<T>(&self) -> Result<()>
+    where
+        T: TransactionValue,
+    {
+        let size = self.size()?;
+
+        if size != std::mem::size_of::<T>() {
+            return Err(Error::TransactionSizeMismatch {
+                expected: std::mem::size_of::<T>(),
+                actual: size,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Get the value of this transaction as a little endian integer of type `T`. The size of
+    /// the transaction must be exactly the size of `T`.
+    pub fn get_le<T>(&self) -> Result<T>
+    where
+        T: TransactionValue,
+    {
+        self.check_value_type::<T>()?;
+        self.value_le().map(T::from_le)
+    }
+
+    /// Get the value of this transaction as a big endian integer of type `T`. The size of the
+    /// transaction must be exactly the size of `T`.
+    pub fn get_be<T>(&self) -> Result<T>
+    where
+        T: TransactionValue,
+    {
+        self.check_value_type::<T>()?;
+        self.value_be().map(T::from_be)
+    }
+
+    /// Set the value of this transaction as a little endian integer of type `T`. The size of
+    /// the transaction must be exactly the size of `T`.
+    pub fn set_le<T>(&mut self, value: T) -> Result<()>
+    where
+        T: TransactionValue,
+    {
+        self.check_value_type::<T>()?;
+        self.set_value_le(value.into_value())
+    }
+
+    /// Set the value of this transaction as a big endian integer of type `T`. The size of the
+    /// transaction must be exactly the size of `T`.
+    pub fn set_be<T>(&mut self, value: T) -> Result<()>
+    where
+        T: TransactionValue,
+    {
+        self.check_value_type::<T>()?;
+        self.set_value_be(value.into_value())
+    }
+
+    /// Get a copy of the data of this transaction
+    pub fn bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = vec![0; self.size()?];
+        self.get_bytes(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Copy the data of this transaction into `buf`, which must be exactly the size of the
+    /// transaction
+    pub fn get_bytes(&self, buf: &mut [u8]) -> Result<()> {
+        get_transaction_bytes(self.ptr(), buf)
+    }
+
+    /// Copy the data of this transaction starting at offset `offs` into `buf`. If
+    /// `zerofill_holes` is set, bytes not provided by the transaction are filled with zeroes.
+    pub fn get_bytes_offs(&self, offs: usize, buf: &mut [u8], zerofill_holes: bool) -> Result<()> {
+        get_transaction_bytes_offs(self.ptr(), offs.try_into()?, buf, zerofill_holes)
+    }
+
+    /// Set the data of this transaction from `bytes`, which must be exactly the size of the
+    /// transaction
+    pub fn set_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        set_transaction_bytes(self.as_mut_ptr(), bytes)
+    }
+
+    /// Set the data of this transaction starting at offset `offs` from `bytes`
+    pub fn set_bytes_offs(&mut self, offs: usize, bytes: &[u8]) -> Result<()> {
+        set_transaction_bytes_offs(self.as_mut_ptr(), offs.try_into()?, bytes)
+    }
+
+    /// Set every byte of the data of this transaction to `value`
+    pub fn set_bytes_constant(&mut self, value: u8) -> Result<()> {
+        set_transaction_bytes_constant(self.as_mut_ptr(), value)
+    }
+
+    /// Get the value of the atom of type `A` in this transaction or in any transaction it is
+    /// chained to, or `None` if there is no such atom
+    pub fn get_atom<A>(&self) -> Result<Option<A::Value>>
+    where
+        A: AtomType,
+    {
+        Ok(lookup_transaction_atom(self.ptr(), A::id()?)?
+            .map(|atom| unsafe { decode_atom_value((*atom).ptr) }))
+    }
+
+    /// Set the value of the atom of type `A` in this transaction. Only atoms in the atom
+    /// list of this transaction itself can be modified, not those of transactions it is
+    /// chained to.
+    pub fn set_atom<A>(&mut self, value: A::Value) -> Result<()>
+    where
+        A: AtomType,
+    {
+        let id = A::id()?;
+        let list_end = ListEndAtom::id()?;
+        let mut atom = self.0.atoms;
+
+        while !atom.is_null() && unsafe { (*atom).id } != list_end {
+            if unsafe { (*atom).id } == id {
+                unsafe { (*atom).ptr = encode_atom_value(value) };
+                return Ok(());
+            }
+
+            atom = unsafe { atom.add(1) };
+        }
+
+        Err(Error::AtomNotFound {
+            name: A::NAME.to_string(),
+        })
+    }
+
+    /// Defer this transaction so it can be completed later, typically after some simulated
+    /// time has passed. The target should return `Sim_PE_Deferred` from its `issue` method
+    /// after deferring a transaction.
+    pub fn defer(&mut self, obj: *mut ConfObject) -> Result<DeferredTransaction> {
+        defer_transaction(obj, self.as_mut_ptr())?
+            .map(|t| DeferredTransaction { t, owner: None })
+            .ok_or(Error::TransactionNotDeferrable)
+    }
+}
+
+/// A closure called when a transaction built by a [`TransactionBuilder`] completes. It
+/// receives the completed transaction and the completion status, and returns the final
+/// status of the transaction.
+pub type TransactionCompletionClosure =
+    Box<dyn FnMut(&mut Transaction, ExceptionType) -> ExceptionType>;
+
+#[repr(C)]
+/// Backing storage of an [`OwnedTransaction`]. The transaction must be the first field so a
+/// pointer to it can be converted back to the storage in the completion callback.
+struct TransactionStorage {
+    transaction: transaction_t,
+    atoms: Vec<atom_t>,
+    data: Vec<u8>,
+    completion: Option<TransactionCompletionClosure>,
+    deferrable: bool,
+    /// Whether the transaction was issued and may therefore have been deferred by its target
+    issued: bool,
+    /// The number of deferred transactions created with [`OwnedTransaction::defer_owned`]
+    /// which are chained to this transaction and have not been completed
+    owned_deferrals: Rc<Cell<usize>>,
+}
+
+extern "C" fn transaction_completion_handler(
+    _obj: *mut ConfObject,
+    t: *mut transaction_t,
+    ex: ExceptionType,
+) -> ExceptionType {
+    let storage = t as *mut TransactionStorage;
+
+    // Take the closure while it runs so it can freely access the transaction. The storage is
+    // only borrowed to take and restore the closure, because the transaction passed to the
+    // closure is the first field of the storage.
+    let Some(mut completion) = (unsafe { (*storage).completion.take() }) else {
+        return ex;
+    };
+
+    let ex = completion(unsafe { Transaction::from_raw_mut(t) }, ex);
+    unsafe { (*storage).completion = Some(completion) };
+    ex
+}
+
+/// Builder for an [`OwnedTransaction`] which assembles a correctly terminated atom list
+///
+/// ```rust,ignore
+/// let mut t = Transaction::builder()
+///     .read(4)
+///     .initiator(obj)
+///     .completion(|_, ex| ex)
+///     .build()?;
+/// ```
+pub struct TransactionBuilder {
+    flags: TransactionFlags,
+    data: Vec<u8>,
+    atoms: Vec<atom_t>,
+    completion: Option<TransactionCompletionClosure>,
+    deferrable: bool,
+    prev: *mut transaction_t,
+    error: Option<Error>,
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionBuilder {
+    /// Create a new builder for a zero-sized read transaction
+    pub fn new() -> Self {
+        Self {
+            flags: TransactionFlags(0),
+            data: Vec::new(),
+            atoms: Vec::new(),
+            completion: None,
+            deferrable: false,
+            prev: null_mut(),
+            error: None,
+        }
+    }
+
+    /// Make this a read transaction of `size` bytes
+    pub fn read(mut self, size: usize) -> Self {
+        self.data = vec![0; size];
+        self
+    }
+
+    /// Make this a write transaction of `data`
+    pub fn write<D>(mut self, data: D) -> Self
+    where
+        D: Into<Vec<u8>>,
+    {
+        self.data = data.into();
+        self.flags = self.flags | TransactionFlags::Sim_Transaction_Write;
+        self
+    }
+
+    /// Make this an instruction fetch transaction of `size` bytes
+    pub fn fetch(mut self, size: usize) -> Self {
+        self.data = vec![0; size];
+        self.flags = self.flags | TransactionFlags::Sim_Transaction_Fetch;
+        self
+    }
+
+    /// Make this an inquiry transaction, which must not have side effects on the target
+    pub fn inquiry(mut self) -> Self {
+        self.flags = self.flags | TransactionFlags::Sim_Transaction_Inquiry;
+        self
+    }
+
+    /// Add flags to the transaction
+    pub fn flags(mut self, flags: TransactionFlags) -> Self {
+        self.flags = self.flags | flags;
+        self
+    }
+
+    /// Set the initiator of the transaction
+    pub fn initiator(self, initiator: *mut ConfObject) -> Self {
+        self.atom::<InitiatorAtom>(initiator)
+    }
+
+    /// Set the owner of the transaction
+    pub fn owner(self, owner: *mut ConfObject) -> Self {
+        self.atom::<OwnerAtom>(owner)
+    }
+
+    /// Add an atom of type `A` to the transaction
+    pub fn atom<A>(mut self, value: A::Value) -> Self
+    where
+        A: AtomType,
+    {
+        match A::atom(value) {
+            Ok(atom) => self.atoms.push(atom),
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+        self
+    }
+
+    /// Set a closure to be called when the transaction completes. A transaction with a
+    /// completion closure may be deferred by its target.
+    pub fn completion<F>(mut self, completion: F) -> Self
+    where
+        F: FnMut(&mut Transaction, ExceptionType) -> ExceptionType + 'static,
+    {
+        self.completion = Some(Box::new(completion));
+        self
+    }
+
+    /// Allow the transaction to be deferred without a completion closure. The initiator must
+    /// then wait for the transaction with [`OwnedTransaction::wait`] after issuing it.
+    pub fn deferrable(mut self) -> Self {
+        self.deferrable = true;
+        self
+    }
+
+    /// Chain the transaction to a parent transaction. Atoms not present in the new
+    /// transaction are looked up in the parent.
+    pub fn prev(mut self, prev: &mut Transaction) -> Self {
+        self.prev = prev.as_mut_ptr();
+        self
+    }
+
+    /// Build the transaction
+    pub fn build(self) -> Result<OwnedTransaction> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+
+        let mut storage = Box::new(TransactionStorage {
+            transaction: transaction_t::default(),
+            atoms: Vec::with_capacity(self.atoms.len() + 5),
+            deferrable: self.completion.is_some() || self.deferrable,
+            data: self.data,
+            completion: self.completion,
+            issued: false,
+            owned_deferrals: Rc::new(Cell::new(0)),
+        });
+
+        let size = storage.data.len().try_into()?;
+        let data = storage.data.as_mut_ptr();
+        storage.atoms.push(DataAtom::atom(data)?);
+        storage.atoms.push(SizeAtom::atom(size)?);
+        storage.atoms.push(FlagsAtom::atom(self.flags)?);
+
+        if storage.completion.is_some() {
+            storage
+                .atoms
+                .push(CompletionAtom::atom(Some(transaction_completion_handler))?);
+        } else if self.deferrable {
+            storage.atoms.push(CompletionAtom::atom(None)?);
+        }
+
+        storage.atoms.extend(self.atoms);
+        storage.atoms.push(ListEndAtom::atom(0)?);
+        storage.transaction.atoms = storage.atoms.as_mut_ptr();
+        storage.transaction.prev = self.prev;
+
+        Ok(OwnedTransaction(ManuallyDrop::new(storage)))
+    }
+}
+
+/// A transaction built by a [`TransactionBuilder`] which owns its atom list, data buffer
+/// and completion closure. It dereferences to a [`Transaction`].
+///
+/// A transaction which was deferred by its target must not be dropped before it completes.
+/// Issue the transaction with [`OwnedTransaction::issue`] and use [`OwnedTransaction::wait`]
+/// or [`OwnedTransaction::poll`] to find out when it completes. If a transaction is dropped
+/// while it is still deferred, or while a transaction deferred from it with
+/// [`OwnedTransaction::defer_owned`] has not been completed, an error is logged and its
+/// storage is leaked so the deferred transaction can still be completed.
+pub struct OwnedTransaction(ManuallyDrop<Box<TransactionStorage>>);
+
+impl OwnedTransaction {
+    /// Check whether the transaction was issued and deferred and has not yet completed
+    fn is_deferred(&mut self) -> bool {
+        self.0.deferrable
+            && self.0.issued
+            && poll_transaction(self.as_mut_ptr())
+                .is_ok_and(|ex| ex == ExceptionType::Sim_PE_Deferred)
+    }
+
+    /// Check whether the transaction or a transaction deferred from it with
+    /// [`OwnedTransaction::defer_owned`] has not yet completed
+    fn is_outstanding(&mut self) -> bool {
+        self.0.owned_deferrals.get() > 0 || self.is_deferred()
+    }
+
+    /// Issue the transaction to `target` at `addr`. If the target defers the transaction,
+    /// the returned status is `Sim_PE_Deferred` and the transaction must be waited for or
+    /// monitored.
+    pub fn issue(&mut self, target: &MapTarget, addr: u64) -> Result<ExceptionType> {
+        self.0.issued = true;
+        target.issue(self, addr)
+    }
+
+    /// The data buffer of the transaction. For a read, this holds the data read once the
+    /// transaction completes.
+    pub fn data(&self) -> &[u8] {
+        &self.0.data
+    }
+
+    /// Consume the transaction and return its data buffer. Fails if the transaction is
+    /// still deferred, in which case its data may still be accessed by the target.
+    pub fn into_data(mut self) -> Result<Vec<u8>> {
+        if self.is_outstanding() {
+            return Err(Error::TransactionDeferred);
+        }
+
+        Ok(take(&mut self.0.data))
+    }
+
+    /// Set the value of the atom of type `A` in this transaction, adding the atom if the
+    /// transaction does not already contain one. Fails if the transaction is still deferred,
+    /// in which case its atoms may still be accessed by the target.
+    pub fn set_atom<A>(&mut self, value: A::Value) -> Result<()>
+    where
+        A: AtomType,
+    {
+        if self.is_outstanding() {
+            return Err(Error::TransactionDeferred);
+        }
+
+        match self.deref_mut().set_atom::<A>(value) {
+            Err(Error::AtomNotFound { .. }) => {
+                // Insert the atom before the list terminator, which is always the last atom
+                let storage = &mut self.0;
+                let end = storage.atoms.len() - 1;
+                storage.atoms.insert(end, A::atom(value)?);
+                storage.transaction.atoms = storage.atoms.as_mut_ptr();
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Wait for the transaction to complete, given the status returned when it was issued.
+    /// If the transaction was deferred, the simulation runs until it completes. This must be
+    /// called from Cell Context.
+    pub fn wait(&mut self, ex: ExceptionType) -> Result<ExceptionType> {
+        self.0.issued |= ex == ExceptionType::Sim_PE_Deferred;
+        transaction_wait(self.as_mut_ptr(), ex)
+    }
+
+    /// Check whether the transaction has completed, returning its completion status if it
+    /// has or `None` if it is still deferred
+    pub fn poll(&mut self) -> Result<Option<ExceptionType>> {
+        if !self.0.issued {
+            return Err(Error::TransactionNotIssued);
+        }
+
+        let ex = poll_transaction(self.as_mut_ptr())?;
+        Ok((ex != ExceptionType::Sim_PE_Deferred).then_some(ex))
+    }
+
+    /// Monitor the transaction for completion, given the status returned when it was
+    /// issued. The completion closure is called when the transaction completes, which may
+    /// be immediately.
+    pub fn monitor(&mut self, ex: ExceptionType) -> Result<ExceptionType> {
+        self.0.issued |= ex == ExceptionType::Sim_PE_Deferred;
+        monitor_transaction(self.as_mut_ptr(), ex)
+    }
+
+    /// Defer the transaction from the initiator side, returning a handle which completes it.
+    /// The deferred transaction is chained to this transaction, so this transaction's
+    /// storage is kept alive until the handle is completed or dropped.
+    pub fn defer_owned(&mut self) -> Result<DeferredTransaction> {
+        let t =
+            defer_owned_transaction(self.as_mut_ptr())?.ok_or(Error::TransactionNotDeferrable)?;
+        let owner = self.0.owned_deferrals.clone();
+        owner.set(owner.get() + 1);
+        Ok(DeferredTransaction {
+            t,
+            owner: Some(owner),
+        })
+    }
+
+    /// Reconnect the transaction, restored from a checkpoint, with the deferred transaction
+    /// identified by `id`
+    pub fn reconnect(&mut self, id: i64) -> Result<()> {
+        self.0.issued = true;
+        reconnect_transaction(self.as_mut_ptr(), id)
+    }
+}
+
+impl Drop for OwnedTransaction {
+    fn drop(&mut self) {
+        if self.is_outstanding() {
+            // NOTE: The target still holds a pointer to the transaction and will complete
+            // it later, so the storage must outlive this handle
+            let obj = transaction_initiator(self.as_mut_ptr())
+                .ok()
+                .flatten()
+                .or_else(|| get_object("sim").ok());
+
+            if let Some(obj) = obj {
+                log_error(
+                    obj,
+                    "Transaction dropped while it or a transaction deferred from it is \
+                     outstanding, leaking its storage",
+                )
+                .ok();
+            }
+
+            return;
+        }
+
+        unsafe { ManuallyDrop::drop(&mut self.0) };
+    }
+}
+
+impl Deref for OwnedTransaction {
+    type Target = Transaction;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { Transaction::from_raw(&self.0.transaction) }
+    }
+}
+
+impl DerefMut for OwnedTransaction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { Transaction::from_raw_mut(&mut self.0.transaction) }
+    }
+}
+
+#[must_use = "a deferred transaction must be completed"]
+/// A handle to a deferred transaction. The handle is consumed when the transaction is
+/// completed, so a deferred transaction can only be completed once. Completing the
+/// transaction calls the completion callback of its initiator. If the handle is dropped
+/// without completing the transaction, an error is logged and the transaction is completed
+/// with `Sim_PE_IO_Error` so its initiator does not wait for it forever.
+pub struct DeferredTransaction {
+    t: *mut transaction_t,
+    /// The outstanding deferral count of the [`OwnedTransaction`] this transaction was
+    /// deferred from with [`OwnedTransaction::defer_owned`], if any
+    owner: Option<Rc<Cell<usize>>>,
+}
+
+impl DeferredTransaction {
+    /// Create a handle from a raw deferred transaction pointer
+    ///
+    /// # Safety
+    ///
+    /// `t` must be a valid pointer to a deferred transaction which has not been completed,
+    /// and no other handle to the same transaction may exist
+    pub unsafe fn from_raw(t: *mut transaction_t) -> Self {
+        Self { t, owner: None }
+    }
+
+    /// Get the raw pointer to the deferred transaction
+    pub fn as_ptr(&self) -> *mut transaction_t {
+        self.t
+    }
+
+    /// Complete the deferred transaction and release its hold on the storage of the
+    /// [`OwnedTransaction`] it was deferred from
+    fn finish(&mut self, ex: ExceptionType) -> Result<()> {
+        let result = complete_transaction(self.t, ex);
+
+        if let Some(owner) = self.owner.take() {
+            owner.set(owner.get() - 1);
+        }
+
+        result
+    }
+
+    /// Access the deferred transaction, for example to set the data of a read before
+    /// completing it
+    pub fn transaction(&mut self) -> &mut Transaction {
+        unsafe { Transaction::from_raw_mut(self.t) }
+    }
+
+    /// Get the id of the deferred transaction, which identifies it in a checkpoint
+    pub fn id(&self) -> Result<i64> {
+        get_transaction_id(self.t)
+    }
+
+    /// Complete the deferred transaction with the status `ex`
+    pub fn complete(mut self, ex: ExceptionType) -> Result<()> {
+        let result = self.finish(ex);
+        forget(self);
+        result
+    }
+}
+
+impl Drop for DeferredTransaction {
+    fn drop(&mut self) {
+        let obj = transaction_initiator(self.t)
+            .ok()
+            .flatten()
+            .or_else(|| get_object("sim").ok());
+
+        if let Some(obj) = obj {
+            log_error(obj, "Deferred transaction dropped without being completed").ok();
+        }
+
+        self.finish(ExceptionType::Sim_PE_IO_Error).ok();
+    }
+}
+
+impl Deref for DeferredTransaction {
+    type Target = Transaction;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { Transaction::from_raw(self.t) }
+    }
+}
+
+impl DerefMut for DeferredTransaction {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.transaction()
+    }
+}
@@ -0,0 +1,660 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! CPU instrumentation
+//!
+//! Processors let instrumentation tools subscribe to the instructions they execute and the
+//! memory accesses they perform through the `cpu_instrumentation_subscribe` interface. The
+//! subscriptions in this module call Rust closures with views of the instruction or memory
+//! access, which are backed by the `cpu_instruction_query` and `cpu_memory_query`
+//! interfaces, and are removed when their handles are dropped.
+//!
+//! ```rust,ignore
+//! let mut cpu = Cpu::new(cpu_obj)?;
+//! let handle = cpu.on_instruction_before(|cpu, instruction| {
+//!     if let (Ok(pc), Ok(bytes)) = (cpu.read_register("pc"), instruction.bytes()) {
+//!         println!("{pc:#x}: {bytes:02x?}");
+//!     }
+//! })?;
+//! ```
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    get_interface,
+    sys::{
+        bytes_t, cached_instruction_handle_t, cpu_access_scope_t, cpu_bytes_t, cpu_cb_handle_t,
+        instruction_handle_t, memory_handle_t,
+    },
+    ConfObject, CpuCachedInstructionInterface, CpuInstructionQueryInterface,
+    CpuInstrumentationSubscribeInterface, CpuMemoryQueryInterface, Error, IntRegisterInterface,
+    LogicalAddress, PhysicalAddress, Result,
+};
+use std::{
+    collections::HashMap,
+    ffi::{c_void, CString},
+    ptr::null_mut,
+    slice::from_raw_parts,
+};
+
+/// Alias for `cpu_cb_handle_t`
+pub type CpuCbHandle = cpu_cb_handle_t;
+/// Alias for `cpu_access_scope_t`
+pub type CpuAccessScope = cpu_access_scope_t;
+/// Alias for `cpu_bytes_t`
+pub type CpuBytes = cpu_bytes_t;
+/// Alias for `instruction_handle_t`
+pub type InstructionHandle = instruction_handle_t;
+/// Alias for `memory_handle_t`
+pub type MemoryHandle = memory_handle_t;
+/// Alias for `cached_instruction_handle_t`
+pub type CachedInstructionHandle = cached_instruction_handle_t;
+
+type InstructionCallback = Box<dyn FnMut(&mut Cpu, &mut Instruction)>;
+type MemoryCallback = Box<dyn FnMut(&mut Cpu, &mut MemoryAccess)>;
+type CachedInstructionCallback = Box<dyn FnMut(&mut Cpu, &mut CachedInstruction)>;
+
+/// Get the bytes of a `cpu_bytes_t`, which remain valid for the duration of the
+/// instrumentation callback they were obtained in
+fn cpu_bytes<'a>(bytes: CpuBytes) -> &'a [u8] {
+    if bytes.data.is_null() || bytes.size == 0 {
+        &[]
+    } else {
+        unsafe { from_raw_parts(bytes.data, bytes.size) }
+    }
+}
+
+/// A processor which can be instrumented, and whose registers can be accessed from
+/// instrumentation callbacks
+pub struct Cpu {
+    obj: *mut ConfObject,
+    connection: *mut ConfObject,
+    int_register: IntRegisterInterface,
+    register_numbers: HashMap<String, i32>,
+}
+
+impl Cpu {
+    /// Create a new instrumented processor from a processor object, which must implement the
+    /// `int_register` interface
+    pub fn new(obj: *mut ConfObject) -> Result<Self> {
+        Ok(Self {
+            obj,
+            connection: null_mut(),
+            int_register: get_interface(obj)?,
+            register_numbers: HashMap::new(),
+        })
+    }
+
+    /// Create a new instrumented processor whose callbacks are registered for the
+    /// instrumentation tool connection `connection`, so they can be enabled and disabled
+    /// together with the other callbacks of the connection. The callbacks of the connection
+    /// must not be removed through the `cpu_instrumentation_subscribe` interface while their
+    /// handles are alive, because each handle removes its own callback when it is dropped.
+    pub fn with_connection(obj: *mut ConfObject, connection: *mut ConfObject) -> Result<Self> {
+        Ok(Self {
+            connection,
+            ..Self::new(obj)?
+        })
+    }
+
+    /// The processor object
+    pub fn obj(&self) -> *mut ConfObject {
+        self.obj
+    }
+
+    /// The instrumentation tool connection callbacks are registered for, if any
+    pub fn connection(&self) -> Option<*mut ConfObject> {
+        (!self.connection.is_null()).then_some(self.connection)
+    }
+
+    /// Get the number of the register named `name`. Register numbers are looked up once and
+    /// cached, so registers can be accessed by name from instrumentation callbacks.
+    pub fn register_number<S>(&mut self, name: S) -> Result<i32>
+    where
+        S: AsRef<str>,
+    {
+        let name = name.as_ref();
+
+        if let Some(number) = self.register_numbers.get(name) {
+            return Ok(*number);
+        }
+
+        let number = self.int_register.get_number(CString::new(name)?.as_ptr())?;
+
+        if number < 0 {
+            return Err(Error::NoRegister {
+                name: name.to_string(),
+            });
+        }
+
+        self.register_numbers.insert(name.to_string(), number);
+
+        Ok(number)
+    }
+
+    /// Read the value of the register numbered `number`
+    pub fn read_register_number(&mut self, number: i32) -> Result<u64> {
+        self.int_register.read(number)
+    }
+
+    /// Write a value to the register numbered `number`
+    pub fn write_register_number(&mut self, number: i32, value: u64) -> Result<()> {
+        self.int_register.write(number, value)
+    }
+
+    /// Read the value of the register named `name`
+    pub fn read_register<S>(&mut self, name: S) -> Result<u64>
+    where
+        S: AsRef<str>,
+    {
+        let number = self.register_number(name)?;
+        self.read_register_number(number)
+    }
+
+    /// Write a value to the register named `name`
+    pub fn write_register<S>(&mut self, name: S, value: u64) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let number = self.register_number(name)?;
+        self.write_register_number(number, value)
+    }
+
+    /// Create another view of this processor, for use by a new callback
+    fn duplicate(&self) -> Result<Self> {
+        Self::with_connection(self.obj, self.connection)
+    }
+
+    fn subscribe<S, R>(&self, state: S, register: R) -> Result<CpuCallbackHandle>
+    where
+        R: FnOnce(
+            &mut CpuInstrumentationSubscribeInterface,
+            *mut ConfObject,
+            *mut c_void,
+        ) -> Result<*mut CpuCbHandle>,
+    {
+        let mut subscribe = get_interface::<CpuInstrumentationSubscribeInterface>(self.obj)?;
+        let state = Box::into_raw(Box::new(state)) as *mut c_void;
+
+        match register(&mut subscribe, self.connection, state) {
+            Ok(handle) => Ok(CpuCallbackHandle {
+                cpu: self.obj,
+                handle,
+                state,
+                free_state: free_state::<S>,
+            }),
+            Err(e) => {
+                unsafe { free_state::<S>(state) };
+                Err(e)
+            }
+        }
+    }
+
+    fn subscribe_instruction<F, R>(&self, cb: F, register: R) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut Instruction) + 'static,
+        R: FnOnce(
+            &mut CpuInstrumentationSubscribeInterface,
+            *mut ConfObject,
+            *mut c_void,
+        ) -> Result<*mut CpuCbHandle>,
+    {
+        let state = InstructionCallbackState {
+            cpu: self.duplicate()?,
+            query: get_interface(self.obj)?,
+            callback: Box::new(cb),
+        };
+
+        self.subscribe(state, register)
+    }
+
+    fn subscribe_memory<F, R>(&self, cb: F, register: R) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+        R: FnOnce(
+            &mut CpuInstrumentationSubscribeInterface,
+            *mut ConfObject,
+            *mut c_void,
+        ) -> Result<*mut CpuCbHandle>,
+    {
+        let state = MemoryCallbackState {
+            cpu: self.duplicate()?,
+            query: get_interface(self.obj)?,
+            callback: Box::new(cb),
+        };
+
+        self.subscribe(state, register)
+    }
+
+    /// Call a closure before each instruction the processor executes
+    pub fn on_instruction_before<F>(&self, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut Instruction) + 'static,
+    {
+        self.subscribe_instruction(cb, |subscribe, connection, state| {
+            subscribe.register_instruction_before_cb(connection, Some(instruction_callback), state)
+        })
+    }
+
+    /// Call a closure after each instruction the processor executes
+    pub fn on_instruction_after<F>(&self, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut Instruction) + 'static,
+    {
+        self.subscribe_instruction(cb, |subscribe, connection, state| {
+            subscribe.register_instruction_after_cb(connection, Some(instruction_callback), state)
+        })
+    }
+
+    /// Call a closure before each read the processor performs in `scope`
+    pub fn on_read_before<F>(&self, scope: CpuAccessScope, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        self.subscribe_memory(cb, |subscribe, connection, state| {
+            subscribe.register_read_before_cb(connection, scope, Some(memory_callback), state)
+        })
+    }
+
+    /// Call a closure after each read the processor performs in `scope`
+    pub fn on_read_after<F>(&self, scope: CpuAccessScope, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        self.subscribe_memory(cb, |subscribe, connection, state| {
+            subscribe.register_read_after_cb(connection, scope, Some(memory_callback), state)
+        })
+    }
+
+    /// Call a closure before each write the processor performs in `scope`
+    pub fn on_write_before<F>(&self, scope: CpuAccessScope, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        self.subscribe_memory(cb, |subscribe, connection, state| {
+            subscribe.register_write_before_cb(connection, scope, Some(memory_callback), state)
+        })
+    }
+
+    /// Call a closure after each write the processor performs in `scope`
+    pub fn on_write_after<F>(&self, scope: CpuAccessScope, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        self.subscribe_memory(cb, |subscribe, connection, state| {
+            subscribe.register_write_after_cb(connection, scope, Some(memory_callback), state)
+        })
+    }
+
+    /// Call a closure when the processor decodes an instruction and caches it. The closure
+    /// can inspect the instruction and register callbacks for this instruction only through
+    /// the [`CachedInstruction`], which avoids the cost of calling a callback for every
+    /// instruction the processor executes.
+    pub fn on_cached_instruction<F>(&self, cb: F) -> Result<CpuCallbackHandle>
+    where
+        F: FnMut(&mut Cpu, &mut CachedInstruction) + 'static,
+    {
+        let state = CachedInstructionCallbackState {
+            cpu: self.duplicate()?,
+            query: get_interface(self.obj)?,
+            cached: get_interface(self.obj)?,
+            callback: Box::new(cb),
+        };
+
+        self.subscribe(state, |subscribe, connection, state| {
+            subscribe.register_cached_instruction_cb(
+                connection,
+                Some(cached_instruction_callback),
+                state,
+            )
+        })
+    }
+}
+
+struct InstructionCallbackState {
+    cpu: Cpu,
+    query: CpuInstructionQueryInterface,
+    callback: InstructionCallback,
+}
+
+struct MemoryCallbackState {
+    cpu: Cpu,
+    query: CpuMemoryQueryInterface,
+    callback: MemoryCallback,
+}
+
+struct CachedInstructionCallbackState {
+    cpu: Cpu,
+    query: CpuInstructionQueryInterface,
+    cached: CpuCachedInstructionInterface,
+    callback: CachedInstructionCallback,
+}
+
+unsafe fn free_state<S>(state: *mut c_void) {
+    drop(Box::from_raw(state as *mut S));
+}
+
+extern "C" fn instruction_callback(
+    _connection: *mut ConfObject,
+    _cpu: *mut ConfObject,
+    handle: *mut InstructionHandle,
+    user_data: *mut c_void,
+) {
+    // NOTE: The state is owned by the callback handle or, for callbacks on cached
+    // instructions, freed by the processor when it is no longer needed
+    let state = unsafe { &mut *(user_data as *mut InstructionCallbackState) };
+    let mut instruction = Instruction {
+        handle,
+        query: &mut state.query,
+    };
+    (state.callback)(&mut state.cpu, &mut instruction)
+}
+
+extern "C" fn memory_callback(
+    _connection: *mut ConfObject,
+    _cpu: *mut ConfObject,
+    handle: *mut MemoryHandle,
+    user_data: *mut c_void,
+) {
+    let state = unsafe { &mut *(user_data as *mut MemoryCallbackState) };
+    let mut access = MemoryAccess {
+        handle,
+        query: &mut state.query,
+    };
+    (state.callback)(&mut state.cpu, &mut access)
+}
+
+extern "C" fn cached_instruction_callback(
+    _connection: *mut ConfObject,
+    _cpu: *mut ConfObject,
+    ci_handle: *mut CachedInstructionHandle,
+    iq_handle: *mut InstructionHandle,
+    user_data: *mut c_void,
+) {
+    let state = unsafe { &mut *(user_data as *mut CachedInstructionCallbackState) };
+    let mut cached = CachedInstruction {
+        obj: state.cpu.obj,
+        connection: state.cpu.connection,
+        ci_handle,
+        instruction: Instruction {
+            handle: iq_handle,
+            query: &mut state.query,
+        },
+        cached: &mut state.cached,
+    };
+    (state.callback)(&mut state.cpu, &mut cached)
+}
+
+extern "C" fn free_instruction_callback(
+    _connection: *mut ConfObject,
+    _cpu: *mut ConfObject,
+    user_data: *mut c_void,
+) {
+    unsafe { free_state::<InstructionCallbackState>(user_data) };
+}
+
+extern "C" fn free_memory_callback(
+    _connection: *mut ConfObject,
+    _cpu: *mut ConfObject,
+    user_data: *mut c_void,
+) {
+    unsafe { free_state::<MemoryCallbackState>(user_data) };
+}
+
+#[derive(Debug)]
+/// A callback registered on a processor, which is removed and freed when dropped. The
+/// callback must not be removed by other means, such as removing all callbacks of its
+/// connection, while the handle is alive.
+pub struct CpuCallbackHandle {
+    cpu: *mut ConfObject,
+    handle: *mut CpuCbHandle,
+    state: *mut c_void,
+    free_state: unsafe fn(*mut c_void),
+}
+
+impl CpuCallbackHandle {
+    /// Enable the callback if it has been disabled
+    pub fn enable(&self) -> Result<()> {
+        get_interface::<CpuInstrumentationSubscribeInterface>(self.cpu)?
+            .enable_callback(self.handle)
+    }
+
+    /// Disable the callback, without removing it
+    pub fn disable(&self) -> Result<()> {
+        get_interface::<CpuInstrumentationSubscribeInterface>(self.cpu)?
+            .disable_callback(self.handle)
+    }
+}
+
+impl Drop for CpuCallbackHandle {
+    fn drop(&mut self) {
+        if let Ok(mut subscribe) = get_interface::<CpuInstrumentationSubscribeInterface>(self.cpu) {
+            subscribe.remove_callback(self.handle).ok();
+        }
+
+        unsafe { (self.free_state)(self.state) };
+    }
+}
+
+/// An instruction executed by a processor, valid for the duration of the instrumentation
+/// callback it is passed to
+pub struct Instruction<'a> {
+    handle: *mut InstructionHandle,
+    query: &'a mut CpuInstructionQueryInterface,
+}
+
+impl Instruction<'_> {
+    /// The raw handle of the instruction
+    pub fn as_ptr(&self) -> *mut InstructionHandle {
+        self.handle
+    }
+
+    /// The logical address of the instruction
+    pub fn logical_address(&mut self) -> Result<LogicalAddress> {
+        self.query.logical_address(self.handle)
+    }
+
+    /// The physical address of the instruction
+    pub fn physical_address(&mut self) -> Result<PhysicalAddress> {
+        self.query.physical_address(self.handle)
+    }
+
+    /// The bytes of the instruction
+    pub fn bytes(&mut self) -> Result<&[u8]> {
+        Ok(cpu_bytes(self.query.get_instruction_bytes(self.handle)?))
+    }
+}
+
+/// A memory access performed by a processor, valid for the duration of the instrumentation
+/// callback it is passed to
+pub struct MemoryAccess<'a> {
+    handle: *mut MemoryHandle,
+    query: &'a mut CpuMemoryQueryInterface,
+}
+
+impl MemoryAccess<'_> {
+    /// The raw handle of the memory access
+    pub fn as_ptr(&self) -> *mut MemoryHandle {
+        self.handle
+    }
+
+    /// The logical address of the access
+    pub fn logical_address(&mut self) -> Result<LogicalAddress> {
+        self.query.logical_address(self.handle)
+    }
+
+    /// The physical address of the access
+    pub fn physical_address(&mut self) -> Result<PhysicalAddress> {
+        self.query.physical_address(self.handle)
+    }
+
+    /// The size of the access in bytes
+    pub fn size(&mut self) -> Result<usize> {
+        Ok(self.query.get_bytes(self.handle)?.size)
+    }
+
+    /// The bytes read or written by the access. Before a read, the bytes have not been read
+    /// yet and their contents are undefined.
+    pub fn bytes(&mut self) -> Result<&[u8]> {
+        Ok(cpu_bytes(self.query.get_bytes(self.handle)?))
+    }
+
+    /// Change the bytes read or written by the access. This is only possible after a read
+    /// or before a write, and `bytes` must have the size of the access.
+    pub fn set_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.query.set_bytes(
+            self.handle,
+            bytes_t {
+                data: bytes.as_ptr(),
+                len: bytes.len(),
+            },
+        )
+    }
+
+    /// Whether the access is atomic
+    pub fn is_atomic(&mut self) -> Result<bool> {
+        self.query.atomic(self.handle)
+    }
+}
+
+/// An instruction decoded and cached by a processor, valid for the duration of the
+/// instrumentation callback it is passed to. Callbacks registered on a cached instruction
+/// are called each time the instruction is executed, until the processor flushes it from
+/// its cache.
+pub struct CachedInstruction<'a> {
+    obj: *mut ConfObject,
+    connection: *mut ConfObject,
+    ci_handle: *mut CachedInstructionHandle,
+    instruction: Instruction<'a>,
+    cached: &'a mut CpuCachedInstructionInterface,
+}
+
+impl<'a> CachedInstruction<'a> {
+    /// The raw handle of the cached instruction
+    pub fn as_ptr(&self) -> *mut CachedInstructionHandle {
+        self.ci_handle
+    }
+
+    /// The decoded instruction
+    pub fn instruction(&mut self) -> &mut Instruction<'a> {
+        &mut self.instruction
+    }
+
+    fn instruction_state<F>(&self, cb: F) -> Result<*mut c_void>
+    where
+        F: FnMut(&mut Cpu, &mut Instruction) + 'static,
+    {
+        Ok(Box::into_raw(Box::new(InstructionCallbackState {
+            cpu: Cpu::with_connection(self.obj, self.connection)?,
+            query: get_interface(self.obj)?,
+            callback: Box::new(cb),
+        })) as *mut c_void)
+    }
+
+    fn memory_state<F>(&self, cb: F) -> Result<*mut c_void>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        Ok(Box::into_raw(Box::new(MemoryCallbackState {
+            cpu: Cpu::with_connection(self.obj, self.connection)?,
+            query: get_interface(self.obj)?,
+            callback: Box::new(cb),
+        })) as *mut c_void)
+    }
+
+    /// Call a closure each time before this instruction is executed
+    pub fn on_instruction_before<F>(&mut self, cb: F) -> Result<()>
+    where
+        F: FnMut(&mut Cpu, &mut Instruction) + 'static,
+    {
+        let state = self.instruction_state(cb)?;
+        self.cached
+            .register_instruction_before_cb(
+                self.ci_handle,
+                Some(instruction_callback),
+                state,
+                Some(free_instruction_callback),
+            )
+            .inspect_err(|_| unsafe { free_state::<InstructionCallbackState>(state) })
+    }
+
+    /// Call a closure each time after this instruction is executed
+    pub fn on_instruction_after<F>(&mut self, cb: F) -> Result<()>
+    where
+        F: FnMut(&mut Cpu, &mut Instruction) + 'static,
+    {
+        let state = self.instruction_state(cb)?;
+        self.cached
+            .register_instruction_after_cb(
+                self.ci_handle,
+                Some(instruction_callback),
+                state,
+                Some(free_instruction_callback),
+            )
+            .inspect_err(|_| unsafe { free_state::<InstructionCallbackState>(state) })
+    }
+
+    /// Call a closure before each read performed by this instruction
+    pub fn on_read_before<F>(&mut self, cb: F) -> Result<()>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        let state = self.memory_state(cb)?;
+        self.cached
+            .register_read_before_cb(
+                self.ci_handle,
+                Some(memory_callback),
+                state,
+                Some(free_memory_callback),
+            )
+            .inspect_err(|_| unsafe { free_state::<MemoryCallbackState>(state) })
+    }
+
+    /// Call a closure after each read performed by this instruction
+    pub fn on_read_after<F>(&mut self, cb: F) -> Result<()>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        let state = self.memory_state(cb)?;
+        self.cached
+            .register_read_after_cb(
+                self.ci_handle,
+                Some(memory_callback),
+                state,
+                Some(free_memory_callback),
+            )
+            .inspect_err(|_| unsafe { free_state::<MemoryCallbackState>(state) })
+    }
+
+    /// Call a closure before each write performed by this instruction
+    pub fn on_write_before<F>(&mut self, cb: F) -> Result<()>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        let state = self.memory_state(cb)?;
+        self.cached
+            .register_write_before_cb(
+                self.ci_handle,
+                Some(memory_callback),
+                state,
+                Some(free_memory_callback),
+            )
+            .inspect_err(|_| unsafe { free_state::<MemoryCallbackState>(state) })
+    }
+
+    /// Call a closure after each write performed by this instruction
+    pub fn on_write_after<F>(&mut self, cb: F) -> Result<()>
+    where
+        F: FnMut(&mut Cpu, &mut MemoryAccess) + 'static,
+    {
+        let state = self.memory_state(cb)?;
+        self.cached
+            .register_write_after_cb(
+                self.ci_handle,
+                Some(memory_callback),
+                state,
+                Some(free_memory_callback),
+            )
+            .inspect_err(|_| unsafe { free_state::<MemoryCallbackState>(state) })
+    }
+}
@@ -4,9 +4,11 @@
 //! Processor APIs
 
 pub mod context;
+pub mod instrumentation;
 pub mod stc;
 pub mod types;
 
 pub use context::*;
+pub use instrumentation::*;
 pub use stc::*;
 pub use types::*;
@@ -0,0 +1,852 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Breakpoint manager APIs
+//!
+//! The breakpoint manager keeps track of all breakpoints in the simulation, regardless of
+//! which object provides them, and implements the `bp` commands. Objects can register their
+//! own breakpoints with the manager, or register a new type of breakpoint which the manager
+//! creates on request through the `breakpoint_type_provider` interface of the provider.
+//!
+//! ```rust,ignore
+//! let manager = BreakpointManager::new()?;
+//! let mut breakpoint = manager.add_breakpoint("Break on magic value", |trigger| {
+//!     println!("Magic value written by {trigger:?}");
+//! })?;
+//!
+//! // When the condition of the breakpoint is detected
+//! breakpoint.hit(Some(initiator))?;
+//! ```
+//!
+//! A Rust class provides a new type of breakpoint by implementing
+//! [`BreakpointTypeProvider`], registering the `breakpoint_type_provider` interface with
+//! [`BreakpointTypeProvider::register_breakpoint_type_provider`] and registering the type
+//! with [`BreakpointManager::register_type`] once its provider object exists.
+
+use crate::{
+    alloc, get_interface, get_object, simics_exception,
+    sys::{
+        attr_value_t, breakpoint_type_provider_interface_t, SIM_register_interface,
+        BREAKPOINT_PROP_DESCRIPTION, BREAKPOINT_PROP_ENABLED, BREAKPOINT_PROP_HIT_COUNT,
+        BREAKPOINT_PROP_IGNORE_COUNT, BREAKPOINT_PROP_TEMPORARY,
+    },
+    AttrValue, AttrValueType, BreakpointManagerInterface, BreakpointRegistrationInterface,
+    BreakpointTypeInterface, BreakpointTypeProviderInterface, ConfClass, ConfObject, Error,
+    FromConfObject, Interface, Result,
+};
+use raw_cstr::{raw_cstr, AsRawCstr};
+use std::{
+    collections::BTreeMap,
+    ffi::{c_char, CStr, CString},
+    mem::replace,
+    ptr::{copy_nonoverlapping, null_mut},
+};
+
+/// The name of the breakpoint manager object
+pub const BREAKPOINT_MANAGER: &str = "bp";
+
+/// The id of a breakpoint in the breakpoint manager
+pub type BreakpointManagerId = i32;
+
+/// Get the name of a `BREAKPOINT_PROP_*` property
+fn property_name(property: &[u8]) -> String {
+    String::from_utf8_lossy(property.strip_suffix(&[0]).unwrap_or(property)).to_string()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// The properties of a breakpoint, as reported by the breakpoint manager
+pub struct BreakpointProperties {
+    /// Whether the breakpoint is enabled
+    pub enabled: bool,
+    /// Whether the breakpoint is deleted when it is hit
+    pub temporary: bool,
+    /// The number of hits which are ignored before the breakpoint triggers
+    pub ignore_count: u64,
+    /// The number of times the breakpoint has been hit
+    pub hit_count: u64,
+    /// The description of the breakpoint
+    pub description: String,
+}
+
+impl TryFrom<AttrValue> for BreakpointProperties {
+    type Error = crate::Error;
+
+    fn try_from(value: AttrValue) -> Result<Self> {
+        let properties = value.as_heterogeneous_dict()?.unwrap_or_default();
+        let property = |name: &[u8]| {
+            properties
+                .get(&AttrValueType::String(property_name(name)))
+                .cloned()
+        };
+
+        Ok(Self {
+            enabled: property(BREAKPOINT_PROP_ENABLED)
+                .map(bool::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            temporary: property(BREAKPOINT_PROP_TEMPORARY)
+                .map(bool::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            ignore_count: property(BREAKPOINT_PROP_IGNORE_COUNT)
+                .map(u64::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            hit_count: property(BREAKPOINT_PROP_HIT_COUNT)
+                .map(u64::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            description: property(BREAKPOINT_PROP_DESCRIPTION)
+                .map(String::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl TryFrom<&BreakpointProperties> for AttrValue {
+    type Error = crate::Error;
+
+    fn try_from(value: &BreakpointProperties) -> Result<Self> {
+        AttrValue::try_from(BTreeMap::from([
+            (
+                property_name(BREAKPOINT_PROP_ENABLED),
+                AttrValueType::Bool(value.enabled),
+            ),
+            (
+                property_name(BREAKPOINT_PROP_TEMPORARY),
+                AttrValueType::Bool(value.temporary),
+            ),
+            (
+                property_name(BREAKPOINT_PROP_IGNORE_COUNT),
+                AttrValueType::Unsigned(value.ignore_count),
+            ),
+            (
+                property_name(BREAKPOINT_PROP_HIT_COUNT),
+                AttrValueType::Unsigned(value.hit_count),
+            ),
+            (
+                property_name(BREAKPOINT_PROP_DESCRIPTION),
+                AttrValueType::String(value.description.clone()),
+            ),
+        ]))
+    }
+}
+
+/// The breakpoint manager, through which breakpoints of every type are listed, queried and
+/// registered
+pub struct BreakpointManager {
+    obj: *mut ConfObject,
+}
+
+impl BreakpointManager {
+    /// Get the breakpoint manager of the simulation
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_obj(get_object(BREAKPOINT_MANAGER)?))
+    }
+
+    /// Create a breakpoint manager from a breakpoint manager object
+    pub fn from_obj(obj: *mut ConfObject) -> Self {
+        Self { obj }
+    }
+
+    /// The breakpoint manager object
+    pub fn obj(&self) -> *mut ConfObject {
+        self.obj
+    }
+
+    fn manager(&self) -> Result<BreakpointManagerInterface> {
+        get_interface(self.obj)
+    }
+
+    /// The ids of all breakpoints in the simulation
+    pub fn breakpoints(&self) -> Result<Vec<BreakpointManagerId>> {
+        self.manager()?.list_breakpoints()?.try_into()
+    }
+
+    /// The properties of the breakpoint with id `id`
+    pub fn properties(&self, id: BreakpointManagerId) -> Result<BreakpointProperties> {
+        self.manager()?.get_properties(id)?.try_into()
+    }
+
+    /// The number of times the breakpoint with id `id` has been hit
+    pub fn hit_count(&self, id: BreakpointManagerId) -> Result<u64> {
+        Ok(self.properties(id)?.hit_count)
+    }
+
+    /// Whether the breakpoint with id `id` is enabled
+    pub fn is_enabled(&self, id: BreakpointManagerId) -> Result<bool> {
+        Ok(self.properties(id)?.enabled)
+    }
+
+    /// The description of the breakpoint with id `id`
+    pub fn description(&self, id: BreakpointManagerId) -> Result<String> {
+        Ok(self.properties(id)?.description)
+    }
+
+    /// Enable or disable the breakpoint with id `id`. Returns whether the breakpoint exists.
+    pub fn set_enabled(&self, id: BreakpointManagerId, enabled: bool) -> Result<bool> {
+        self.manager()?.set_enabled(id, enabled)
+    }
+
+    /// Set whether the breakpoint with id `id` is deleted when it is hit. Returns whether
+    /// the breakpoint exists.
+    pub fn set_temporary(&self, id: BreakpointManagerId, temporary: bool) -> Result<bool> {
+        self.manager()?.set_temporary(id, temporary)
+    }
+
+    /// Set the number of hits of the breakpoint with id `id` to ignore before it triggers.
+    /// Returns whether the breakpoint exists.
+    pub fn set_ignore_count(&self, id: BreakpointManagerId, ignore_count: u64) -> Result<bool> {
+        self.manager()?.set_ignore_count(id, ignore_count)
+    }
+
+    /// Delete the breakpoint with id `id`. Returns whether the breakpoint existed.
+    pub fn delete(&self, id: BreakpointManagerId) -> Result<bool> {
+        self.manager()?.delete_breakpoint(id)
+    }
+
+    /// Register a breakpoint with the manager, calling `on_hit` with the object which
+    /// triggered it each time the breakpoint is hit through [`Breakpoint::hit`]. The
+    /// breakpoint is listed and can be controlled with the `bp` commands until the returned
+    /// handle is dropped.
+    pub fn add_breakpoint<S, F>(&self, description: S, on_hit: F) -> Result<Breakpoint>
+    where
+        S: AsRef<str>,
+        F: FnMut(Option<*mut ConfObject>) + 'static,
+    {
+        let state = Box::into_raw(Box::new(BreakpointState {
+            properties: BreakpointProperties {
+                enabled: true,
+                description: description.as_ref().to_string(),
+                ..Default::default()
+            },
+            deleted: false,
+            on_hit: Box::new(on_hit),
+        }));
+        let data = state as u64;
+
+        let id = get_interface::<BreakpointRegistrationInterface>(self.obj).and_then(
+            |mut registration| {
+                registration.register_breakpoint(
+                    Some(breakpoint_delete),
+                    data,
+                    Some(breakpoint_get_properties),
+                    data,
+                    Some(breakpoint_set_enabled),
+                    data,
+                    Some(breakpoint_set_temporary),
+                    data,
+                    Some(breakpoint_set_ignore_count),
+                    data,
+                )
+            },
+        );
+
+        match id {
+            Ok(id) => Ok(Breakpoint {
+                manager: self.obj,
+                id,
+                state,
+            }),
+            Err(e) => {
+                drop(unsafe { Box::from_raw(state) });
+                Err(e)
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    /// Register a new type of breakpoint named `name`, whose breakpoints are created and
+    /// removed by the object `provider` through its `breakpoint_type_provider` interface,
+    /// which Rust classes implement with [`BreakpointTypeProvider`]. The manager adds
+    /// commands to create breakpoints of the type, which take the arguments `args` and are
+    /// documented by `docs`. If `object_required` is set, the breakpoints are set on objects
+    /// of the class `cls` implementing the interface `iface`, and if `recursive` is set, also
+    /// on the objects below them in the object hierarchy. `cls` and `iface` are only used
+    /// when `object_required` is set.
+    pub fn register_type<S>(
+        &self,
+        name: S,
+        provider: *mut ConfObject,
+        args: AttrValue,
+        cls: Option<&str>,
+        iface: Option<&str>,
+        docs: AttrValue,
+        object_required: bool,
+        temporary_default: bool,
+        recursive: bool,
+    ) -> Result<bool>
+    where
+        S: AsRef<str>,
+    {
+        get_interface::<BreakpointTypeInterface>(self.obj)?.register_type(
+            raw_cstr(name)?,
+            provider,
+            args.into_raw(),
+            cls.map(raw_cstr).transpose()?.unwrap_or(null_mut()),
+            iface.map(raw_cstr).transpose()?.unwrap_or(null_mut()),
+            docs.into_raw(),
+            object_required,
+            temporary_default,
+            recursive,
+        )
+    }
+
+    /// Report that the breakpoint with the provider id `bp_id` of a breakpoint type
+    /// provided by `provider` was hit, with a message describing the hit. Returns whether
+    /// the simulation should stop.
+    pub fn trigger<S>(
+        &self,
+        provider: *mut ConfObject,
+        bp_id: u64,
+        trigger: Option<*mut ConfObject>,
+        msg: S,
+    ) -> Result<bool>
+    where
+        S: AsRef<str>,
+    {
+        get_interface::<BreakpointTypeInterface>(self.obj)?.trigger(
+            provider,
+            bp_id,
+            trigger.unwrap_or(null_mut()),
+            CString::new(msg.as_ref())?.as_ptr(),
+        )
+    }
+
+    /// Register the breakpoint with the provider id `bp_id` of a breakpoint type provided by
+    /// `provider` with the manager, typically from
+    /// [`BreakpointTypeProvider::register_bp`]. The breakpoint is listed and can be
+    /// controlled with the `bp` commands until the returned handle is dropped.
+    pub fn register_provider_breakpoint<S>(
+        &self,
+        provider: *mut ConfObject,
+        bp_id: u64,
+        description: S,
+    ) -> Result<ProviderBreakpoint>
+    where
+        S: AsRef<str>,
+    {
+        Ok(ProviderBreakpoint {
+            breakpoint: self.add_breakpoint(description, |_| {})?,
+            provider,
+            bp_id,
+        })
+    }
+
+    /// Get the provider id of the breakpoint with the manager id `id` of a breakpoint type
+    pub fn break_id(&self, id: BreakpointManagerId) -> Result<u64> {
+        get_interface::<BreakpointTypeInterface>(self.obj)?.get_break_id(id)
+    }
+
+    /// Get the manager id of the breakpoint with the provider id `bp_id` of a breakpoint
+    /// type provided by `provider`
+    pub fn manager_id(&self, provider: *mut ConfObject, bp_id: u64) -> Result<BreakpointManagerId> {
+        get_interface::<BreakpointTypeInterface>(self.obj)?.get_manager_id(provider, bp_id)
+    }
+}
+
+struct BreakpointState {
+    properties: BreakpointProperties,
+    deleted: bool,
+    on_hit: Box<dyn FnMut(Option<*mut ConfObject>)>,
+}
+
+fn breakpoint_state<'a>(data: u64) -> &'a mut BreakpointState {
+    // NOTE: The state is owned by the breakpoint handle, which unregisters the breakpoint
+    // before freeing it
+    unsafe { &mut *(data as *mut BreakpointState) }
+}
+
+extern "C" fn breakpoint_delete(data: u64) {
+    breakpoint_state(data).deleted = true;
+}
+
+extern "C" fn breakpoint_get_properties(data: u64) -> attr_value_t {
+    // NOTE: The breakpoint manager expects a dict, so an empty dict is reported if the
+    // properties cannot be converted
+    AttrValue::try_from(&breakpoint_state(data).properties)
+        .or_else(|_| AttrValue::dict(0))
+        .unwrap_or_else(|_| AttrValue::nil())
+        .into_raw()
+}
+
+extern "C" fn breakpoint_set_enabled(data: u64, enabled: bool) {
+    breakpoint_state(data).properties.enabled = enabled;
+}
+
+extern "C" fn breakpoint_set_temporary(data: u64, temporary: bool) {
+    breakpoint_state(data).properties.temporary = temporary;
+}
+
+extern "C" fn breakpoint_set_ignore_count(data: u64, ignore_count: u64) {
+    breakpoint_state(data).properties.ignore_count = ignore_count;
+}
+
+/// A breakpoint registered with the breakpoint manager, which is removed from the manager
+/// and freed when dropped
+pub struct Breakpoint {
+    manager: *mut ConfObject,
+    id: BreakpointManagerId,
+    state: *mut BreakpointState,
+}
+
+impl Breakpoint {
+    fn state(&self) -> &BreakpointState {
+        unsafe { &*self.state }
+    }
+
+    fn state_mut(&mut self) -> &mut BreakpointState {
+        unsafe { &mut *self.state }
+    }
+
+    /// The id of the breakpoint in the breakpoint manager
+    pub fn id(&self) -> BreakpointManagerId {
+        self.id
+    }
+
+    /// The properties of the breakpoint
+    pub fn properties(&self) -> BreakpointProperties {
+        self.state().properties.clone()
+    }
+
+    /// The number of times the breakpoint has been hit
+    pub fn hit_count(&self) -> u64 {
+        self.state().properties.hit_count
+    }
+
+    /// Whether the breakpoint is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.state().properties.enabled
+    }
+
+    /// Whether the breakpoint has been deleted through the breakpoint manager, after which
+    /// it is never hit
+    pub fn is_deleted(&self) -> bool {
+        self.state().deleted
+    }
+
+    /// Count a hit of the breakpoint. Returns whether the breakpoint triggers, which it does
+    /// unless it is deleted, disabled or ignores the hit.
+    fn count_hit(&mut self) -> bool {
+        let state = self.state_mut();
+
+        if state.deleted || !state.properties.enabled {
+            return false;
+        }
+
+        if state.properties.ignore_count > 0 {
+            state.properties.ignore_count -= 1;
+            return false;
+        }
+
+        state.properties.hit_count += 1;
+        true
+    }
+
+    /// Hit the breakpoint, because its condition was detected by an access from `trigger`.
+    /// Unless the breakpoint is deleted, disabled or ignores the hit, its hit count is
+    /// incremented and its hit closure is called, and a temporary breakpoint is deleted.
+    /// Returns whether the breakpoint triggered.
+    pub fn hit(&mut self, trigger: Option<*mut ConfObject>) -> Result<bool> {
+        if !self.count_hit() {
+            return Ok(false);
+        }
+
+        let mut on_hit = replace(&mut self.state_mut().on_hit, Box::new(|_| {}));
+
+        // NOTE: The closure is called without borrowing the state, because the breakpoint
+        // manager may change the properties of the breakpoint through its callbacks while the
+        // closure runs, for example when the closure disables the breakpoint
+        on_hit(trigger);
+
+        let state = self.state_mut();
+        state.on_hit = on_hit;
+
+        if state.properties.temporary {
+            BreakpointManager::from_obj(self.manager).delete(self.id)?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl Drop for Breakpoint {
+    fn drop(&mut self) {
+        if !self.is_deleted() {
+            if let Ok(mut registration) =
+                get_interface::<BreakpointRegistrationInterface>(self.manager)
+            {
+                registration.deleted(self.id).ok();
+            }
+        }
+
+        drop(unsafe { Box::from_raw(self.state) });
+    }
+}
+
+/// A breakpoint of a type provided through [`BreakpointTypeProvider`], registered with the
+/// breakpoint manager with [`BreakpointManager::register_provider_breakpoint`]. The
+/// breakpoint is removed from the manager and freed when dropped.
+///
+/// When the breakpoint manager deletes the breakpoint, it calls
+/// [`BreakpointTypeProvider::remove_bp`] of the provider, which should drop the handle.
+pub struct ProviderBreakpoint {
+    breakpoint: Breakpoint,
+    provider: *mut ConfObject,
+    bp_id: u64,
+}
+
+impl ProviderBreakpoint {
+    /// The id of the breakpoint in the breakpoint manager
+    pub fn id(&self) -> BreakpointManagerId {
+        self.breakpoint.id()
+    }
+
+    /// The id of the breakpoint in its provider
+    pub fn bp_id(&self) -> u64 {
+        self.bp_id
+    }
+
+    /// The properties of the breakpoint
+    pub fn properties(&self) -> BreakpointProperties {
+        self.breakpoint.properties()
+    }
+
+    /// Whether the breakpoint is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.breakpoint.is_enabled()
+    }
+
+    /// Whether the breakpoint has been deleted through the breakpoint manager, after which
+    /// it is never hit
+    pub fn is_deleted(&self) -> bool {
+        self.breakpoint.is_deleted()
+    }
+
+    /// Hit the breakpoint, because its condition was detected by an access from `trigger`.
+    /// Unless the breakpoint is deleted, disabled or ignores the hit, its hit count is
+    /// incremented and the hit is reported to the breakpoint manager with the message `msg`.
+    /// Returns whether the simulation should stop.
+    ///
+    /// A temporary breakpoint is not deleted by the hit, because the manager removes it
+    /// through the provider, which owns this handle. The provider should drop the handle
+    /// after a hit of a temporary breakpoint instead.
+    pub fn hit<S>(&mut self, trigger: Option<*mut ConfObject>, msg: S) -> Result<bool>
+    where
+        S: AsRef<str>,
+    {
+        if !self.breakpoint.count_hit() {
+            return Ok(false);
+        }
+
+        BreakpointManager::from_obj(self.breakpoint.manager).trigger(
+            self.provider,
+            self.bp_id,
+            trigger,
+            msg,
+        )
+    }
+}
+
+/// An object which provides a type of breakpoint by implementing the
+/// `breakpoint_type_provider` interface. The breakpoint manager calls the provider to create
+/// and remove breakpoints of the type, and to describe their hits.
+///
+/// ```rust,ignore
+/// impl BreakpointTypeProvider for MagicBreakpoints {
+///     fn add_bp(&mut self, _flags: i32, args: AttrValue) -> Result<u64> {
+///         self.next_id += 1;
+///         self.values.insert(self.next_id, args.try_into()?);
+///         Ok(self.next_id)
+///     }
+///
+///     fn register_bp(&mut self, provider: *mut ConfObject, bp_id: u64) -> Result<i32> {
+///         let breakpoint = BreakpointManager::new()?.register_provider_breakpoint(
+///             provider,
+///             bp_id,
+///             format!("Break on magic value {}", self.values[&bp_id]),
+///         )?;
+///         let id = breakpoint.id();
+///         self.breakpoints.insert(bp_id, breakpoint);
+///         Ok(id)
+///     }
+///
+///     fn remove_bp(&mut self, bp_id: u64) {
+///         self.values.remove(&bp_id);
+///         self.breakpoints.remove(&bp_id);
+///     }
+///
+///     fn trace_msg(&mut self, bp_id: u64) -> String {
+///         format!("Magic value {} seen", self.values[&bp_id])
+///     }
+///
+///     fn break_msg(&mut self, bp_id: u64) -> String {
+///         format!("Stopped on magic value {}", self.values[&bp_id])
+///     }
+/// }
+///
+/// MagicBreakpoints::register_breakpoint_type_provider(cls)?;
+/// ```
+pub trait BreakpointTypeProvider: FromConfObject + 'static {
+    /// Create a breakpoint of the type from the arguments `args` of the command creating it.
+    /// `flags` are the `Breakpoint_Type_*` flags of the command. Returns the provider id of
+    /// the new breakpoint, which must not be 0.
+    fn add_bp(&mut self, flags: i32, args: AttrValue) -> Result<u64>;
+
+    /// Register the breakpoint with the provider id `bp_id` with the breakpoint manager,
+    /// typically with [`BreakpointManager::register_provider_breakpoint`] passing
+    /// `provider`, the provider object, and keeping the returned handle until the breakpoint
+    /// is removed. Returns the id of the breakpoint in the breakpoint manager.
+    fn register_bp(&mut self, provider: *mut ConfObject, bp_id: u64)
+        -> Result<BreakpointManagerId>;
+
+    /// Remove the breakpoint with the provider id `bp_id`
+    fn remove_bp(&mut self, bp_id: u64);
+
+    /// The message printed when the breakpoint with the provider id `bp_id` is hit while
+    /// tracing
+    fn trace_msg(&mut self, bp_id: u64) -> String;
+
+    /// The message printed when the simulation stops because the breakpoint with the
+    /// provider id `bp_id` is hit
+    fn break_msg(&mut self, bp_id: u64) -> String;
+
+    /// The message printed when a script waiting for the breakpoint with the provider id
+    /// `bp_id` is resumed. Defaults to the break message.
+    fn wait_msg(&mut self, bp_id: u64) -> String {
+        self.break_msg(bp_id)
+    }
+
+    /// The data returned to a script waiting for the breakpoint with the provider id
+    /// `bp_id`. Defaults to nil.
+    fn break_data(&mut self, _bp_id: u64) -> AttrValue {
+        AttrValue::nil()
+    }
+
+    /// The possible values of the command argument `arg`, given the arguments `prev_args`
+    /// before it, used for tab completion. Defaults to no values.
+    fn values(&mut self, _arg: &str, _prev_args: AttrValue) -> Result<AttrValue> {
+        AttrValue::list(0)
+    }
+
+    /// Register the `breakpoint_type_provider` interface for the class of this object
+    fn register_breakpoint_type_provider(cls: *mut ConfClass) -> Result<()> {
+        register_breakpoint_type_provider::<Self>(cls)
+    }
+}
+
+/// Copy a message into a string allocated by the simulator, which the breakpoint manager
+/// frees. The message is truncated at its first nul byte.
+fn simulator_string<S>(msg: S) -> *mut c_char
+where
+    S: AsRef<str>,
+{
+    let msg = msg.as_ref().as_bytes();
+    let msg = msg.split(|b| *b == 0).next().unwrap_or_default();
+
+    // NOTE: The allocation is zeroed, so the string is terminated
+    match alloc::<c_char, _>(msg.len() + 1, "char", file!(), line!() as i32) {
+        Ok(string) if !string.is_null() => {
+            unsafe { copy_nonoverlapping(msg.as_ptr() as *const c_char, string, msg.len()) };
+            string
+        }
+        _ => null_mut(),
+    }
+}
+
+extern "C" fn provider_register_bp<T>(obj: *mut ConfObject, bp_id: u64) -> u64
+where
+    T: BreakpointTypeProvider,
+{
+    // NOTE: A breakpoint which could not be registered is reported with id 0
+    unsafe { T::from_conf_object_mut(obj) }
+        .register_bp(obj, bp_id)
+        .ok()
+        .and_then(|id| u64::try_from(id).ok())
+        .unwrap_or(0)
+}
+
+extern "C" fn provider_add_bp<T>(obj: *mut ConfObject, flags: i32, data: attr_value_t) -> u64
+where
+    T: BreakpointTypeProvider,
+{
+    // NOTE: A breakpoint which could not be created is reported with id 0
+    unsafe { T::from_conf_object_mut(obj) }
+        .add_bp(flags, data.into())
+        .unwrap_or(0)
+}
+
+extern "C" fn provider_remove_bp<T>(obj: *mut ConfObject, bp_id: u64)
+where
+    T: BreakpointTypeProvider,
+{
+    unsafe { T::from_conf_object_mut(obj) }.remove_bp(bp_id)
+}
+
+extern "C" fn provider_trace_msg<T>(obj: *mut ConfObject, bp_id: u64) -> *mut c_char
+where
+    T: BreakpointTypeProvider,
+{
+    simulator_string(unsafe { T::from_conf_object_mut(obj) }.trace_msg(bp_id))
+}
+
+extern "C" fn provider_break_msg<T>(obj: *mut ConfObject, bp_id: u64) -> *mut c_char
+where
+    T: BreakpointTypeProvider,
+{
+    simulator_string(unsafe { T::from_conf_object_mut(obj) }.break_msg(bp_id))
+}
+
+extern "C" fn provider_wait_msg<T>(obj: *mut ConfObject, bp_id: u64) -> *mut c_char
+where
+    T: BreakpointTypeProvider,
+{
+    simulator_string(unsafe { T::from_conf_object_mut(obj) }.wait_msg(bp_id))
+}
+
+extern "C" fn provider_break_data<T>(obj: *mut ConfObject, bp_id: u64) -> attr_value_t
+where
+    T: BreakpointTypeProvider,
+{
+    unsafe { T::from_conf_object_mut(obj) }
+        .break_data(bp_id)
+        .into_raw()
+}
+
+extern "C" fn provider_values<T>(
+    obj: *mut ConfObject,
+    arg: *const c_char,
+    prev_args: attr_value_t,
+) -> attr_value_t
+where
+    T: BreakpointTypeProvider,
+{
+    let arg = if arg.is_null() {
+        String::new()
+    } else {
+        unsafe { CStr::from_ptr(arg) }.to_string_lossy().to_string()
+    };
+
+    // NOTE: The breakpoint manager expects a list, so an empty list is reported if the
+    // values cannot be computed
+    unsafe { T::from_conf_object_mut(obj) }
+        .values(&arg, prev_args.into())
+        .or_else(|_| AttrValue::list(0))
+        .unwrap_or_else(|_| AttrValue::nil())
+        .into_raw()
+}
+
+#[simics_exception]
+/// Register the `breakpoint_type_provider` interface for a class whose objects implement
+/// [`BreakpointTypeProvider`]
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the interface for
+///
+/// # Context
+///
+/// Global Context
+pub fn register_breakpoint_type_provider<T>(cls: *mut ConfClass) -> Result<()>
+where
+    T: BreakpointTypeProvider,
+{
+    // NOTE: The interface structure must never be freed, so it is leaked here
+    let iface = Box::into_raw(Box::new(breakpoint_type_provider_interface_t {
+        register_bp: Some(provider_register_bp::<T>),
+        add_bp: Some(provider_add_bp::<T>),
+        remove_bp: Some(provider_remove_bp::<T>),
+        trace_msg: Some(provider_trace_msg::<T>),
+        break_msg: Some(provider_break_msg::<T>),
+        wait_msg: Some(provider_wait_msg::<T>),
+        break_data: Some(provider_break_data::<T>),
+        values: Some(provider_values::<T>),
+        ..Default::default()
+    }));
+
+    if unsafe {
+        SIM_register_interface(
+            cls,
+            BreakpointTypeProviderInterface::NAME.as_raw_cstr()?,
+            iface as *mut _,
+        )
+    } != 0
+    {
+        return Err(Error::RegisterInterface {
+            name: "breakpoint_type_provider".to_string(),
+            message: crate::last_error(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{property_name, BreakpointProperties};
+    use crate::{
+        sys::{BREAKPOINT_PROP_ENABLED, BREAKPOINT_PROP_HIT_COUNT},
+        AttrValue, AttrValueType,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_property_name() {
+        assert_eq!(property_name(b"enabled\0"), "enabled");
+        assert_eq!(property_name(b"enabled"), "enabled");
+    }
+
+    #[test]
+    fn test_properties_round_trip() {
+        let properties = BreakpointProperties {
+            enabled: true,
+            temporary: true,
+            ignore_count: 2,
+            hit_count: 5,
+            description: "Break on magic value".to_string(),
+        };
+
+        let value = AttrValue::try_from(&properties).expect("Failed to convert properties");
+
+        assert_eq!(
+            BreakpointProperties::try_from(value).expect("Failed to convert value"),
+            properties
+        );
+    }
+
+    #[test]
+    fn test_properties_from_partial_dict() {
+        let value = AttrValue::try_from(BTreeMap::from([
+            (
+                property_name(BREAKPOINT_PROP_ENABLED),
+                AttrValueType::Bool(true),
+            ),
+            (
+                property_name(BREAKPOINT_PROP_HIT_COUNT),
+                AttrValueType::Unsigned(3),
+            ),
+        ]))
+        .expect("Failed to convert dict");
+
+        assert_eq!(
+            BreakpointProperties::try_from(value).expect("Failed to convert value"),
+            BreakpointProperties {
+                enabled: true,
+                hit_count: 3,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_properties_wrong_type() {
+        let value = AttrValue::try_from(BTreeMap::from([(
+            property_name(BREAKPOINT_PROP_HIT_COUNT),
+            AttrValueType::String("many".to_string()),
+        )]))
+        .expect("Failed to convert dict");
+
+        assert!(BreakpointProperties::try_from(value).is_err());
+    }
+}
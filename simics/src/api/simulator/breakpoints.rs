@@ -4,14 +4,16 @@
 //! Breakpoint APIs
 
 use crate::{
-    simics_exception,
+    free, simics_exception,
     sys::{
-        access_t, breakpoint_flag, breakpoint_id_t, breakpoint_kind_t, SIM_breakpoint,
-        SIM_breakpoint_remove, SIM_delete_breakpoint, SIM_disable_breakpoint,
-        SIM_enable_breakpoint,
+        access_t, breakpoint_flag, breakpoint_handle_t, breakpoint_id_t, breakpoint_info_t,
+        breakpoint_kind_t, breakpoint_set_t, SIM_breakpoint, SIM_breakpoint_remove,
+        SIM_delete_breakpoint, SIM_disable_breakpoint, SIM_enable_breakpoint,
+        SIM_inspect_breakpoints,
     },
     ConfObject, GenericAddress,
 };
+use std::slice::from_raw_parts;
 
 /// Alias for `breakpoint_kind_t`
 pub type BreakpointKind = breakpoint_kind_t;
@@ -21,6 +23,12 @@ pub type Access = access_t;
 pub type BreakpointFlag = breakpoint_flag;
 /// Alias for `breakpoint_id_t`
 pub type BreakpointId = breakpoint_id_t;
+/// Alias for `breakpoint_handle_t`
+pub type BreakpointHandle = breakpoint_handle_t;
+/// Alias for `breakpoint_info_t`
+pub type BreakpointInfo = breakpoint_info_t;
+/// Alias for `breakpoint_set_t`
+pub type BreakpointSet = breakpoint_set_t;
 
 #[simics_exception]
 /// Add breakpoint on an object implementing the breakpoint interface. This is typically
@@ -160,3 +168,47 @@ pub fn breakpoint_remove(
 ) {
     unsafe { SIM_breakpoint_remove(id, access, address, length) };
 }
+
+#[simics_exception]
+/// Find the breakpoints set on an object which match an access in an address range. The
+/// breakpoints are returned with the part of their range which overlaps the given range.
+///
+/// # Arguments
+///
+/// - `obj`: The object to inspect the breakpoints of, typically a memory space
+/// - `access`: A bitfield of the types of access to find breakpoints for
+/// - `start`: The start address of the range
+/// - `end`: The end address of the range, inclusive
+///
+/// # Return Value
+///
+/// The breakpoints matching the access in the range
+///
+/// # Context
+///
+/// Cell Context
+pub fn inspect_breakpoints(
+    obj: *mut ConfObject,
+    access: Access,
+    start: GenericAddress,
+    end: GenericAddress,
+) -> Vec<BreakpointInfo> {
+    let set = unsafe { SIM_inspect_breakpoints(obj, access, start, end) };
+
+    if set.breakpoints.is_null() {
+        return Vec::new();
+    }
+
+    let breakpoints = unsafe {
+        from_raw_parts(
+            set.breakpoints,
+            usize::try_from(set.num_breakpoints).unwrap_or_default(),
+        )
+    }
+    .to_vec();
+
+    // NOTE: The array of breakpoints is owned by the caller
+    free(set.breakpoints);
+
+    breakpoints
+}
@@ -0,0 +1,295 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Streams and futures over simulator callbacks
+//!
+//! A [`CallbackStream`] owns the subscription of a callback, such as a hap callback or a
+//! notifier subscription, and yields a value each time the callback is called. Together with
+//! an executor polled from simulator callbacks, this allows a sequence of simulation events
+//! to be awaited in order:
+//!
+//! ```rust,ignore
+//! let (_cpu, _magic) = CoreMagicInstructionHap::next_occurrence()?.await;
+//! let (_cpu, _register, _value) = CoreControlRegisterWriteHap::next_occurrence()?.await;
+//! take_snapshot("cr3-changed")?;
+//! ```
+//!
+//! The `stream` and `next_occurrence` methods are available on the generated hap types whose
+//! callbacks return nothing, such as `CoreMagicInstructionHap`, and on hap types declared with
+//! `#[hap]` through the `CustomHap` trait. Streams over other callbacks are created with
+//! [`CallbackStream::new`].
+//!
+//! A waker may poll its task from inside the callback. If the task drops a stream over a hap
+//! from there, the hap callback is deleted immediately and its closure is freed once the
+//! callback returns.
+
+use crate::{HapSubscription, Result};
+use futures_core::Stream;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll, Waker},
+};
+
+/// A stream of the occurrences of a hap
+pub type HapStream<T> = CallbackStream<T, HapSubscription>;
+/// A future resolving on the next occurrence of a hap
+pub type HapOccurrence<T> = NextOccurrence<T, HapSubscription>;
+
+/// The values sent to a stream which have not yet been received, and the waker of the task
+/// waiting to receive them
+struct CallbackQueue<T> {
+    values: VecDeque<T>,
+    waker: Option<Waker>,
+}
+
+impl<T> CallbackQueue<T> {
+    fn poll_value(&mut self, cx: &mut Context<'_>) -> Poll<T> {
+        match self.values.pop_front() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                self.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// The sending half of a [`CallbackStream`], which is moved into the callback feeding the
+/// stream
+pub struct CallbackSender<T> {
+    queue: Rc<RefCell<CallbackQueue<T>>>,
+}
+
+impl<T> CallbackSender<T> {
+    /// Send a value to the stream, waking the task waiting on it
+    pub fn send(&self, value: T) {
+        let waker = {
+            let mut queue = self.queue.borrow_mut();
+            queue.values.push_back(value);
+            queue.waker.take()
+        };
+
+        // NOTE: Waking the task must be the last use of the sender, because an executor may
+        // poll the task immediately, which may drop the stream and release the callback
+        // owning this sender
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// A stream of values sent from a simulator callback. The callback is removed when the stream
+/// is dropped. The stream never ends while it is subscribed.
+pub struct CallbackStream<T, S> {
+    queue: Rc<RefCell<CallbackQueue<T>>>,
+    _subscription: S,
+}
+
+impl<T, S> CallbackStream<T, S> {
+    /// Create a new stream. `subscribe` is called with the sender of the stream and returns
+    /// the subscription of a callback which sends values with it.
+    ///
+    /// ```rust,ignore
+    /// let stream = CallbackStream::new(|sender| {
+    ///     add_notifier(obj, what, None, move |obj| sender.send(obj))
+    /// })?;
+    /// ```
+    pub fn new<F>(subscribe: F) -> Result<Self>
+    where
+        F: FnOnce(CallbackSender<T>) -> Result<S>,
+    {
+        let queue = Rc::new(RefCell::new(CallbackQueue {
+            values: VecDeque::new(),
+            waker: None,
+        }));
+
+        let subscription = subscribe(CallbackSender {
+            queue: queue.clone(),
+        })?;
+
+        Ok(Self {
+            queue,
+            _subscription: subscription,
+        })
+    }
+
+    /// Get a future resolving to the next value sent to the stream. The stream is dropped,
+    /// removing its callback, when the future resolves.
+    pub fn next_occurrence(self) -> NextOccurrence<T, S> {
+        NextOccurrence { stream: Some(self) }
+    }
+}
+
+impl<T, S> Stream for CallbackStream<T, S> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.queue.borrow_mut().poll_value(cx).map(Some)
+    }
+}
+
+/// A future resolving to the next value sent to a [`CallbackStream`]. The callback is removed
+/// when the future resolves or is dropped.
+pub struct NextOccurrence<T, S> {
+    stream: Option<CallbackStream<T, S>>,
+}
+
+// NOTE: The stream is never pinned, so the future can be moved after it is polled
+impl<T, S> Unpin for NextOccurrence<T, S> {}
+
+impl<T, S> Future for NextOccurrence<T, S> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // NOTE: A future which has already resolved is never woken again
+        let Some(stream) = self.stream.as_ref() else {
+            return Poll::Pending;
+        };
+
+        let value = stream.queue.borrow_mut().poll_value(cx);
+
+        if value.is_ready() {
+            self.stream = None;
+        }
+
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallbackSender, CallbackStream};
+    use futures_core::Stream;
+    use std::{
+        cell::Cell,
+        future::Future,
+        pin::Pin,
+        rc::Rc,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        task::{Context, Poll, Wake, Waker},
+    };
+
+    /// A waker counting the number of times it is woken
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl CountingWaker {
+        fn count(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// A subscription recording whether it has been dropped
+    struct Subscription(Rc<Cell<bool>>);
+
+    impl Drop for Subscription {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    fn stream() -> (
+        CallbackStream<u32, Subscription>,
+        CallbackSender<u32>,
+        Rc<Cell<bool>>,
+    ) {
+        let dropped = Rc::new(Cell::new(false));
+        let mut sender = None;
+        let stream = CallbackStream::new(|s| {
+            sender = Some(s);
+            Ok(Subscription(dropped.clone()))
+        })
+        .expect("Failed to create stream");
+
+        (stream, sender.expect("Sender not created"), dropped)
+    }
+
+    #[test]
+    fn test_stream_queue() {
+        let (mut stream, sender, _) = stream();
+        let waker = Arc::new(CountingWaker::default());
+        let waker_ref = Waker::from(waker.clone());
+        let mut cx = Context::from_waker(&waker_ref);
+
+        sender.send(1);
+        sender.send(2);
+
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(1))
+        );
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(2))
+        );
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+        assert_eq!(waker.count(), 0);
+    }
+
+    #[test]
+    fn test_stream_waker() {
+        let (mut stream, sender, _) = stream();
+        let waker = Arc::new(CountingWaker::default());
+        let waker_ref = Waker::from(waker.clone());
+        let mut cx = Context::from_waker(&waker_ref);
+
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+
+        sender.send(1);
+        // The waker is taken when it is woken, so it is only woken once until polled again
+        sender.send(2);
+
+        assert_eq!(waker.count(), 1);
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(1))
+        );
+        assert_eq!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(2))
+        );
+        assert_eq!(Pin::new(&mut stream).poll_next(&mut cx), Poll::Pending);
+
+        sender.send(3);
+
+        assert_eq!(waker.count(), 2);
+    }
+
+    #[test]
+    fn test_next_occurrence_unsubscribes() {
+        let (stream, sender, dropped) = stream();
+        let waker = Arc::new(CountingWaker::default());
+        let waker_ref = Waker::from(waker.clone());
+        let mut cx = Context::from_waker(&waker_ref);
+        let mut next = stream.next_occurrence();
+
+        assert_eq!(Pin::new(&mut next).poll(&mut cx), Poll::Pending);
+        assert!(!dropped.get());
+
+        sender.send(1);
+        sender.send(2);
+
+        assert_eq!(waker.count(), 1);
+        assert_eq!(Pin::new(&mut next).poll(&mut cx), Poll::Ready(1));
+        assert!(dropped.get());
+
+        // Values sent after the future resolved are discarded
+        sender.send(3);
+
+        assert_eq!(Pin::new(&mut next).poll(&mut cx), Poll::Pending);
+        assert_eq!(waker.count(), 1);
+    }
+}
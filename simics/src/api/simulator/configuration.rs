@@ -8,13 +8,15 @@ use crate::{
     sys::{
         pre_conf_object_set_t, save_flags_t, SIM_add_configuration, SIM_current_checkpoint_dir,
         SIM_read_configuration, SIM_set_configuration, SIM_write_configuration_to_file,
+        SIM_write_persistent_state,
     },
-    AttrValue, Error, Result,
+    AttrValue, ConfObject, Error, Result,
 };
 use raw_cstr::raw_cstr;
 use std::{
     ffi::CStr,
     path::{Path, PathBuf},
+    ptr::null_mut,
 };
 
 /// Alias for `pre_conf_object_set_t`
@@ -186,3 +188,39 @@ where
     };
     Ok(())
 }
+
+#[simics_exception]
+/// Save the persistent state of the configuration to a file
+///
+/// Saves only the attributes marked [`crate::AttrAttr::Sim_Attr_Persistent`], such as the
+/// contents of disk images, of all objects below `root` in the object hierarchy, or of all
+/// objects if `root` is `None`. The saved state can be loaded on top of a configuration
+/// with [`read_configuration`].
+///
+/// # Arguments
+///
+/// * `file` - The file to save the persistent state to
+/// * `root` - The object whose descendants to save the persistent state of, or `None` to
+///   save the persistent state of all objects
+/// * `flags` - Flags controlling how the state is saved
+///
+/// # Context
+///
+/// Global Context
+pub fn write_persistent_state<P>(
+    file: P,
+    root: Option<*mut ConfObject>,
+    flags: SaveFlags,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    unsafe {
+        SIM_write_persistent_state(
+            raw_cstr(file.as_ref().to_str().ok_or(Error::ToString)?)?,
+            root.unwrap_or(null_mut()),
+            flags,
+        )
+    };
+    Ok(())
+}
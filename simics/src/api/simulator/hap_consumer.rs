@@ -7,22 +7,206 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref, clippy::too_long_first_doc_paragraph)]
 
 use crate::{
-    simics_exception,
+    last_error, simics_exception,
     sys::{
         hap_handle_t, hap_type_t, SIM_get_all_hap_types, SIM_hap_add_type, SIM_hap_get_name,
         SIM_hap_get_number, SIM_hap_is_active, SIM_hap_is_active_obj, SIM_hap_is_active_obj_idx,
-        SIM_hap_occurred_always, SIM_hap_remove_type,
+        SIM_hap_occurred, SIM_hap_occurred_always, SIM_hap_remove_type,
     },
-    AttrValue, ConfObject, Result,
+    AttrValue, ConfObject, CustomHap, Error, Hap, ObjectDeleteWatch, Result,
+};
+use raw_cstr::{raw_cstr, AsRawCstr};
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{c_void, CStr},
+    ptr::null_mut,
 };
-use raw_cstr::raw_cstr;
-use std::{ffi::CStr, ptr::null_mut};
 
 /// Alias for `hap_handle_t`
 pub type HapHandle = hap_handle_t;
 /// Alias for `hap_type_t`
 pub type HapType = hap_type_t;
 
+#[doc(hidden)]
+/// The closure of a hap callback. The closure is owned by the [`HapSubscription`] of the
+/// callback, and is freed once the subscription is dropped and the closure is no longer
+/// running, so a subscription can be dropped from inside its own callback. A callback which
+/// causes its own hap to occur again is not called for that occurrence.
+pub struct HapClosure<F> {
+    callback: RefCell<F>,
+    released: Cell<bool>,
+}
+
+impl<F> HapClosure<F> {
+    /// Allocate the closure of a hap callback, which is passed to the simulator as the user
+    /// data of the callback
+    pub fn new(callback: F) -> *mut Self {
+        Box::into_raw(Box::new(Self {
+            callback: RefCell::new(callback),
+            released: Cell::new(false),
+        }))
+    }
+
+    /// Call the closure of a hap callback from its handler with `call`. If the closure is
+    /// already running, it is not called again and the default value is returned. If the
+    /// subscription of the callback was dropped while the closure was running, the closure
+    /// is freed once it returns.
+    ///
+    /// # Safety
+    ///
+    /// `closure` must have been created with [`HapClosure::new`] and must not have been freed
+    pub unsafe fn call<R, C>(closure: *mut c_void, call: C) -> R
+    where
+        R: Default,
+        C: FnOnce(&mut F) -> R,
+    {
+        let state = &*(closure as *const Self);
+
+        // An occurrence of the hap caused by the callback itself is not delivered back to it
+        let Ok(mut callback) = state.callback.try_borrow_mut() else {
+            return R::default();
+        };
+
+        let result = call(&mut *callback);
+        drop(callback);
+
+        if state.released.get() {
+            drop(Box::from_raw(closure as *mut Self));
+        }
+
+        result
+    }
+
+    /// Release the closure of a hap callback whose subscription was dropped. The closure is
+    /// freed immediately unless it is running, in which case it is freed when it returns.
+    ///
+    /// # Safety
+    ///
+    /// `closure` must have been created with [`HapClosure::new`] and must not have been
+    /// released before
+    unsafe fn release(closure: *mut c_void) {
+        let state = &*(closure as *const Self);
+
+        if state.callback.try_borrow_mut().is_err() {
+            state.released.set(true);
+        } else {
+            drop(Box::from_raw(closure as *mut Self));
+        }
+    }
+}
+
+/// A callback added to a hap. The callback is deleted and its closure is freed when the
+/// subscription is dropped. A subscription may be dropped from inside its own callback, in
+/// which case the closure is freed once the callback returns.
+///
+/// A callback which should stay installed for the rest of the simulation can be made
+/// permanent with [`HapSubscription::forget`]. The simulator deletes the callbacks added for
+/// an object when the object is deleted, which the subscription keeps track of so it does
+/// not delete the callback again.
+#[derive(Debug)]
+#[must_use = "the hap callback is removed when the subscription is dropped"]
+pub struct HapSubscription {
+    handle: HapHandle,
+    obj: Option<*mut ConfObject>,
+    delete: fn(Option<*mut ConfObject>, HapHandle) -> Result<()>,
+    closure: *mut c_void,
+    free_closure: unsafe fn(*mut c_void),
+    watch: Option<ObjectDeleteWatch>,
+}
+
+impl HapSubscription {
+    /// Create a subscription owning the closure `closure`, added as a callback
+    /// for the hap `H` with the handle `handle`, for the object `obj` if it was added for a
+    /// specific object. If the callback could not be added, which is reported by a negative
+    /// handle, the closure is freed and an error is returned.
+    ///
+    /// # Safety
+    ///
+    /// `closure` must have been created with [`HapClosure::new`] and must not be freed
+    /// elsewhere
+    pub(crate) unsafe fn new<H, F>(
+        handle: HapHandle,
+        obj: Option<*mut ConfObject>,
+        closure: *mut HapClosure<F>,
+    ) -> Result<Self>
+    where
+        H: Hap,
+    {
+        if handle < 0 {
+            HapClosure::<F>::release(closure as *mut c_void);
+            return Err(Error::AddHapCallback {
+                message: last_error(),
+            });
+        }
+
+        let mut subscription = Self {
+            handle,
+            obj,
+            delete: delete_hap_callback::<H>,
+            closure: closure as *mut c_void,
+            free_closure: HapClosure::<F>::release,
+            watch: None,
+        };
+
+        // NOTE: If the object cannot be watched, the subscription is dropped, deleting the
+        // callback and freeing the closure
+        subscription.watch = obj.map(ObjectDeleteWatch::new).transpose()?;
+
+        Ok(subscription)
+    }
+
+    /// Get the raw handle of the callback
+    pub fn handle(&self) -> HapHandle {
+        self.handle
+    }
+
+    /// Get the object the callback was added for, if it was added for a specific object
+    pub fn object(&self) -> Option<*mut ConfObject> {
+        self.obj
+    }
+
+    /// Whether the callback is still added to the hap. The callback of a subscription for an
+    /// object is deleted by the simulator when the object is deleted.
+    pub fn is_active(&self) -> bool {
+        !self
+            .watch
+            .as_ref()
+            .is_some_and(ObjectDeleteWatch::is_deleted)
+    }
+
+    /// Make the callback permanent. The callback is never deleted by the subscription, and
+    /// its closure is never freed. The raw handle of the callback is returned, which can still
+    /// be used to delete the callback with [`Hap::delete_callback_id`] or
+    /// [`Hap::delete_callback_obj_id`].
+    pub fn forget(mut self) -> HapHandle {
+        let handle = self.handle;
+        // NOTE: The watch of the object is dropped, because it is no longer needed
+        drop(self.watch.take());
+        std::mem::forget(self);
+        handle
+    }
+}
+
+impl Drop for HapSubscription {
+    fn drop(&mut self) {
+        if self.is_active() {
+            (self.delete)(self.obj, self.handle).ok();
+        }
+
+        unsafe { (self.free_closure)(self.closure) };
+    }
+}
+
+fn delete_hap_callback<H>(obj: Option<*mut ConfObject>, handle: HapHandle) -> Result<()>
+where
+    H: Hap,
+{
+    match obj {
+        Some(obj) => H::delete_callback_obj_id(obj, handle),
+        None => H::delete_callback_id(handle),
+    }
+}
+
 #[simics_exception]
 /// Return an attribute list of all hap types.
 pub fn get_all_hap_types() -> AttrValue {
@@ -82,6 +266,18 @@ pub fn hap_is_active_obj_idx(hap: HapType, obj: *mut ConfObject, index: i64) ->
     unsafe { SIM_hap_is_active_obj_idx(hap, obj, index) }
 }
 
+#[simics_exception]
+/// Trigger a hap occurrence, unless the hap has already occurred on the same object during
+/// the current cycle
+pub fn hap_occurred(
+    hap: HapType,
+    obj: Option<*mut ConfObject>,
+    value: i64,
+    list: &mut AttrValue,
+) -> i32 {
+    unsafe { SIM_hap_occurred(hap, obj.unwrap_or(null_mut()), value, list.as_mut_ptr()) }
+}
+
 #[simics_exception]
 /// Trigger a hap occurrence
 pub fn hap_occurred_always(
@@ -103,8 +299,248 @@ where
     Ok(())
 }
 
+#[simics_exception]
+/// Register the hap type of a hap declared in Rust with `#[hap]`
+///
+/// # Return Value
+///
+/// The number of the new hap type, or an error if the hap type could not be registered,
+/// for example because a hap type with the same name already exists
+///
+/// # Context
+///
+/// Global Context
+pub fn register_custom_hap<H>() -> Result<HapType>
+where
+    H: CustomHap,
+{
+    let index = H::INDEX.map(raw_cstr).transpose()?;
+
+    let hap_type = unsafe {
+        SIM_hap_add_type(
+            H::NAME.as_raw_cstr()?,
+            raw_cstr(H::PARAMS)?,
+            raw_cstr(H::PARAM_DESC)?,
+            index.unwrap_or(null_mut()),
+            raw_cstr(H::DESCRIPTION)?,
+            0,
+        )
+    };
+
+    if hap_type < 0 {
+        Err(Error::RegisterHap {
+            message: last_error(),
+        })
+    } else {
+        Ok(hap_type)
+    }
+}
+
+#[simics_exception]
+/// Look up the number of the hap type of a hap declared in Rust with `#[hap]`. Use
+/// [`CustomHap::hap_type`] instead, which caches the number.
+///
+/// # Return Value
+///
+/// The number of the hap type, or an error if the hap type has not been registered
+///
+/// # Context
+///
+/// Cell Context
+pub fn custom_hap_type<H>() -> Result<HapType>
+where
+    H: CustomHap,
+{
+    let name = H::NAME.as_raw_cstr()?;
+    let hap_type = unsafe { SIM_hap_get_number(name) };
+
+    // NOTE: Hap type numbers are positive, an unknown hap type is reported as zero
+    if hap_type <= 0 {
+        return Err(Error::HapNotFound {
+            name: unsafe { CStr::from_ptr(name) }
+                .to_string_lossy()
+                .to_string(),
+        });
+    }
+
+    Ok(hap_type)
+}
+
+#[simics_exception]
+/// Trigger an occurrence of a hap declared in Rust with `#[hap]`, with the fields of `hap` as
+/// the parameters of the occurrence
+///
+/// # Arguments
+///
+/// * `obj` - The object the hap occurs on
+/// * `index` - The index value of the occurrence
+/// * `hap` - The parameters of the occurrence
+/// * `always` - Whether to trigger the occurrence even if the hap has already occurred on
+///   the object during the current cycle
+///
+/// # Return Value
+///
+/// Nonzero if any callbacks were called
+///
+/// # Context
+///
+/// Cell Context
+pub fn custom_hap_occurred<H>(
+    obj: Option<*mut ConfObject>,
+    index: i64,
+    hap: H,
+    always: bool,
+) -> Result<i32>
+where
+    H: CustomHap,
+{
+    let hap_type = H::hap_type()?;
+    Ok(unsafe { H::occurred_raw(hap_type, obj.unwrap_or(null_mut()), index, hap, always) })
+}
+
 // NOTE: recommended to only use the always version
 
 include!(concat!(env!("OUT_DIR"), "/haps.rs"));
 // Re-export all HAPs
 pub use self::haps::*;
+
+#[cfg(test)]
+mod tests {
+    use super::{HapClosure, HapHandle, HapSubscription};
+    use crate::{CallbackStream, ConfObject, HapOccurrence, Result};
+    use std::{
+        cell::{Cell, RefCell},
+        ffi::c_void,
+        future::Future,
+        pin::Pin,
+        rc::Rc,
+        task::{Context, RawWaker, RawWakerVTable, Waker},
+    };
+
+    fn delete_nothing(_: Option<*mut ConfObject>, _: HapHandle) -> Result<()> {
+        Ok(())
+    }
+
+    fn subscription<F>(closure: *mut HapClosure<F>) -> HapSubscription {
+        HapSubscription {
+            handle: 0,
+            obj: None,
+            delete: delete_nothing,
+            closure: closure as *mut c_void,
+            free_closure: HapClosure::<F>::release,
+            watch: None,
+        }
+    }
+
+    unsafe fn call_with_one<F>(closure: *mut c_void)
+    where
+        F: FnMut(u32),
+    {
+        HapClosure::<F>::call(closure, |callback| callback(1))
+    }
+
+    /// Get a handler calling a closure with the value 1, as the simulator calls hap handlers
+    fn handler<F>(_: *mut HapClosure<F>) -> unsafe fn(*mut c_void)
+    where
+        F: FnMut(u32),
+    {
+        call_with_one::<F>
+    }
+
+    /// Sets a flag when the closure capturing it is freed
+    struct FreeFlag(Rc<Cell<bool>>);
+
+    impl Drop for FreeFlag {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    type PendingOccurrence = RefCell<Option<HapOccurrence<u32>>>;
+
+    const POLLING_WAKER: RawWakerVTable =
+        RawWakerVTable::new(clone_waker, wake_waker, wake_waker, drop_waker);
+
+    unsafe fn clone_waker(data: *const ()) -> RawWaker {
+        RawWaker::new(data, &POLLING_WAKER)
+    }
+
+    /// Poll the pending occurrence from inside the waker, as an executor may
+    unsafe fn wake_waker(data: *const ()) {
+        let pending = &*(data as *const PendingOccurrence);
+        let mut pending = pending.borrow_mut();
+
+        if let Some(occurrence) = pending.as_mut() {
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(Pin::new(occurrence).poll(&mut cx).is_ready());
+        }
+    }
+
+    unsafe fn drop_waker(_: *const ()) {}
+
+    #[test]
+    fn test_drop_subscription_in_sender() {
+        let freed = Rc::new(Cell::new(false));
+        let freed_in_callback = freed.clone();
+        let flag = FreeFlag(freed.clone());
+        let mut closure = None;
+        let mut call = None;
+
+        let stream = CallbackStream::new(|sender| {
+            let raw = HapClosure::new(move |value: u32| {
+                let _flag = &flag;
+                sender.send(value);
+                // The subscription was dropped by the waker, but the closure is still alive
+                assert!(!freed_in_callback.get());
+            });
+            closure = Some(raw as *mut c_void);
+            call = Some(handler(raw));
+            Ok(subscription(raw))
+        })
+        .expect("Failed to create stream");
+        let closure = closure.expect("Closure not created");
+        let call = call.expect("Handler not created");
+
+        let pending: Rc<PendingOccurrence> = Rc::new(RefCell::new(Some(stream.next_occurrence())));
+        let waker = unsafe {
+            Waker::from_raw(RawWaker::new(
+                Rc::as_ptr(&pending) as *const (),
+                &POLLING_WAKER,
+            ))
+        };
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(
+            Pin::new(pending.borrow_mut().as_mut().expect("No occurrence"))
+                .poll(&mut cx)
+                .is_pending()
+        );
+
+        unsafe { call(closure) };
+
+        assert!(freed.get());
+    }
+
+    #[test]
+    fn test_reentrant_call_skipped() {
+        type Callback = Box<dyn FnMut(u32)>;
+
+        let this = Rc::new(Cell::new(std::ptr::null_mut::<c_void>()));
+        let this_in_callback = this.clone();
+        let calls = Rc::new(Cell::new(0));
+        let calls_in_callback = calls.clone();
+
+        let callback: Callback = Box::new(move |value| {
+            calls_in_callback.set(calls_in_callback.get() + value);
+            // The callback causes its own hap to occur again
+            unsafe { call_with_one::<Callback>(this_in_callback.get()) };
+        });
+        let raw = HapClosure::new(callback);
+        this.set(raw as *mut c_void);
+
+        unsafe { handler(raw)(raw as *mut c_void) };
+        assert_eq!(calls.get(), 1);
+
+        drop(subscription(raw));
+    }
+}
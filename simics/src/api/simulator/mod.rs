@@ -3,7 +3,9 @@
 
 //! Simulator control APIs
 
+pub mod breakpoint_manager;
 pub mod breakpoints;
+pub mod callback_stream;
 pub mod callbacks;
 pub mod configuration;
 pub mod control;
@@ -13,6 +15,7 @@ pub mod hap_consumer;
 pub mod host_profiling;
 pub mod memory;
 pub mod modules;
+pub mod output;
 pub mod paths;
 pub mod processor;
 pub mod python;
@@ -37,8 +40,12 @@ pub mod sim_get_class;
     simics_version = "6.0.172",
 )))]
 pub mod snapshots;
+pub mod threading;
+pub mod watchpoint;
 
+pub use breakpoint_manager::*;
 pub use breakpoints::*;
+pub use callback_stream::*;
 pub use callbacks::*;
 pub use configuration::*;
 pub use control::*;
@@ -48,6 +55,7 @@ pub use hap_consumer::*;
 pub use host_profiling::*;
 pub use memory::*;
 pub use modules::*;
+pub use output::*;
 pub use paths::*;
 pub use processor::*;
 pub use python::*;
@@ -72,3 +80,5 @@ pub use sim_get_class::*;
     simics_version = "6.0.172",
 )))]
 pub use snapshots::*;
+pub use threading::*;
+pub use watchpoint::*;
@@ -0,0 +1,175 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Simulator console output
+
+use crate::{
+    simics_exception,
+    sys::{
+        SIM_add_output_handler, SIM_flush, SIM_printf, SIM_puts, SIM_remove_output_handler,
+        SIM_write,
+    },
+    Result,
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CString},
+    io::Write,
+    slice::from_raw_parts,
+};
+
+// NOTE: The callback is kept in a `RefCell` because it may write to the console itself,
+// which calls the output handler again while the callback is still running
+type OutputCallback = RefCell<Box<dyn FnMut(&[u8]) + 'static>>;
+
+/// A handler receiving the output of the simulator. The handler is removed and its callback
+/// is freed when it is dropped. The handler must not be dropped from inside its own
+/// callback, because that frees the callback while it is running.
+#[derive(Debug)]
+pub struct OutputHandler {
+    callback: *mut OutputCallback,
+}
+
+impl Drop for OutputHandler {
+    fn drop(&mut self) {
+        unsafe {
+            SIM_remove_output_handler(Some(handle_output), self.callback as *mut c_void);
+            drop(Box::from_raw(self.callback));
+        }
+    }
+}
+
+extern "C" fn handle_output(data: *mut c_void, src: *const c_char, length: usize) {
+    // NOTE: The callback is owned by its handler and freed when it is dropped
+    let callback = unsafe { &*(data as *const OutputCallback) };
+
+    if src.is_null() || length == 0 {
+        return;
+    }
+
+    // Output written by the callback itself is not delivered back to it
+    let Ok(mut callback) = callback.try_borrow_mut() else {
+        return;
+    };
+
+    callback(unsafe { from_raw_parts(src as *const u8, length) })
+}
+
+#[simics_exception]
+/// Add a handler receiving all output the simulator writes to its console. The handler is
+/// called with each chunk of output, which is not necessarily a complete line, until the
+/// returned handler is dropped. Output written to the console by the callback itself is not
+/// passed to the callback. The returned handler must not be dropped from inside the callback.
+///
+/// # Arguments
+///
+/// * `cb` - The closure to call with each chunk of output
+///
+/// # Return Value
+///
+/// The handler, which is removed when dropped
+///
+/// # Context
+///
+/// Global Context
+pub fn add_output_handler<F>(cb: F) -> OutputHandler
+where
+    F: FnMut(&[u8]) + 'static,
+{
+    let callback: OutputCallback = RefCell::new(Box::new(cb));
+    let callback = Box::into_raw(Box::new(callback));
+    unsafe { SIM_add_output_handler(Some(handle_output), callback as *mut c_void) };
+    OutputHandler { callback }
+}
+
+#[simics_exception]
+/// Add a handler writing all output the simulator writes to its console to a writer, until
+/// the returned handler is dropped. Errors writing to the writer are ignored.
+///
+/// # Arguments
+///
+/// * `writer` - The writer to write the output to
+///
+/// # Return Value
+///
+/// The handler, which is removed when dropped
+///
+/// # Context
+///
+/// Global Context
+pub fn add_output_writer<W>(mut writer: W) -> Result<OutputHandler>
+where
+    W: Write + 'static,
+{
+    add_output_handler(move |output| {
+        writer.write_all(output).ok();
+    })
+}
+
+#[simics_exception]
+/// Write bytes to the simulator console
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to write
+///
+/// # Return Value
+///
+/// The number of bytes written
+///
+/// # Context
+///
+/// All Contexts
+pub fn write(bytes: &[u8]) -> Result<usize> {
+    let written = unsafe { SIM_write(bytes.as_ptr() as *const c_void, bytes.len().try_into()?) };
+    Ok(written.try_into()?)
+}
+
+#[simics_exception]
+/// Write a string followed by a newline to the simulator console
+///
+/// # Arguments
+///
+/// * `s` - The string to write
+///
+/// # Context
+///
+/// All Contexts
+pub fn puts<S>(s: S) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    let s = CString::new(s.as_ref())?;
+    unsafe { SIM_puts(s.as_ptr()) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Write a string to the simulator console. Unlike the C function, the string is written
+/// as-is and not interpreted as a format string.
+///
+/// # Arguments
+///
+/// * `s` - The string to write
+///
+/// # Context
+///
+/// All Contexts
+pub fn printf<S>(s: S) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    let s = CString::new(s.as_ref())?;
+    unsafe { SIM_printf(c"%s".as_ptr(), s.as_ptr()) };
+    Ok(())
+}
+
+#[simics_exception]
+/// Flush the output written to the simulator console
+///
+/// # Context
+///
+/// All Contexts
+pub fn flush() {
+    unsafe { SIM_flush() };
+}
@@ -0,0 +1,263 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Thread domain locking
+//!
+//! Models in modules with the `THREADSAFE` capability may be accessed from threads which do
+//! not hold the thread domain of the object. Before accessing an object from such a thread,
+//! its thread domain must be acquired, and it must be released again afterward. The guards in
+//! this module release the domains they acquire when dropped, and must be dropped on the
+//! thread which created them, in the reverse order of creation.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    api::base::sim_exception::{clear_exception, get_pending_exception, last_error},
+    simics_exception,
+    sys::{
+        domain_lock_t, SIM_acquire_cell, SIM_acquire_object, SIM_acquire_object_for_execution,
+        SIM_acquire_target, SIM_drop_thread_domains, SIM_reacquire_thread_domains,
+        SIM_release_cell, SIM_release_object, SIM_release_target, SIM_yield_thread_domains,
+    },
+    ConfObject, Error, Result, SimException,
+};
+use raw_cstr::raw_cstr;
+use std::{panic::Location, ptr::null_mut};
+
+/// Alias for `domain_lock_t`
+pub type DomainLock = domain_lock_t;
+
+/// Format the location a lock is acquired at, which the simulator reports when diagnosing
+/// lock contention
+fn lock_location(location: &Location) -> String {
+    format!("{}:{}", location.file(), location.line())
+}
+
+/// Check for an exception raised while acquiring or dropping thread domains. The functions
+/// returning guards check for exceptions themselves instead of using `#[simics_exception]`,
+/// which would drop the guard of a domain that was never acquired and so release it.
+fn check_exception(name: &str) -> Result<()> {
+    match get_pending_exception() {
+        SimException::SimExc_No_Exception => Ok(()),
+        exception => {
+            clear_exception();
+            Err(Error::SimicsException {
+                exception,
+                msg: last_error() + "(" + name + ")",
+            })
+        }
+    }
+}
+
+#[derive(Debug)]
+/// A lock on the thread domain of an object, released when dropped
+pub struct ObjectLockGuard {
+    obj: *mut ConfObject,
+    lock: *mut DomainLock,
+}
+
+impl Drop for ObjectLockGuard {
+    fn drop(&mut self) {
+        unsafe { SIM_release_object(self.obj, self.lock) };
+    }
+}
+
+#[derive(Debug)]
+/// A lock on the cell of an object, released when dropped
+pub struct CellLockGuard {
+    obj: *mut ConfObject,
+    lock: *mut DomainLock,
+}
+
+impl Drop for CellLockGuard {
+    fn drop(&mut self) {
+        unsafe { SIM_release_cell(self.obj, self.lock) };
+    }
+}
+
+#[derive(Debug)]
+/// A lock on the thread domain of an object and the cell it belongs to, released when
+/// dropped
+pub struct TargetLockGuard {
+    obj: *mut ConfObject,
+    lock: *mut DomainLock,
+}
+
+impl Drop for TargetLockGuard {
+    fn drop(&mut self) {
+        unsafe { SIM_release_target(self.obj, self.lock) };
+    }
+}
+
+#[derive(Debug)]
+/// The thread domains held by a thread, which are reacquired when dropped
+pub struct DroppedThreadDomains {
+    lock: *mut DomainLock,
+}
+
+impl Drop for DroppedThreadDomains {
+    fn drop(&mut self) {
+        unsafe { SIM_reacquire_thread_domains(self.lock) };
+    }
+}
+
+#[track_caller]
+/// Acquire the thread domain of an object, entering Cell Context for the object. The
+/// domain is released when the returned guard is dropped.
+///
+/// # Arguments
+///
+/// * `obj` - The object to acquire the thread domain of
+///
+/// # Return Value
+///
+/// A guard which releases the thread domain when dropped
+///
+/// # Context
+///
+/// All Contexts
+pub fn acquire_object(obj: *mut ConfObject) -> Result<ObjectLockGuard> {
+    let location = lock_location(Location::caller());
+    let mut lock = null_mut();
+    unsafe {
+        SIM_acquire_object(
+            obj,
+            raw_cstr("acquire_object")?,
+            raw_cstr(location)?,
+            &mut lock,
+        )
+    };
+    check_exception("acquire_object")?;
+    Ok(ObjectLockGuard { obj, lock })
+}
+
+#[track_caller]
+/// Acquire the cell of an object, entering Cell Context for every object in the cell. The
+/// cell is released when the returned guard is dropped.
+///
+/// # Arguments
+///
+/// * `obj` - The object whose cell to acquire
+///
+/// # Return Value
+///
+/// A guard which releases the cell when dropped
+///
+/// # Context
+///
+/// All Contexts
+pub fn acquire_cell(obj: *mut ConfObject) -> Result<CellLockGuard> {
+    let location = lock_location(Location::caller());
+    let mut lock = null_mut();
+    unsafe {
+        SIM_acquire_cell(
+            obj,
+            raw_cstr("acquire_cell")?,
+            raw_cstr(location)?,
+            &mut lock,
+        )
+    };
+    check_exception("acquire_cell")?;
+    Ok(CellLockGuard { obj, lock })
+}
+
+#[track_caller]
+/// Acquire the thread domain of an object as needed to call its interfaces. This acquires
+/// the cell of the object unless the object is thread safe, in which case only its own
+/// thread domain is acquired. The domains are released when the returned guard is dropped.
+///
+/// # Arguments
+///
+/// * `obj` - The object to acquire
+///
+/// # Return Value
+///
+/// A guard which releases the acquired domains when dropped
+///
+/// # Context
+///
+/// All Contexts
+pub fn acquire_target(obj: *mut ConfObject) -> Result<TargetLockGuard> {
+    let location = lock_location(Location::caller());
+    let mut lock = null_mut();
+    unsafe {
+        SIM_acquire_target(
+            obj,
+            raw_cstr("acquire_target")?,
+            raw_cstr(location)?,
+            &mut lock,
+        )
+    };
+    check_exception("acquire_target")?;
+    Ok(TargetLockGuard { obj, lock })
+}
+
+#[simics_exception]
+/// Acquire the thread domain of an object for the rest of the execution of the current
+/// instruction, typically to access a device from a processor in another thread domain
+///
+/// # Arguments
+///
+/// * `obj` - The object to acquire the thread domain of
+///
+/// # Context
+///
+/// Cell Context
+pub fn acquire_object_for_execution(obj: *mut ConfObject) {
+    unsafe { SIM_acquire_object_for_execution(obj) };
+}
+
+#[simics_exception]
+/// Temporarily release all thread domains held by the current thread, letting other threads
+/// waiting for them run, and then reacquire them
+///
+/// # Context
+///
+/// All Contexts
+pub fn yield_held_thread_domains() {
+    unsafe { SIM_yield_thread_domains() };
+}
+
+/// Release all thread domains held by the current thread, for example before blocking on
+/// another thread which may need them. The domains are reacquired when the returned guard is
+/// dropped.
+///
+/// # Return Value
+///
+/// A guard which reacquires the thread domains when dropped
+///
+/// # Context
+///
+/// All Contexts
+pub fn drop_thread_domains() -> Result<DroppedThreadDomains> {
+    let lock = unsafe { SIM_drop_thread_domains() };
+    check_exception("drop_thread_domains")?;
+    Ok(DroppedThreadDomains { lock })
+}
+
+/// Run a closure with all thread domains held by the current thread released, reacquiring
+/// them afterward. This should be used around code which blocks on other threads, to avoid
+/// deadlocking with threads which need the held domains.
+///
+/// ```rust,ignore
+/// let result = yield_thread_domains(|| worker.join())?;
+/// ```
+///
+/// # Arguments
+///
+/// * `f` - The closure to run without holding any thread domains
+///
+/// # Return Value
+///
+/// The return value of the closure
+///
+/// # Context
+///
+/// All Contexts
+pub fn yield_thread_domains<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> R,
+{
+    let _domains = drop_thread_domains()?;
+    Ok(f())
+}
@@ -0,0 +1,375 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Conditional memory watchpoints
+//!
+//! A watchpoint is a breakpoint on a range of memory which only triggers when an access to
+//! the range satisfies a condition on the accessed value and the initiator of the access.
+//! The memory operation of each triggering access is decoded into a [`WatchpointHit`].
+//!
+//! ```rust,ignore
+//! let watchpoint = Watchpoint::builder(phys_mem)
+//!     .physical_range(0x1000, 4)
+//!     .write()
+//!     .condition(|value, _initiator| value.is_some_and(|value| value & (1 << 3) != 0))
+//!     .on_hit(|hit| println!("{:x?} -> {:x?}", hit.old_value, hit.new_value))
+//!     .stop(true)
+//!     .build()?;
+//! ```
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    break_simulation, breakpoint, cycle_count, delete_breakpoint, disable_breakpoint,
+    enable_breakpoint, get_interface,
+    sys::{cpu_endian_t, generic_transaction_t},
+    Access, BreakpointFlag, BreakpointId, BreakpointKind, ConfObject, CoreBreakpointMemopHap,
+    Cycles, Error, HapSubscription, LogicalAddress, MapTarget, MemOp, PhysicalAddress,
+    ProcessorInfoV2Interface, Result,
+};
+
+/// The largest access, in bytes, whose value is decoded for a watchpoint
+const MAX_VALUE_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An access which triggered a [`Watchpoint`]. Values are in the byte order of the
+/// initiating processor, or little endian if the access was not initiated by a processor.
+pub struct WatchpointHit {
+    /// The id of the breakpoint of the watchpoint
+    pub id: BreakpointId,
+    /// The object the breakpoint was triggered on
+    pub trigger: *mut ConfObject,
+    /// The initiator of the access, if any
+    pub initiator: Option<*mut ConfObject>,
+    /// The type of the access, one of `Sim_Access_Read`, `Sim_Access_Write` or
+    /// `Sim_Access_Execute`
+    pub access: Access,
+    /// The physical address of the access
+    pub physical_address: PhysicalAddress,
+    /// The logical address of the access
+    pub logical_address: LogicalAddress,
+    /// The size of the access in bytes
+    pub size: usize,
+    /// The value in memory before the access, if it could be read without side effects
+    pub old_value: Option<u64>,
+    /// The value written by the access, or for reads and fetches the value read, if it
+    /// could be decoded. Reads and fetches have the value of `old_value`.
+    pub new_value: Option<u64>,
+    /// The cycle the access occurred on, if the initiator or trigger object has a clock
+    pub cycle: Option<Cycles>,
+}
+
+/// A condition on the accessed value, if it could be decoded, and the initiator of an access
+type WatchpointCondition = Box<dyn FnMut(Option<u64>, Option<*mut ConfObject>) -> bool>;
+/// A callback receiving each access triggering a watchpoint
+type WatchpointCallback = Box<dyn FnMut(&WatchpointHit)>;
+
+struct WatchpointState {
+    memory: Option<MapTarget>,
+    condition: Option<WatchpointCondition>,
+    on_hit: Option<WatchpointCallback>,
+    stop: bool,
+}
+
+impl WatchpointState {
+    /// Read the value in memory at the location of an access which has not yet been performed.
+    /// The value is read with an inquiry access, so reading it has no side effects on the
+    /// memory, and is `None` if the memory does not support inquiry accesses.
+    fn read_value(&self, mop: &MemOp) -> Option<u64> {
+        let mut info = mop
+            .initiator()
+            .and_then(|cpu| get_interface::<ProcessorInfoV2Interface>(cpu).ok());
+
+        let bytes = match (&self.memory, info.as_mut()) {
+            (Some(memory), _) => memory.inquiry_read(mop.physical_address(), mop.size()),
+            // Virtual watchpoints are set on a context, so the physical memory of the
+            // initiating processor is read instead
+            (None, Some(info)) => MapTarget::new(info.get_physical_memory().ok()?, None, None)
+                .and_then(|memory| memory.inquiry_read(mop.physical_address(), mop.size())),
+            (None, None) => return None,
+        }
+        .ok()?;
+
+        let big_endian = matches!(
+            info.and_then(|mut info| info.get_endian().ok()),
+            Some(cpu_endian_t::Sim_Endian_Big)
+        );
+
+        let value = if big_endian {
+            bytes
+                .iter()
+                .fold(0, |value, byte| (value << 8) | *byte as u64)
+        } else {
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, byte| (value << 8) | *byte as u64)
+        };
+
+        Some(value)
+    }
+
+    /// Decode a memory operation which triggered the breakpoint of the watchpoint. The values
+    /// of accesses larger than 8 bytes are not decoded.
+    fn decode(&self, id: BreakpointId, trigger: *mut ConfObject, mop: &MemOp) -> WatchpointHit {
+        let access = if mop.is_instruction() {
+            Access::Sim_Access_Execute
+        } else if mop.is_write() {
+            Access::Sim_Access_Write
+        } else {
+            Access::Sim_Access_Read
+        };
+
+        // NOTE: The hap is triggered before the access is performed, so memory still holds
+        // the old value, which is also the value a read will return
+        let old_value = (mop.size() <= MAX_VALUE_SIZE)
+            .then(|| self.read_value(mop))
+            .flatten();
+
+        let new_value = if !mop.is_write() {
+            old_value
+        } else if mop.size() <= MAX_VALUE_SIZE {
+            mop.value_cpu().or_else(|_| mop.value_le()).ok()
+        } else {
+            None
+        };
+
+        WatchpointHit {
+            id,
+            trigger,
+            initiator: mop.initiator(),
+            access,
+            physical_address: mop.physical_address(),
+            logical_address: mop.logical_address(),
+            size: mop.size(),
+            old_value,
+            new_value,
+            cycle: cycle_count(mop.initiator().unwrap_or(trigger)).ok(),
+        }
+    }
+
+    fn hit(&mut self, id: BreakpointId, trigger: *mut ConfObject, mop: &MemOp) {
+        let hit = self.decode(id, trigger, mop);
+
+        if let Some(condition) = self.condition.as_mut() {
+            if !condition(hit.new_value, hit.initiator) {
+                return;
+            }
+        }
+
+        if let Some(on_hit) = self.on_hit.as_mut() {
+            on_hit(&hit);
+        }
+
+        if self.stop {
+            break_simulation(format!("Watchpoint {id} triggered")).ok();
+        }
+    }
+}
+
+/// A builder for a [`Watchpoint`]
+///
+/// ```rust,ignore
+/// let watchpoint = Watchpoint::builder(context)
+///     .virtual_range(0xffff0000, 8)
+///     .read()
+///     .write()
+///     .on_hit(|hit| println!("{hit:?}"))
+///     .build()?;
+/// ```
+pub struct WatchpointBuilder {
+    obj: *mut ConfObject,
+    kind: BreakpointKind,
+    access: Access,
+    address: u64,
+    length: u64,
+    condition: Option<WatchpointCondition>,
+    on_hit: Option<WatchpointCallback>,
+    stop: bool,
+}
+
+impl WatchpointBuilder {
+    /// Create a new builder for a watchpoint on `obj`, which must implement the breakpoint
+    /// interface. Physical watchpoints are set on a memory space, and virtual watchpoints on
+    /// a context.
+    pub fn new(obj: *mut ConfObject) -> Self {
+        Self {
+            obj,
+            kind: BreakpointKind::Sim_Break_Physical,
+            access: Access(0),
+            address: 0,
+            length: 0,
+            condition: None,
+            on_hit: None,
+            stop: false,
+        }
+    }
+
+    /// Watch `length` bytes of physical memory starting at `address`
+    pub fn physical_range(mut self, address: PhysicalAddress, length: u64) -> Self {
+        self.kind = BreakpointKind::Sim_Break_Physical;
+        self.address = address;
+        self.length = length;
+        self
+    }
+
+    /// Watch `length` bytes of virtual memory starting at `address`
+    pub fn virtual_range(mut self, address: LogicalAddress, length: u64) -> Self {
+        self.kind = BreakpointKind::Sim_Break_Virtual;
+        self.address = address;
+        self.length = length;
+        self
+    }
+
+    /// Watch reads of the range
+    pub fn read(mut self) -> Self {
+        self.access = self.access | Access::Sim_Access_Read;
+        self
+    }
+
+    /// Watch writes to the range
+    pub fn write(mut self) -> Self {
+        self.access = self.access | Access::Sim_Access_Write;
+        self
+    }
+
+    /// Watch instruction fetches from the range
+    pub fn execute(mut self) -> Self {
+        self.access = self.access | Access::Sim_Access_Execute;
+        self
+    }
+
+    /// Add types of access to watch
+    pub fn access(mut self, access: Access) -> Self {
+        self.access = self.access | access;
+        self
+    }
+
+    /// Only trigger the watchpoint for accesses where `condition` returns `true` for the
+    /// value written or read and the initiator of the access. The value is `None` if it could
+    /// not be decoded, for example for a read of memory which does not support inquiry
+    /// accesses.
+    pub fn condition<F>(mut self, condition: F) -> Self
+    where
+        F: FnMut(Option<u64>, Option<*mut ConfObject>) -> bool + 'static,
+    {
+        self.condition = Some(Box::new(condition));
+        self
+    }
+
+    /// Call `on_hit` with each access which triggers the watchpoint
+    pub fn on_hit<F>(mut self, on_hit: F) -> Self
+    where
+        F: FnMut(&WatchpointHit) + 'static,
+    {
+        self.on_hit = Some(Box::new(on_hit));
+        self
+    }
+
+    /// Stop the simulation when the watchpoint is triggered
+    pub fn stop(mut self, stop: bool) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Set the breakpoint of the watchpoint. Fails if no type of access to watch was added
+    /// with [`WatchpointBuilder::read`], [`WatchpointBuilder::write`],
+    /// [`WatchpointBuilder::execute`] or [`WatchpointBuilder::access`].
+    pub fn build(self) -> Result<Watchpoint> {
+        if self.access.0 == 0 {
+            return Err(Error::NoWatchpointAccess);
+        }
+
+        // NOTE: Physical watchpoints are set on a memory space, which is read directly, while
+        // virtual watchpoints read the physical memory of the initiating processor
+        let memory = if self.kind == BreakpointKind::Sim_Break_Physical {
+            Some(MapTarget::new(self.obj, None, None)?)
+        } else {
+            None
+        };
+
+        // NOTE: The breakpoint is a simulation breakpoint, so the simulation only stops when
+        // the condition is satisfied
+        let id = breakpoint(
+            self.obj,
+            self.kind,
+            self.access,
+            self.address,
+            self.length,
+            BreakpointFlag::Sim_Breakpoint_Simulation,
+        )?;
+
+        let mut state = WatchpointState {
+            memory,
+            condition: self.condition,
+            on_hit: self.on_hit,
+            stop: self.stop,
+        };
+
+        let subscription = CoreBreakpointMemopHap::add_callback_index(
+            move |trigger: *mut ConfObject, _: i64, memop: *mut generic_transaction_t| {
+                state.hit(id, trigger, unsafe { MemOp::from_raw(memop) });
+            },
+            id as i64,
+        )
+        .inspect_err(|_| {
+            delete_breakpoint(id).ok();
+        })?;
+
+        Ok(Watchpoint {
+            id,
+            _subscription: subscription,
+        })
+    }
+}
+
+/// A conditional watchpoint on a range of memory. The breakpoint of the watchpoint is
+/// deleted when the watchpoint is dropped.
+pub struct Watchpoint {
+    id: BreakpointId,
+    _subscription: HapSubscription,
+}
+
+impl Watchpoint {
+    /// Create a new builder for a watchpoint on `obj`
+    pub fn builder(obj: *mut ConfObject) -> WatchpointBuilder {
+        WatchpointBuilder::new(obj)
+    }
+
+    /// The id of the breakpoint of the watchpoint
+    pub fn id(&self) -> BreakpointId {
+        self.id
+    }
+
+    /// Enable the watchpoint
+    pub fn enable(&self) -> Result<()> {
+        enable_breakpoint(self.id)
+    }
+
+    /// Disable the watchpoint. A disabled watchpoint is not triggered until it is enabled
+    pub fn disable(&self) -> Result<()> {
+        disable_breakpoint(self.id)
+    }
+}
+
+impl Drop for Watchpoint {
+    fn drop(&mut self) {
+        delete_breakpoint(self.id).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchpointBuilder;
+    use crate::Error;
+    use std::ptr::null_mut;
+
+    #[test]
+    fn test_build_without_access() {
+        let result = WatchpointBuilder::new(null_mut())
+            .physical_range(0x1000, 4)
+            .build();
+
+        assert!(matches!(result, Err(Error::NoWatchpointAccess)));
+    }
+}
@@ -0,0 +1,244 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Traits for objects which are clocks, providing a time source for other objects
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    get_interface, log_error, object_descendant, object_is_configured, register_clock,
+    register_typed_attribute, simics_exception, sys::cycle_interface_t, AttrAttr, AttrValue,
+    ConfClass, ConfObject, CycleEventInterface, Cycles, Error, FrequencyChangeNotifier,
+    FromConfObject, Notifier, Result, SetErr, TypeStringType,
+};
+
+/// The port object of a clock which keeps the virtual time of the clock in cycles
+const VTIME_CYCLES: &str = "vtime.cycles";
+
+/// An object which is a clock, keeping time for the objects which use it as their queue by
+/// implementing the `cycle` interface. The virtual time of the clock is kept by the
+/// simulator in the `vtime` port objects registered for the class, and advances as the
+/// simulation runs in the cell of the clock. Events posted on the clock through the
+/// `cycle_event` interface are run by the simulator as its virtual time advances, and the
+/// `cycle` interface reports the same time, so the clock only provides its frequency.
+///
+/// ```rust,ignore
+/// impl Clock for RtcClock {
+///     fn frequency(&self) -> u64 {
+///         self.frequency
+///     }
+///
+///     fn set_frequency(&mut self, frequency: u64) -> Result<()> {
+///         self.frequency = frequency;
+///         Ok(())
+///     }
+/// }
+///
+/// #[class(name = "rtc_clock", clock)]
+/// #[derive(FromConfObject, Default)]
+/// struct RtcClock {
+///     frequency: u64,
+/// }
+///
+/// // A device using the clock as its queue posts an event one second ahead, which runs
+/// // once the clock has advanced by `frequency` cycles
+/// let rtc = get_object("rtc")?;
+/// tick.post_time(device, rtc, 1.0, |obj| println!("Tick on {obj:?}"))?;
+/// assert_eq!(cycle_count(rtc)?, clock_cycle_count(rtc)?);
+/// ```
+pub trait Clock: FromConfObject + 'static {
+    /// The frequency of the clock in Hz
+    fn frequency(&self) -> u64;
+
+    /// Set the frequency of the clock in Hz. This is called when the `frequency` or
+    /// `freq_mhz` attribute of the clock is set. Once the clock is configured, the
+    /// subscribers of its [`FrequencyChangeNotifier`] are notified after the frequency is
+    /// set.
+    fn set_frequency(&mut self, frequency: u64) -> Result<()>;
+
+    /// Register the class of this object as a clock, with the `cycle` interface and the
+    /// `frequency` and `freq_mhz` attributes
+    fn register_clock(cls: *mut ConfClass) -> Result<()> {
+        register_clock_class::<Self>(cls)
+    }
+}
+
+#[simics_exception]
+/// Get the number of cycles elapsed on a clock registered with [`register_clock_class`],
+/// from the virtual time the simulator keeps for the clock
+///
+/// # Arguments
+///
+/// * `clock` - The clock to get the cycle count of
+///
+/// # Return Value
+///
+/// The number of cycles elapsed on the clock
+///
+/// # Context
+///
+/// Cell Context
+pub fn clock_cycle_count(clock: *mut ConfObject) -> Result<Cycles> {
+    let vtime = object_descendant(clock, VTIME_CYCLES)?.ok_or_else(|| Error::ObjectNotFound {
+        name: VTIME_CYCLES.to_string(),
+    })?;
+
+    get_interface::<CycleEventInterface>(vtime)?.cycles()
+}
+
+/// The time elapsed on a clock with `cycles` cycles elapsed at `frequency` Hz, in seconds
+fn clock_time(cycles: Cycles, frequency: u64) -> f64 {
+    if frequency == 0 {
+        0.0
+    } else {
+        cycles as f64 / frequency as f64
+    }
+}
+
+/// The number of cycles from `cycles` until the time `when` in seconds on a clock running at
+/// `frequency` Hz. The result saturates at the bounds of [`Cycles`].
+fn clock_cycles_until(cycles: Cycles, frequency: u64, when: f64) -> Cycles {
+    // NOTE: Float to integer casts saturate, so only the subtraction can overflow
+    ((when * frequency as f64) as Cycles).saturating_sub(cycles)
+}
+
+/// Get the number of cycles elapsed on a clock for its `cycle` interface, which cannot
+/// report errors. If the virtual time of the clock cannot be read, an error is logged and
+/// no cycles are reported.
+fn clock_cycles_or_log(obj: *mut ConfObject) -> Cycles {
+    clock_cycle_count(obj).unwrap_or_else(|e| {
+        log_error(
+            obj,
+            format!("Failed to get the virtual time of the clock: {e}"),
+        )
+        .ok();
+        0
+    })
+}
+
+extern "C" fn clock_get_cycle_count(obj: *mut ConfObject) -> Cycles {
+    clock_cycles_or_log(obj)
+}
+
+extern "C" fn clock_get_time<T>(obj: *mut ConfObject) -> f64
+where
+    T: Clock,
+{
+    let frequency = unsafe { T::from_conf_object(obj) }.frequency();
+    clock_time(clock_cycles_or_log(obj), frequency)
+}
+
+extern "C" fn clock_cycles_delta<T>(obj: *mut ConfObject, when: f64) -> Cycles
+where
+    T: Clock,
+{
+    let frequency = unsafe { T::from_conf_object(obj) }.frequency();
+    clock_cycles_until(clock_cycles_or_log(obj), frequency, when)
+}
+
+extern "C" fn clock_get_frequency<T>(obj: *mut ConfObject) -> u64
+where
+    T: Clock,
+{
+    unsafe { T::from_conf_object(obj) }.frequency()
+}
+
+fn set_clock_frequency<T>(obj: *mut ConfObject, frequency: u64) -> Result<SetErr>
+where
+    T: Clock,
+{
+    let configured = object_is_configured(obj);
+
+    // NOTE: A frequency of zero is accepted while the object is being created or restored,
+    // because it is the frequency of a clock checkpointed before its frequency was set
+    if frequency == 0 && configured {
+        return Ok(SetErr::Sim_Set_Illegal_Value);
+    }
+
+    unsafe { T::from_conf_object_mut(obj) }.set_frequency(frequency)?;
+
+    // Objects using the clock are only told about changes after the clock is configured
+    if configured {
+        FrequencyChangeNotifier::notify(obj)?;
+    }
+
+    Ok(SetErr::Sim_Set_Ok)
+}
+
+#[simics_exception]
+/// Register a class whose objects implement [`Clock`] as a clock. This registers the
+/// `cycle` interface of the class, which reports the virtual time of the clock, the
+/// `frequency` attribute, which is saved in checkpoints, the `freq_mhz` pseudo attribute,
+/// and the `frequency-change` notifier, which is notified when the frequency is set.
+///
+/// # Arguments
+///
+/// * `cls` - The class to register as a clock
+///
+/// # Context
+///
+/// Global Context
+pub fn register_clock_class<T>(cls: *mut ConfClass) -> Result<()>
+where
+    T: Clock,
+{
+    // NOTE: The interface structure must never be freed, so it is leaked here
+    let iface = Box::into_raw(Box::new(cycle_interface_t {
+        get_cycle_count: Some(clock_get_cycle_count),
+        get_time: Some(clock_get_time::<T>),
+        cycles_delta: Some(clock_cycles_delta::<T>),
+        get_frequency: Some(clock_get_frequency::<T>),
+        ..Default::default()
+    }));
+
+    register_clock(cls, iface)?;
+    FrequencyChangeNotifier::register(cls)?;
+
+    register_typed_attribute(
+        cls,
+        "frequency",
+        Some(|o: *mut ConfObject, _: AttrValue| -> Result<AttrValue> {
+            Ok(unsafe { T::from_conf_object(o) }.frequency().into())
+        }),
+        Some(
+            |o: *mut ConfObject, v: AttrValue, _: AttrValue| -> Result<SetErr> {
+                let Ok(frequency) = u64::try_from(v) else {
+                    return Ok(SetErr::Sim_Set_Illegal_Type);
+                };
+
+                set_clock_frequency::<T>(o, frequency)
+            },
+        ),
+        AttrAttr::Sim_Attr_Optional,
+        Some(TypeStringType::Integer),
+        None,
+        "The frequency of the clock in Hz",
+    )?;
+
+    register_typed_attribute(
+        cls,
+        "freq_mhz",
+        Some(|o: *mut ConfObject, _: AttrValue| -> Result<AttrValue> {
+            Ok((unsafe { T::from_conf_object(o) }.frequency() as f64 / 1e6).into())
+        }),
+        Some(
+            |o: *mut ConfObject, v: AttrValue, _: AttrValue| -> Result<SetErr> {
+                let Ok(freq_mhz) = f64::try_from(v) else {
+                    return Ok(SetErr::Sim_Set_Illegal_Type);
+                };
+
+                if !freq_mhz.is_finite() || freq_mhz < 0.0 {
+                    return Ok(SetErr::Sim_Set_Illegal_Value);
+                }
+
+                set_clock_frequency::<T>(o, (freq_mhz * 1e6).round() as u64)
+            },
+        ),
+        AttrAttr::Sim_Attr_Pseudo,
+        Some(TypeStringType::Float),
+        None,
+        "The frequency of the clock in MHz",
+    )?;
+
+    Ok(())
+}
@@ -8,9 +8,15 @@
 use raw_cstr::AsRawCstr;
 
 use crate::{
-    sys::{SIM_hap_delete_callback_id, SIM_hap_delete_callback_obj_id},
-    ConfObject, HapHandle, Result,
+    custom_hap_occurred, custom_hap_type, register_custom_hap,
+    sys::{
+        SIM_hap_add_callback, SIM_hap_add_callback_index, SIM_hap_add_callback_obj,
+        SIM_hap_delete_callback_id, SIM_hap_delete_callback_obj_id,
+    },
+    CallbackStream, ConfObject, HapClosure, HapHandle, HapOccurrence, HapStream, HapSubscription,
+    HapType, Result,
 };
+use std::ffi::c_void;
 
 /// A SIMICS Hap and the type of callbacks associated with it
 pub trait Hap {
@@ -32,3 +38,143 @@ pub trait Hap {
         Ok(())
     }
 }
+
+/// A hap declared in Rust with `#[hap]`, whose parameters are the fields of the type
+/// implementing it
+///
+/// ```rust,ignore
+/// #[hap(name = "My_Threshold_Reached", index = "threshold")]
+/// struct ThresholdReached {
+///     value: i64,
+///     source: *mut ConfObject,
+/// }
+///
+/// ThresholdReached::register()?;
+///
+/// let subscription = ThresholdReached::add_callback(|obj, hap| {
+///     println!("{obj:?} reached {}", hap.value);
+/// })?;
+///
+/// ThresholdReached::occurred_always(Some(obj), 0, ThresholdReached { value: 10, source })?;
+/// ```
+pub trait CustomHap: Hap + Sized + 'static {
+    /// The parameter types of the hap, one character per parameter
+    const PARAMS: &'static str;
+    /// The names of the parameters of the hap, separated by spaces
+    const PARAM_DESC: &'static str;
+    /// A description of the index value of the hap, if the hap has one
+    const INDEX: Option<&'static str>;
+    /// A description of the hap
+    const DESCRIPTION: &'static str;
+
+    /// Trigger an occurrence of the hap with the fields of `hap` as its parameters. This is
+    /// implemented by `#[hap]`.
+    ///
+    /// # Safety
+    ///
+    /// `hap_type` must be the registered hap type of this hap
+    unsafe fn occurred_raw(
+        hap_type: HapType,
+        obj: *mut ConfObject,
+        index: i64,
+        hap: Self,
+        always: bool,
+    ) -> i32;
+
+    /// Get the handler for the hap which calls a [`HapClosure`] of type `F` with the
+    /// parameters of the hap. This is implemented by `#[hap]`.
+    fn handler<F>() -> unsafe extern "C" fn()
+    where
+        F: FnMut(*mut ConfObject, Self) + 'static;
+
+    /// Register the hap type. This should be called once, when the module is initialized.
+    fn register() -> Result<HapType> {
+        register_custom_hap::<Self>()
+    }
+
+    /// Get the registered hap type. This is implemented by `#[hap]`, which looks up the hap
+    /// type once and caches it.
+    fn hap_type() -> Result<HapType> {
+        custom_hap_type::<Self>()
+    }
+
+    /// Trigger an occurrence of the hap on `obj`, unless the hap has already occurred on the
+    /// object during the current cycle
+    fn occurred(obj: Option<*mut ConfObject>, index: i64, hap: Self) -> Result<i32> {
+        custom_hap_occurred(obj, index, hap, false)
+    }
+
+    /// Trigger an occurrence of the hap on `obj`
+    fn occurred_always(obj: Option<*mut ConfObject>, index: i64, hap: Self) -> Result<i32> {
+        custom_hap_occurred(obj, index, hap, true)
+    }
+
+    /// Add a callback called with the object the hap occurred on and the parameters of each
+    /// occurrence of the hap
+    fn add_callback<F>(callback: F) -> Result<HapSubscription>
+    where
+        F: FnMut(*mut ConfObject, Self) + 'static,
+    {
+        let name = Self::NAME.as_raw_cstr()?;
+        let callback_raw = HapClosure::new(callback);
+        let handle = unsafe {
+            SIM_hap_add_callback(
+                name,
+                Some(Self::handler::<F>()),
+                callback_raw as *mut c_void,
+            )
+        };
+        unsafe { HapSubscription::new::<Self, F>(handle, None, callback_raw) }
+    }
+
+    /// Add a callback called with the parameters of each occurrence of the hap on `obj`
+    fn add_callback_object<F>(callback: F, obj: *mut ConfObject) -> Result<HapSubscription>
+    where
+        F: FnMut(*mut ConfObject, Self) + 'static,
+    {
+        let name = Self::NAME.as_raw_cstr()?;
+        let callback_raw = HapClosure::new(callback);
+        let handle = unsafe {
+            SIM_hap_add_callback_obj(
+                name,
+                obj,
+                0,
+                Some(Self::handler::<F>()),
+                callback_raw as *mut c_void,
+            )
+        };
+        unsafe { HapSubscription::new::<Self, F>(handle, Some(obj), callback_raw) }
+    }
+
+    /// Add a callback called with the object the hap occurred on and the parameters of each
+    /// occurrence of the hap with the index value `index`
+    fn add_callback_index<F>(callback: F, index: i64) -> Result<HapSubscription>
+    where
+        F: FnMut(*mut ConfObject, Self) + 'static,
+    {
+        let name = Self::NAME.as_raw_cstr()?;
+        let callback_raw = HapClosure::new(callback);
+        let handle = unsafe {
+            SIM_hap_add_callback_index(
+                name,
+                Some(Self::handler::<F>()),
+                callback_raw as *mut c_void,
+                index,
+            )
+        };
+        unsafe { HapSubscription::new::<Self, F>(handle, None, callback_raw) }
+    }
+
+    /// Get a stream of the occurrences of the hap, yielding the object the hap occurred on and
+    /// the parameters of each occurrence. The callback feeding the stream is deleted when the
+    /// stream is dropped.
+    fn stream() -> Result<HapStream<(*mut ConfObject, Self)>> {
+        CallbackStream::new(|sender| Self::add_callback(move |obj, hap| sender.send((obj, hap))))
+    }
+
+    /// Get a future resolving to the object the hap occurred on and the parameters of the
+    /// next occurrence of the hap
+    fn next_occurrence() -> Result<HapOccurrence<(*mut ConfObject, Self)>> {
+        Ok(Self::stream()?.next_occurrence())
+    }
+}
@@ -3,7 +3,10 @@
 
 //! Traits for interfaces
 
-use crate::{get_interface, register_interface, ConfClass, ConfObject, Result};
+use crate::{
+    get_interface, get_port_interface, register_interface, register_port_interface, ConfClass,
+    ConfObject, Result,
+};
 use raw_cstr::AsRawCstr;
 
 /// A SIMICS interface containing a number of methods that can be called on an
@@ -38,6 +41,58 @@ pub trait Interface {
     {
         get_interface::<Self>(obj)
     }
+
+    /// Register this interface for a type on the port named `portname`
+    fn register_port<S>(cls: *mut ConfClass, portname: S) -> Result<()>
+    where
+        Self: Sized,
+        S: AsRef<str>,
+    {
+        Self::register_port_with_description(cls, portname, "")
+    }
+
+    /// Register this interface for a type on the port named `portname`, described by `desc`
+    fn register_port_with_description<S, D>(cls: *mut ConfClass, portname: S, desc: D) -> Result<()>
+    where
+        Self: Sized,
+        S: AsRef<str>,
+        D: AsRef<str>,
+    {
+        register_port_interface::<Self, _, _>(cls, portname, desc)?;
+        Ok(())
+    }
+
+    /// Get this interface on the port named `portname` of an object that implements it
+    fn get_port<S>(obj: *mut ConfObject, portname: S) -> Result<Self>
+    where
+        Self: Sized,
+        S: AsRef<str>,
+    {
+        get_port_interface::<Self, _>(obj, portname)
+    }
+}
+
+/// A built-in SIMICS interface which can be implemented by Rust objects of type `T`. This
+/// trait is implemented for each generated interface `XInterface` for every `T` which
+/// implements the generated implementer-side trait `XImpl`.
+///
+/// ```rust,ignore
+/// impl SignalImpl for MyDevice {
+///     fn signal_raise(&mut self) {
+///         self.raised = true;
+///     }
+///
+///     fn signal_lower(&mut self) {
+///         self.raised = false;
+///     }
+/// }
+///
+/// register_builtin_interface::<MyDevice, SignalInterface>(cls)?;
+/// ```
+pub trait BuiltinInterface<T>: Interface {
+    /// Create the interface structure, whose methods dispatch to the implementation of the
+    /// interface for `T`
+    fn internal_interface() -> Self::InternalInterface;
 }
 
 /// An object which has a SIMICS interface I
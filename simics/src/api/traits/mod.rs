@@ -4,11 +4,19 @@
 //! Useful traits implementable by API consumers
 
 pub mod class;
+pub mod clock;
 pub mod conf_object_traits;
 pub mod hap;
 pub mod interface;
+pub mod notifier_traits;
+pub mod register_bank;
+pub mod transaction_target;
 
 pub use class::*;
+pub use clock::*;
 pub use conf_object_traits::*;
 pub use hap::*;
 pub use interface::*;
+pub use notifier_traits::*;
+pub use register_bank::*;
+pub use transaction_target::*;
@@ -0,0 +1,141 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Traits for notifiers
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    add_notifier, class_has_notifier, has_notifier, notifier_type, notify, register_notifier,
+    ConfClass, ConfObject, NotifierSubscription, NotifierType, Result,
+};
+
+/// A SIMICS notifier type
+///
+/// ```rust,ignore
+/// struct ThresholdReached;
+///
+/// impl Notifier for ThresholdReached {
+///     const NAME: &'static str = "threshold-reached";
+///     const DESCRIPTION: &'static str = "Notified when the counter reaches its threshold";
+/// }
+///
+/// ThresholdReached::register(cls)?;
+/// let subscription = ThresholdReached::subscribe(obj, None, |obj| println!("{obj:?}"))?;
+/// ThresholdReached::notify(obj)?;
+/// ```
+pub trait Notifier {
+    /// The name of the notifier type
+    const NAME: &'static str;
+    /// A description of when the notifier is notified
+    const DESCRIPTION: &'static str;
+
+    /// Get the type id of this notifier, creating the notifier type if it does not exist
+    fn notifier_type() -> Result<NotifierType> {
+        notifier_type(Self::NAME)
+    }
+
+    /// Register that objects of a class notify this notifier
+    fn register(cls: *mut ConfClass) -> Result<()> {
+        register_notifier(cls, Self::notifier_type()?, Self::DESCRIPTION)
+    }
+
+    /// Notify the subscribers of this notifier of an object
+    fn notify(obj: *mut ConfObject) -> Result<()> {
+        notify(obj, Self::notifier_type()?)
+    }
+
+    /// Subscribe to this notifier of an object. The subscription is removed when the returned
+    /// subscription is dropped.
+    fn subscribe<F>(
+        obj: *mut ConfObject,
+        subscriber: Option<*mut ConfObject>,
+        callback: F,
+    ) -> Result<NotifierSubscription>
+    where
+        F: FnMut(*mut ConfObject) + 'static,
+    {
+        add_notifier(obj, Self::notifier_type()?, subscriber, callback)
+    }
+
+    /// Check whether an object notifies this notifier
+    fn is_registered(obj: *mut ConfObject) -> Result<bool> {
+        has_notifier(obj, Self::notifier_type()?)
+    }
+
+    /// Check whether objects of a class notify this notifier
+    fn is_registered_class(cls: *mut ConfClass) -> Result<bool> {
+        class_has_notifier(cls, Self::notifier_type()?)
+    }
+}
+
+macro_rules! builtin_notifier {
+    ($description:literal, $name:ident, $notifier_name:literal, $notifier_type:ident) => {
+        #[doc = $description]
+        pub struct $name;
+
+        impl Notifier for $name {
+            const NAME: &'static str = $notifier_name;
+            const DESCRIPTION: &'static str = $description;
+
+            fn notifier_type() -> Result<NotifierType> {
+                Ok(NotifierType::$notifier_type)
+            }
+        }
+    };
+}
+
+builtin_notifier!(
+    "Notified when the event queue of a clock changes",
+    QueueChangeNotifier,
+    "queue-change",
+    Sim_Notify_Queue_Change
+);
+builtin_notifier!(
+    "Notified when an object moves to a different cell",
+    CellChangeNotifier,
+    "cell-change",
+    Sim_Notify_Cell_Change
+);
+builtin_notifier!(
+    "Notified when the frequency of a clock changes",
+    FrequencyChangeNotifier,
+    "frequency-change",
+    Sim_Notify_Frequency_Change
+);
+builtin_notifier!(
+    "Notified when the threading mode or concurrency of the simulation changes",
+    ConcurrencyChangeNotifier,
+    "concurrency-change",
+    Sim_Notify_Concurrency_Change
+);
+builtin_notifier!(
+    "Notified when an object is about to be deleted",
+    ObjectDeleteNotifier,
+    "object-delete",
+    Sim_Notify_Object_Delete
+);
+builtin_notifier!(
+    "Notified when the mappings of a memory space or translator change",
+    MapChangeNotifier,
+    "map-change",
+    Sim_Notify_Map_Change
+);
+builtin_notifier!(
+    "Notified when the state of an object changes",
+    StateChangeNotifier,
+    "state-change",
+    Sim_Notify_State_Change
+);
+builtin_notifier!(
+    "Notified when a processor enters or leaves freerunning mode",
+    FreerunningModeChangeNotifier,
+    "freerunning-mode-change",
+    Sim_Notify_Freerunning_Mode_Change
+);
+builtin_notifier!(
+    "Notified when the value of a register in a bank changes",
+    BankRegisterValueChangeNotifier,
+    "bank-register-value-change",
+    Sim_Notify_Bank_Register_Value_Change
+);
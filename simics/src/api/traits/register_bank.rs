@@ -0,0 +1,610 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Traits for register banks
+//!
+//! Register banks are usually declared with the `#[bank]` attribute macro, which generates
+//! the register storage and an implementation of [`RegisterBank`] for a class.
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    register_typed_attribute, simics_exception,
+    sys::{
+        after_read_callback_t, after_write_callback_t, bank_instrumentation_subscribe_interface_t,
+        before_read_callback_t, before_write_callback_t, SIM_register_interface,
+    },
+    AttrAttr, AttrValue, BankAccess, BankCallbackHandle, BankInstrumentation,
+    BankInstrumentationSubscribeInterface, ConfClass, ConfObject, Error, ExceptionType,
+    FromConfObject, Interface, Result, SetErr, Transaction, TransactionTarget, TypeStringType,
+};
+use raw_cstr::AsRawCstr;
+use std::ffi::c_void;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How software may access the bits of a register field
+pub enum FieldAccess {
+    /// Readable and writable
+    ReadWrite,
+    /// Readable, writes are ignored
+    ReadOnly,
+    /// Writable, reads return zero
+    WriteOnly,
+    /// Readable, writing a one clears the bit
+    WriteOneToClear,
+    /// Readable, writing a one sets the bit
+    WriteOneToSet,
+    /// Readable, reading clears the field and writes are ignored
+    ReadToClear,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A field of a register, spanning the bits `lsb..=msb`
+pub struct FieldInfo {
+    /// The name of the field
+    pub name: &'static str,
+    /// The least significant bit of the field
+    pub lsb: u32,
+    /// The most significant bit of the field
+    pub msb: u32,
+    /// How the field may be accessed
+    pub access: FieldAccess,
+}
+
+impl FieldInfo {
+    /// The mask of the bits of the field in the register
+    pub const fn mask(&self) -> u64 {
+        (u64::MAX >> (63 - (self.msb - self.lsb))) << self.lsb
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A register in a register bank
+pub struct RegisterInfo {
+    /// The name of the register, which is also the name of its attribute
+    pub name: &'static str,
+    /// The description of the register
+    pub description: &'static str,
+    /// The offset of the register in the bank
+    pub offset: u64,
+    /// The size of the register in bytes, at most 8
+    pub size: u64,
+    /// The value of the register after reset
+    pub reset: u64,
+    /// The fields of the register. A register without fields is readable and writable as a
+    /// whole.
+    pub fields: &'static [FieldInfo],
+}
+
+impl RegisterInfo {
+    /// The mask of all bits of the register
+    pub const fn mask(&self) -> u64 {
+        u64::MAX >> (64 - self.size * 8)
+    }
+
+    /// Whether an access of `size` bytes at `offset` falls entirely within this register
+    pub fn contains(&self, offset: u64, size: u64) -> bool {
+        offset >= self.offset && offset.saturating_add(size) <= self.offset + self.size
+    }
+
+    /// The bits of the register which are visible to reads
+    pub fn read_mask(&self) -> u64 {
+        if self.fields.is_empty() {
+            self.mask()
+        } else {
+            self.fields
+                .iter()
+                .filter(|f| f.access != FieldAccess::WriteOnly)
+                .fold(0, |mask, f| mask | f.mask())
+        }
+    }
+
+    /// The value of the register after reading the bits `read` of `value` from it. Only the
+    /// bits of read-to-clear fields which were read are cleared.
+    pub fn after_read(&self, value: u64, read: u64) -> u64 {
+        self.fields
+            .iter()
+            .filter(|f| f.access == FieldAccess::ReadToClear)
+            .fold(value, |value, f| value & !(f.mask() & read))
+    }
+
+    /// The value of the register after writing the bits `written` of `value` to it, when its
+    /// previous value is `old`. Bits not covered by any field keep their previous value.
+    pub fn after_write(&self, old: u64, value: u64, written: u64) -> u64 {
+        if self.fields.is_empty() {
+            return (old & !written) | (value & written);
+        }
+
+        self.fields.iter().fold(old, |new, f| {
+            let mask = f.mask() & written;
+            match f.access {
+                FieldAccess::ReadWrite | FieldAccess::WriteOnly => (new & !mask) | (value & mask),
+                FieldAccess::ReadOnly | FieldAccess::ReadToClear => new,
+                FieldAccess::WriteOneToClear => new & !(value & mask),
+                FieldAccess::WriteOneToSet => new | (value & mask),
+            }
+        })
+    }
+}
+
+/// The mask of the bits written by an access of `size` bytes
+fn access_mask(size: u64) -> u64 {
+    u64::MAX >> (64 - size.min(8) * 8)
+}
+
+/// A little-endian bank of memory mapped registers. Accesses must fall entirely within one
+/// register; other accesses miss the bank.
+///
+/// This trait is implemented by the `#[bank]` attribute macro:
+///
+/// ```rust,ignore
+/// #[class(name = "my_device")]
+/// #[bank]
+/// #[derive(FromConfObject)]
+/// struct MyDevice {
+///     #[register(offset = 0x0, size = 4, reset = 0x1, on_write = "control_written")]
+///     #[field(name = "enable", bits = 0..=0, access = "rw")]
+///     #[field(name = "error", bits = 3..=5, access = "rw1c")]
+///     control: u32,
+/// }
+///
+/// #[simics_init(name = "my-device", class = "my_device")]
+/// fn init() {
+///     let cls = MyDevice::create().expect("Failed to create class");
+///     MyDevice::register_bank(cls).expect("Failed to register bank");
+/// }
+/// ```
+pub trait RegisterBank: TransactionTarget {
+    /// The registers of the bank
+    const REGISTERS: &'static [RegisterInfo];
+
+    /// Get the stored value of the register at `index` in [`RegisterBank::REGISTERS`]
+    fn get_register(&self, index: usize) -> u64;
+
+    /// Set the stored value of the register at `index` in [`RegisterBank::REGISTERS`]
+    fn set_register(&mut self, index: usize, value: u64);
+
+    /// Get the instrumentation callbacks subscribed to the bank
+    fn instrumentation(&mut self) -> &mut BankInstrumentation;
+
+    /// Called when the register at `index` is read with its stored value, returning the
+    /// value to read. Not called for inquiry accesses.
+    fn on_read(&mut self, _index: usize, value: u64) -> u64 {
+        value
+    }
+
+    /// Called after the register at `index` is written and its value changed from `old` to
+    /// `new`. Not called for inquiry accesses.
+    fn on_write(&mut self, _index: usize, _old: u64, _new: u64) {}
+
+    /// Find the register an access of `size` bytes at `offset` falls within
+    fn find_register(offset: u64, size: u64) -> Option<usize> {
+        Self::REGISTERS
+            .iter()
+            .position(|r| r.contains(offset, size))
+    }
+
+    /// Set all registers to their reset values
+    fn reset_registers(&mut self) {
+        Self::REGISTERS
+            .iter()
+            .enumerate()
+            .for_each(|(index, register)| self.set_register(index, register.reset));
+    }
+
+    /// Read `size` bytes at `offset` in the bank, returning `None` if the access misses
+    fn read_bank(&mut self, offset: u64, size: u64, inquiry: bool) -> Option<u64> {
+        let index = Self::find_register(offset, size)?;
+        let register = &Self::REGISTERS[index];
+        let shift = (offset - register.offset) * 8;
+        let stored = self.get_register(index);
+
+        let value = if inquiry {
+            stored
+        } else {
+            let value = self.on_read(index, stored);
+            let after = register.after_read(self.get_register(index), access_mask(size) << shift);
+            self.set_register(index, after);
+            value & register.read_mask()
+        };
+
+        Some((value >> shift) & access_mask(size))
+    }
+
+    /// Write `size` bytes of `value` at `offset` in the bank, returning `false` if the access
+    /// misses. Inquiry writes set the written bits directly, ignoring field access rules.
+    fn write_bank(&mut self, offset: u64, size: u64, value: u64, inquiry: bool) -> bool {
+        let Some(index) = Self::find_register(offset, size) else {
+            return false;
+        };
+
+        let register = &Self::REGISTERS[index];
+        let shift = (offset - register.offset) * 8;
+        let written = access_mask(size) << shift;
+        let value = value << shift;
+        let old = self.get_register(index);
+
+        if inquiry {
+            self.set_register(index, (old & !written) | (value & written));
+        } else {
+            let new = register.after_write(old, value, written);
+            self.set_register(index, new);
+            self.on_write(index, old, new);
+        }
+
+        true
+    }
+
+    /// Perform a transaction at `offset` in the bank, calling the subscribed instrumentation
+    /// callbacks. This is the implementation of the `transaction` interface of the bank.
+    fn access(&mut self, t: &mut Transaction, offset: u64) -> ExceptionType {
+        let (Ok(size), Ok(is_write), Ok(inquiry)) = (t.size(), t.is_write(), t.is_inquiry()) else {
+            return ExceptionType::Sim_PE_IO_Error;
+        };
+
+        if size == 0 || size > 8 {
+            return ExceptionType::Sim_PE_IO_Not_Taken;
+        }
+
+        let initiator = t.initiator().ok().flatten();
+
+        let access = if is_write {
+            let Ok(value) = t.value_le() else {
+                return ExceptionType::Sim_PE_IO_Error;
+            };
+
+            let mut access = BankAccess::new(offset, size as u64, value, initiator, inquiry);
+            self.instrumentation().before_write(&mut access);
+
+            if !access.suppressed() {
+                let hit = self.write_bank(
+                    access.offset(),
+                    access.size(),
+                    access.value(),
+                    access.is_inquiry(),
+                );
+                access.set_missed(!hit);
+            }
+
+            self.instrumentation().after_write(&mut access);
+            access
+        } else {
+            let mut access = BankAccess::new(offset, size as u64, 0, initiator, inquiry);
+            self.instrumentation().before_read(&mut access);
+
+            match self.read_bank(access.offset(), access.size(), access.is_inquiry()) {
+                Some(value) => access.set_value(value),
+                None => access.set_missed(true),
+            }
+
+            self.instrumentation().after_read(&mut access);
+            access
+        };
+
+        if access.missed() {
+            ExceptionType::Sim_PE_IO_Not_Taken
+        } else if !is_write && t.set_value_le(access.value()).is_err() {
+            ExceptionType::Sim_PE_IO_Error
+        } else {
+            ExceptionType::Sim_PE_No_Exception
+        }
+    }
+
+    /// Register the `transaction` and `bank_instrumentation_subscribe` interfaces and an
+    /// attribute for each register for the class of this bank
+    fn register_bank(cls: *mut ConfClass) -> Result<()> {
+        Self::register_transaction_target(cls)?;
+        register_bank_instrumentation::<Self>(cls)?;
+        register_bank_attributes::<Self>(cls)
+    }
+}
+
+#[simics_exception]
+/// Register a checkpointed integer attribute for each register of a bank
+///
+/// # Arguments
+///
+/// * `cls` - The class of the bank
+///
+/// # Context
+///
+/// Global Context
+pub fn register_bank_attributes<T>(cls: *mut ConfClass) -> Result<()>
+where
+    T: RegisterBank,
+{
+    T::REGISTERS
+        .iter()
+        .enumerate()
+        .try_for_each(|(index, register)| {
+            let description = if register.description.is_empty() {
+                register.name
+            } else {
+                register.description
+            };
+
+            register_typed_attribute(
+                cls,
+                register.name,
+                Some(
+                    move |o: *mut ConfObject, _: AttrValue| -> Result<AttrValue> {
+                        let bank = unsafe { T::from_conf_object(o) };
+                        Ok(bank.get_register(index).into())
+                    },
+                ),
+                Some(
+                    move |o: *mut ConfObject, v: AttrValue, _: AttrValue| -> Result<SetErr> {
+                        let Ok(value) = u64::try_from(v) else {
+                            return Ok(SetErr::Sim_Set_Illegal_Type);
+                        };
+
+                        if value & !register.mask() != 0 {
+                            return Ok(SetErr::Sim_Set_Illegal_Value);
+                        }
+
+                        let bank = unsafe { T::from_conf_object_mut(o) };
+                        bank.set_register(index, value);
+
+                        Ok(SetErr::Sim_Set_Ok)
+                    },
+                ),
+                AttrAttr::Sim_Attr_Optional,
+                Some(TypeStringType::Integer),
+                None,
+                description,
+            )
+        })
+}
+
+extern "C" fn bank_register_before_read<T>(
+    bank: *mut ConfObject,
+    connection: *mut ConfObject,
+    offset: u64,
+    size: u64,
+    before_read: before_read_callback_t,
+    user_data: *mut c_void,
+) -> BankCallbackHandle
+where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .register_before_read(connection, offset, size, before_read, user_data)
+}
+
+extern "C" fn bank_register_after_read<T>(
+    bank: *mut ConfObject,
+    connection: *mut ConfObject,
+    offset: u64,
+    size: u64,
+    after_read: after_read_callback_t,
+    user_data: *mut c_void,
+) -> BankCallbackHandle
+where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .register_after_read(connection, offset, size, after_read, user_data)
+}
+
+extern "C" fn bank_register_before_write<T>(
+    bank: *mut ConfObject,
+    connection: *mut ConfObject,
+    offset: u64,
+    size: u64,
+    before_write: before_write_callback_t,
+    user_data: *mut c_void,
+) -> BankCallbackHandle
+where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .register_before_write(connection, offset, size, before_write, user_data)
+}
+
+extern "C" fn bank_register_after_write<T>(
+    bank: *mut ConfObject,
+    connection: *mut ConfObject,
+    offset: u64,
+    size: u64,
+    after_write: after_write_callback_t,
+    user_data: *mut c_void,
+) -> BankCallbackHandle
+where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .register_after_write(connection, offset, size, after_write, user_data)
+}
+
+extern "C" fn bank_remove_callback<T>(bank: *mut ConfObject, callback: BankCallbackHandle)
+where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .remove_callback(callback)
+}
+
+extern "C" fn bank_remove_connection_callbacks<T>(
+    bank: *mut ConfObject,
+    connection: *const ConfObject,
+) where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .remove_connection_callbacks(connection)
+}
+
+extern "C" fn bank_enable_connection_callbacks<T>(
+    bank: *mut ConfObject,
+    connection: *const ConfObject,
+) where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .enable_connection_callbacks(connection)
+}
+
+extern "C" fn bank_disable_connection_callbacks<T>(
+    bank: *mut ConfObject,
+    connection: *const ConfObject,
+) where
+    T: RegisterBank,
+{
+    unsafe { T::from_conf_object_mut(bank) }
+        .instrumentation()
+        .disable_connection_callbacks(connection)
+}
+
+#[simics_exception]
+/// Register the `bank_instrumentation_subscribe` interface for a class whose objects
+/// implement [`RegisterBank`]
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the interface for
+///
+/// # Context
+///
+/// Global Context
+pub fn register_bank_instrumentation<T>(cls: *mut ConfClass) -> Result<()>
+where
+    T: RegisterBank,
+{
+    // NOTE: The interface structure must never be freed, so it is leaked here
+    let iface = Box::into_raw(Box::new(bank_instrumentation_subscribe_interface_t {
+        register_before_read: Some(bank_register_before_read::<T>),
+        register_after_read: Some(bank_register_after_read::<T>),
+        register_before_write: Some(bank_register_before_write::<T>),
+        register_after_write: Some(bank_register_after_write::<T>),
+        remove_callback: Some(bank_remove_callback::<T>),
+        remove_connection_callbacks: Some(bank_remove_connection_callbacks::<T>),
+        enable_connection_callbacks: Some(bank_enable_connection_callbacks::<T>),
+        disable_connection_callbacks: Some(bank_disable_connection_callbacks::<T>),
+    }));
+
+    if unsafe {
+        SIM_register_interface(
+            cls,
+            BankInstrumentationSubscribeInterface::NAME.as_raw_cstr()?,
+            iface as *mut _,
+        )
+    } != 0
+    {
+        return Err(Error::RegisterInterface {
+            name: "bank_instrumentation_subscribe".to_string(),
+            message: crate::last_error(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FieldAccess, FieldInfo, RegisterInfo};
+
+    const fn field(name: &'static str, lsb: u32, msb: u32, access: FieldAccess) -> FieldInfo {
+        FieldInfo {
+            name,
+            lsb,
+            msb,
+            access,
+        }
+    }
+
+    const fn register(size: u64, fields: &'static [FieldInfo]) -> RegisterInfo {
+        RegisterInfo {
+            name: "r",
+            description: "",
+            offset: 0,
+            size,
+            reset: 0,
+            fields,
+        }
+    }
+
+    const FIELDS: &[FieldInfo] = &[
+        field("rw", 0, 3, FieldAccess::ReadWrite),
+        field("ro", 4, 7, FieldAccess::ReadOnly),
+        field("wo", 8, 11, FieldAccess::WriteOnly),
+        field("w1c", 12, 15, FieldAccess::WriteOneToClear),
+        field("w1s", 16, 19, FieldAccess::WriteOneToSet),
+        field("rc", 20, 23, FieldAccess::ReadToClear),
+    ];
+
+    #[test]
+    fn test_field_mask() {
+        assert_eq!(field("f", 0, 0, FieldAccess::ReadWrite).mask(), 0x1);
+        assert_eq!(field("f", 3, 5, FieldAccess::ReadWrite).mask(), 0x38);
+        assert_eq!(
+            field("f", 31, 31, FieldAccess::ReadWrite).mask(),
+            0x8000_0000
+        );
+        assert_eq!(field("f", 0, 63, FieldAccess::ReadWrite).mask(), u64::MAX);
+        assert_eq!(field("f", 63, 63, FieldAccess::ReadWrite).mask(), 1 << 63);
+    }
+
+    #[test]
+    fn test_register_mask() {
+        assert_eq!(register(1, &[]).mask(), 0xff);
+        assert_eq!(register(4, &[]).mask(), 0xffff_ffff);
+        assert_eq!(register(8, &[]).mask(), u64::MAX);
+        assert_eq!(register(4, FIELDS).read_mask(), 0xff_f0ff);
+    }
+
+    #[test]
+    fn test_after_read() {
+        assert_eq!(
+            register(4, &[]).after_read(0xffff_ffff, 0xffff_ffff),
+            0xffff_ffff
+        );
+        assert_eq!(
+            register(4, FIELDS).after_read(0xff_ffff, 0xffff_ffff),
+            0x0f_ffff
+        );
+    }
+
+    #[test]
+    fn test_after_partial_read() {
+        let r = register(4, FIELDS);
+
+        // Reading the low byte does not clear the read-to-clear field in byte 2
+        assert_eq!(r.after_read(0xff_ffff, 0xff), 0xff_ffff);
+        // Reading byte 2 clears the read-to-clear field
+        assert_eq!(r.after_read(0xff_ffff, 0xff_0000), 0x0f_ffff);
+        // Reading only the low bits of the field clears only those bits
+        assert_eq!(r.after_read(0xff_ffff, 0x3f_ffff), 0xcf_ffff);
+    }
+
+    #[test]
+    fn test_after_write_without_fields() {
+        let r = register(4, &[]);
+
+        assert_eq!(
+            r.after_write(0x1234_5678, 0xaaaa_aaaa, 0xffff_ffff),
+            0xaaaa_aaaa
+        );
+        assert_eq!(
+            r.after_write(0x1234_5678, 0xaaaa_aaaa, 0x0000_ff00),
+            0x1234_aa78
+        );
+    }
+
+    #[test]
+    fn test_after_write_fields() {
+        let r = register(4, FIELDS);
+        let old = 0x55_5555;
+
+        assert_eq!(r.after_write(old, 0xff_ffff, 0xffff_ffff), 0x5f_0f5f);
+        assert_eq!(r.after_write(old, 0, 0xffff_ffff), 0x55_5050);
+        // Bits outside the written bytes and outside any field are unchanged
+        assert_eq!(r.after_write(0xff00_0000 | old, 0xffff, 0xff), 0xff55_555f);
+    }
+}
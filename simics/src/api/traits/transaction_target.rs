@@ -0,0 +1,94 @@
+// Copyright (C) 2024 Intel Corporation
+// SPDX-License-Identifier: Apache-2.0
+
+//! Traits for objects which are the target of memory transactions
+
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::{
+    simics_exception,
+    sys::{transaction_interface_t, transaction_t, SIM_register_interface},
+    ConfClass, ConfObject, Error, ExceptionType, FromConfObject, Interface, Result, Transaction,
+    TransactionInterface,
+};
+use raw_cstr::AsRawCstr;
+
+/// An object which handles memory transactions issued to it by implementing the
+/// `transaction` interface. This is the interface implemented by memory mapped devices.
+///
+/// ```rust,ignore
+/// impl TransactionTarget for MyDevice {
+///     fn issue(&mut self, t: &mut Transaction, addr: u64) -> ExceptionType {
+///         if t.is_read().unwrap_or(false) {
+///             let _ = t.set_value_le(self.registers[addr as usize / 4]);
+///         }
+///         ExceptionType::Sim_PE_No_Exception
+///     }
+/// }
+///
+/// #[simics_init(name = "my-device", class = "my_device")]
+/// fn init() {
+///     let cls = MyDevice::create().expect("Failed to create class");
+///     MyDevice::register_transaction_target(cls).expect("Failed to register interface");
+/// }
+/// ```
+pub trait TransactionTarget: FromConfObject + 'static {
+    /// Handle a transaction issued to the object at the address `addr`, relative to where
+    /// the object is mapped. Return `Sim_PE_No_Exception` on success, or
+    /// `Sim_PE_Deferred` if the transaction was deferred with [`Transaction::defer`].
+    fn issue(&mut self, t: &mut Transaction, addr: u64) -> ExceptionType;
+
+    /// Register the `transaction` interface for the class of this object
+    fn register_transaction_target(cls: *mut ConfClass) -> Result<()> {
+        register_transaction_target::<Self>(cls)
+    }
+}
+
+extern "C" fn transaction_target_issue<T>(
+    obj: *mut ConfObject,
+    t: *mut transaction_t,
+    addr: u64,
+) -> ExceptionType
+where
+    T: TransactionTarget,
+{
+    let target = unsafe { T::from_conf_object_mut(obj) };
+    target.issue(unsafe { Transaction::from_raw_mut(t) }, addr)
+}
+
+#[simics_exception]
+/// Register the `transaction` interface for a class whose objects implement
+/// [`TransactionTarget`]
+///
+/// # Arguments
+///
+/// * `cls` - The class to register the interface for
+///
+/// # Context
+///
+/// Global Context
+pub fn register_transaction_target<T>(cls: *mut ConfClass) -> Result<()>
+where
+    T: TransactionTarget,
+{
+    // NOTE: The interface structure must never be freed, so it is leaked here
+    let iface = Box::into_raw(Box::new(transaction_interface_t {
+        issue: Some(transaction_target_issue::<T>),
+    }));
+
+    if unsafe {
+        SIM_register_interface(
+            cls,
+            TransactionInterface::NAME.as_raw_cstr()?,
+            iface as *mut _,
+        )
+    } != 0
+    {
+        return Err(Error::RegisterInterface {
+            name: "transaction".to_string(),
+            message: crate::last_error(),
+        });
+    }
+
+    Ok(())
+}
@@ -186,6 +186,12 @@ pub enum Error {
     #[error("No matching event found")]
     /// An event matching a query was not found
     NoEventFound,
+    #[error("No register {name} found")]
+    /// A processor did not have a register with a given name
+    NoRegister {
+        /// The name of the missing register
+        name: String,
+    },
     #[error("No method {method} found on interface")]
     /// An interface did not have a given method
     NoInterfaceMethod {
@@ -215,6 +221,80 @@ pub enum Error {
         /// The actual size
         actual: usize,
     },
+    #[error("Transaction size {actual} does not match expected size {expected}")]
+    /// A transaction was accessed with a value or buffer of the wrong size
+    TransactionSizeMismatch {
+        /// The expected size
+        expected: usize,
+        /// The actual size
+        actual: usize,
+    },
+    #[error("Range of {len} bytes at offset {offset} is outside transaction of size {size}")]
+    /// A transaction was accessed outside of its data
+    TransactionRange {
+        /// The offset of the access
+        offset: usize,
+        /// The length of the access
+        len: usize,
+        /// The size of the transaction
+        size: usize,
+    },
+    #[error("Transaction has no atom of type {name}")]
+    /// A transaction did not contain an atom of a given type
+    AtomNotFound {
+        /// The name of the atom type
+        name: String,
+    },
+    #[error("Transaction cannot be deferred")]
+    /// A transaction could not be deferred because it has no completion atom
+    TransactionNotDeferrable,
+    #[error("Transaction is still deferred")]
+    /// A transaction was accessed in a way which requires it to have completed while it was
+    /// still deferred
+    TransactionDeferred,
+    #[error("Transaction has not been issued")]
+    /// A transaction was polled for completion before it was issued
+    TransactionNotIssued,
+    #[error("Could not create map target")]
+    /// A map target could not be created for an object
+    CreateMapTarget,
+    #[error("Transaction failed with {exception:?}")]
+    /// A transaction completed with an error status
+    TransactionFailed {
+        /// The status the transaction completed with
+        exception: crate::ExceptionType,
+    },
+    #[error("Failed to register hap type: {message}")]
+    /// A hap type could not be registered, for example because a hap type with the same name
+    /// already exists
+    RegisterHap {
+        /// The error message
+        message: String,
+    },
+    #[error("Failed to add hap callback: {message}")]
+    /// A callback could not be added to a hap
+    AddHapCallback {
+        /// The error message
+        message: String,
+    },
+    #[error("Hap type {name} is not registered")]
+    /// A hap type was looked up which has not been registered
+    HapNotFound {
+        /// The name of the hap type
+        name: String,
+    },
+    #[error("Failed to add notifier: {message}")]
+    /// A notifier subscription could not be added to an object
+    AddNotifier {
+        /// The error message
+        message: String,
+    },
+    #[error("Object is not a port object")]
+    /// An object was expected to be a port object of another object but is not
+    NotPortObject,
+    #[error("Watchpoint does not watch any type of access")]
+    /// A watchpoint was built without reads, writes or instruction fetches to watch
+    NoWatchpointAccess,
     #[error("{path:?} is not a directory")]
     /// A path that should have been a directory was not
     NotADirectory {
@@ -252,6 +332,10 @@ pub enum Error {
     #[error(transparent)]
     /// A wrapped std::path::StripPrefixError
     RegexError(#[from] regex::Error),
+    #[cfg(feature = "global-message")]
+    #[error(transparent)]
+    /// A wrapped serde_json::Error
+    JsonError(#[from] serde_json::Error),
     // Anyhow error type to allow wrapping any other errors (e.g. from other crates in the
     // workspace)
     #[error(transparent)]